@@ -0,0 +1,85 @@
+use std::f32::consts::PI;
+
+/// An in-place radix-2 FFT of a fixed size, with its twiddle factors and window precomputed
+pub(crate) struct Fft {
+    size: usize,
+    /// (cos, sin) of -2πk/size for k in 0..size/2
+    twiddles: Vec<(f32, f32)>,
+    window: Vec<f32>,
+}
+
+impl Fft {
+    /// `size` must be a power of two
+    pub(crate) fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "FFT size must be a power of two");
+        Self {
+            size,
+            twiddles: (0..size / 2)
+                .map(|k| {
+                    let angle = -2.0 * PI * k as f32 / size as f32;
+                    (angle.cos(), angle.sin())
+                })
+                .collect(),
+            // Hann window, stops the edges of each frame smearing energy across the spectrum
+            window: (0..size)
+                .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / size as f32).cos())
+                .collect(),
+        }
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Adds the power of each frequency bin in `frame` (windowed) to `power`.
+    /// Only the first `size / 2` bins are meaningful for real input.
+    pub(crate) fn accumulate_power(&self, frame: &[f32], power: &mut [f64]) {
+        let mut re: Vec<f32> = frame
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| sample * window)
+            .collect();
+        let mut im = vec![0f32; self.size];
+        self.transform(&mut re, &mut im);
+        for (bin, power) in power.iter_mut().enumerate().take(self.size / 2) {
+            *power += (re[bin] * re[bin] + im[bin] * im[bin]) as f64;
+        }
+    }
+
+    fn transform(&self, re: &mut [f32], im: &mut [f32]) {
+        let n = self.size;
+
+        // bit reversal permutation
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+
+        let mut length = 2;
+        while length <= n {
+            let step = n / length;
+            for start in (0..n).step_by(length) {
+                for k in 0..length / 2 {
+                    let (cos, sin) = self.twiddles[k * step];
+                    let (a, b) = (start + k, start + k + length / 2);
+                    let t_re = re[b] * cos - im[b] * sin;
+                    let t_im = re[b] * sin + im[b] * cos;
+                    re[b] = re[a] - t_re;
+                    im[b] = im[a] - t_im;
+                    re[a] += t_re;
+                    im[a] += t_im;
+                }
+            }
+            length <<= 1;
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::{io::Result, path::Path};
+
+use self::{fft::Fft, pcm::read_sample};
+
+pub(crate) mod fft;
+pub(crate) mod pcm;
+
+const FFT_SIZE: usize = 4096;
+/// Frames quieter than this (RMS) are skipped, silence says nothing about the source
+const SILENCE_RMS: f32 = 1e-4;
+/// Fewer loud frames than this isn't enough to go on
+const MIN_FRAMES: usize = 16;
+/// The spectrum is averaged into bands this wide (Hz) to smooth it out
+const BAND_HZ: f64 = 250.0;
+/// How many bands either side of a possible cutoff get compared
+const CLIFF_BANDS: usize = 4;
+/// How far (dB) the level has to fall across a cutoff for it to count
+const CLIFF_DB: f64 = 30.0;
+/// Lossy encoders don't low-pass below this (Hz), so don't look for cutoffs there
+const MIN_CUTOFF_HZ: f64 = 10_000.0;
+/// Cutoffs above this fraction of the Nyquist frequency are just the anti-aliasing
+/// filter every recording has
+const NYQUIST_FRACTION: f64 = 0.95;
+
+/// What the spectrum says about where a lossless file came from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Verdict {
+    /// Nothing suspicious, the spectrum reaches the Nyquist frequency
+    Genuine,
+    /// The spectrum stops dead at `cutoff` Hz, well below the Nyquist frequency.
+    /// Usually a lossy source, or an upsampled one.
+    Suspicious { cutoff: u32 },
+    /// Too short or too quiet to tell
+    Inconclusive,
+}
+
+impl Verdict {
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Verdict::Genuine => String::from("Genuine"),
+            Verdict::Suspicious { cutoff } => {
+                format!("Suspicious ({:.1} kHz cutoff)", *cutoff as f64 / 1000.0)
+            }
+            Verdict::Inconclusive => String::from("Inconclusive"),
+        }
+    }
+
+    /// (verdict, cutoff) as they're stored in the index
+    pub(crate) fn to_parts(self) -> (u8, Option<u32>) {
+        match self {
+            Verdict::Genuine => (0, None),
+            Verdict::Suspicious { cutoff } => (1, Some(cutoff)),
+            Verdict::Inconclusive => (2, None),
+        }
+    }
+}
+
+/// Whether `analyse` supports the file
+pub(crate) fn can_analyse(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| matches!(e.as_str(), "flac" | "wav" | "aiff" | "aifc" | "aif"))
+}
+
+/// Decodes part of a lossless file and looks for the sharp high frequency cutoff lossy encoders leave.
+/// This is CPU heavy, so shouldn't be run on an async task.
+pub(crate) fn analyse(path: &Path) -> Result<Verdict> {
+    let pcm =
+        read_sample(path).unwrap_or_else(|| Err(std::io::Error::other("Unsupported file type")))?;
+    Ok(judge_spectrum(pcm.sample_rate, &pcm.samples))
+}
+
+fn judge_spectrum(sample_rate: u32, samples: &[f32]) -> Verdict {
+    let fft = Fft::new(FFT_SIZE);
+    let mut power = vec![0f64; fft.size() / 2];
+    let mut frames = 0;
+    for frame in samples.chunks_exact(fft.size()) {
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+        if rms >= SILENCE_RMS {
+            fft.accumulate_power(frame, &mut power);
+            frames += 1;
+        }
+    }
+    if frames < MIN_FRAMES {
+        return Verdict::Inconclusive;
+    }
+
+    // average the bins into bands, in dB
+    let nyquist = sample_rate as f64 / 2.0;
+    let bin_hz = nyquist / power.len() as f64;
+    let bins_per_band = ((BAND_HZ / bin_hz).round() as usize).max(1);
+    let levels: Vec<f64> = power
+        .chunks_exact(bins_per_band)
+        .map(|band| {
+            let mean = band.iter().sum::<f64>() / (band.len() * frames) as f64;
+            10.0 * (mean + 1e-20).log10()
+        })
+        .collect();
+    let band_hz = bin_hz * bins_per_band as f64;
+    let mean = |levels: &[f64]| levels.iter().sum::<f64>() / levels.len() as f64;
+
+    // find the biggest fall in level, which also stays down all the way to the Nyquist frequency
+    let first_band = ((MIN_CUTOFF_HZ / band_hz) as usize).max(CLIFF_BANDS);
+    let cutoff = (first_band..levels.len().saturating_sub(CLIFF_BANDS))
+        .filter_map(|band| {
+            let below = mean(&levels[band - CLIFF_BANDS..band]);
+            let above = mean(&levels[band..band + CLIFF_BANDS]);
+            let rest = mean(&levels[band..]);
+            (below - above >= CLIFF_DB && below - rest >= CLIFF_DB).then_some((band, below - above))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(band, _)| band as f64 * band_hz);
+
+    match cutoff {
+        Some(cutoff) if cutoff < nyquist * NYQUIST_FRACTION => Verdict::Suspicious {
+            cutoff: cutoff.round() as u32,
+        },
+        _ => Verdict::Genuine,
+    }
+}
@@ -0,0 +1,274 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::parsers::AiffParser;
+
+/// The start of a track is often quiet, so skip it if the track is long enough
+const SKIP_SECONDS: u64 = 5;
+/// How much audio gets decoded
+const SAMPLE_SECONDS: u64 = 30;
+
+/// Mono PCM scaled to -1.0..=1.0
+pub(crate) struct Pcm {
+    pub(crate) sample_rate: u32,
+    pub(crate) samples: Vec<f32>,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// Works out which frames to decode, given how many the file has (if it's known)
+fn frame_range(sample_rate: u32, total_frames: Option<u64>) -> (u64, u64) {
+    let wanted = SAMPLE_SECONDS * sample_rate as u64;
+    let skip = match total_frames {
+        Some(total) => (SKIP_SECONDS * sample_rate as u64).min(total.saturating_sub(wanted)),
+        None => 0,
+    };
+    (skip, wanted)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SampleFormat {
+    bytes: usize,
+    float: bool,
+    big_endian: bool,
+    /// WAV stores 8 bit samples unsigned, everything else is signed
+    unsigned: bool,
+}
+
+impl SampleFormat {
+    fn decode(&self, bytes: &[u8]) -> f32 {
+        let mut buf = [0u8; 8];
+        // put the bytes in little endian order
+        for (i, byte) in bytes.iter().enumerate() {
+            let i = if self.big_endian {
+                self.bytes - 1 - i
+            } else {
+                i
+            };
+            buf[i] = *byte;
+        }
+        match (self.bytes, self.float) {
+            (4, true) => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            (8, true) => f64::from_le_bytes(buf) as f32,
+            (1, _) if self.unsigned => (buf[0] as f32 - 128.0) / 128.0,
+            (bytes, _) => {
+                // shift the sample to the top of an i64 so the sign is extended
+                let shift = 64 - bytes * 8;
+                let value = (i64::from_le_bytes(buf) << shift) >> shift;
+                value as f32 / (1i64 << (bytes * 8 - 1)) as f32
+            }
+        }
+    }
+}
+
+/// Reads interleaved frames from `file`'s current position, mixing them down to mono
+fn read_interleaved(
+    file: &mut File,
+    format: SampleFormat,
+    channels: usize,
+    frames: u64,
+) -> Result<Vec<f32>> {
+    let frame_size = format.bytes * channels;
+    let mut data = Vec::new();
+    file.take(frames * frame_size as u64)
+        .read_to_end(&mut data)?;
+    Ok(data
+        .chunks_exact(frame_size)
+        .map(|frame| {
+            frame
+                .chunks_exact(format.bytes)
+                .map(|sample| format.decode(sample))
+                .sum::<f32>()
+                / channels as f32
+        })
+        .collect())
+}
+
+/// Decodes part of a FLAC, WAV or AIFF file. Returns `None` for other files.
+pub(crate) fn read_sample(path: &Path) -> Option<Result<Pcm>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())?;
+    Some(match extension.as_str() {
+        "flac" => read_flac(path),
+        "wav" => read_wav(path),
+        "aiff" | "aifc" | "aif" => read_aiff(path),
+        _ => return None,
+    })
+}
+
+fn read_flac(path: &Path) -> Result<Pcm> {
+    let to_io = |e: claxon::Error| match e {
+        claxon::Error::IoError(e) => e,
+        e => invalid(&e.to_string()),
+    };
+    let mut reader = claxon::FlacReader::open(path).map_err(to_io)?;
+    let info = reader.streaminfo();
+    if info.sample_rate == 0 || info.channels == 0 {
+        return Err(invalid("Invalid STREAMINFO"));
+    }
+    let scale = (1i64 << (info.bits_per_sample - 1)) as f32;
+    let (mut skip, wanted) = frame_range(info.sample_rate, info.samples);
+
+    let mut samples = Vec::with_capacity(wanted as usize);
+    let mut blocks = reader.blocks();
+    let mut buffer = Vec::new();
+    while (samples.len() as u64) < wanted {
+        let block = match blocks.read_next_or_eof(buffer).map_err(to_io)? {
+            Some(block) => block,
+            None => break,
+        };
+        for i in skip.min(block.duration() as u64) as u32..block.duration() {
+            let sum: i64 = (0..block.channels())
+                .map(|channel| block.sample(channel, i) as i64)
+                .sum();
+            samples.push(sum as f32 / block.channels() as f32 / scale);
+        }
+        skip = skip.saturating_sub(block.duration() as u64);
+        buffer = block.into_buffer();
+    }
+    samples.truncate(wanted as usize);
+
+    Ok(Pcm {
+        sample_rate: info.sample_rate,
+        samples,
+    })
+}
+
+fn read_wav(path: &Path) -> Result<Pcm> {
+    let mut file = File::open(path)?;
+    let mut riff_header = [0u8; 12];
+    file.read_exact(&mut riff_header)?;
+    if &riff_header[0..4] != b"RIFF" || &riff_header[8..12] != b"WAVE" {
+        return Err(invalid("Not a valid WAV file"));
+    }
+
+    let mut fmt = None;
+    loop {
+        let mut chunk_header = [0u8; 8];
+        file.read_exact(&mut chunk_header)?;
+        let size = u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()) as u64;
+        match &chunk_header[0..4] {
+            b"fmt " => {
+                let mut data = vec![0u8; size.min(40) as usize];
+                file.read_exact(&mut data)?;
+                file.seek(SeekFrom::Current((size - data.len() as u64) as i64))?;
+                if data.len() < 16 {
+                    return Err(invalid("fmt chunk is too short"));
+                }
+                let mut format_tag = u16::from_le_bytes([data[0], data[1]]);
+                // WAVE_FORMAT_EXTENSIBLE keeps the real format at the start of the sub-format GUID
+                if format_tag == 0xFFFE && data.len() >= 26 {
+                    format_tag = u16::from_le_bytes([data[24], data[25]]);
+                }
+                let channels = u16::from_le_bytes([data[2], data[3]]) as usize;
+                let sample_rate = u32::from_le_bytes(data[4..8].try_into().unwrap());
+                let bits = u16::from_le_bytes([data[14], data[15]]);
+                let float = match format_tag {
+                    1 => false,
+                    3 => true,
+                    _ => return Err(invalid("Only PCM WAV files can be analysed")),
+                };
+                fmt = Some((channels, sample_rate, bits, float));
+            }
+            b"data" => {
+                let (channels, sample_rate, bits, float) =
+                    fmt.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                if channels == 0 || sample_rate == 0 || !(8..=64).contains(&bits) {
+                    return Err(invalid("Invalid WAV format"));
+                }
+                let format = SampleFormat {
+                    bytes: bits.div_ceil(8) as usize,
+                    float,
+                    big_endian: false,
+                    unsigned: bits <= 8,
+                };
+                let frame_size = (format.bytes * channels) as u64;
+                let (skip, wanted) = frame_range(sample_rate, Some(size / frame_size));
+                file.seek(SeekFrom::Current((skip * frame_size) as i64))?;
+                return Ok(Pcm {
+                    sample_rate,
+                    samples: read_interleaved(&mut file, format, channels, wanted)?,
+                });
+            }
+            _ => {
+                // chunks are padded to even byte boundaries
+                file.seek(SeekFrom::Current((size + size % 2) as i64))?;
+            }
+        }
+    }
+}
+
+fn read_aiff(path: &Path) -> Result<Pcm> {
+    let mut file = File::open(path)?;
+    let mut form_header = [0u8; 12];
+    file.read_exact(&mut form_header)?;
+    let is_aifc = match &form_header[8..12] {
+        _ if &form_header[0..4] != b"FORM" => return Err(invalid("Not a valid AIFF file")),
+        b"AIFF" => false,
+        b"AIFC" => true,
+        _ => return Err(invalid("Not a valid AIFF file")),
+    };
+
+    let mut comm = None;
+    loop {
+        let mut chunk_header = [0u8; 8];
+        file.read_exact(&mut chunk_header)?;
+        let size = u32::from_be_bytes(chunk_header[4..8].try_into().unwrap()) as u64;
+        let next = file.stream_position()? + size + size % 2;
+        match &chunk_header[0..4] {
+            b"COMM" => {
+                let mut data = [0u8; 8];
+                file.read_exact(&mut data)?;
+                let channels = u16::from_be_bytes([data[0], data[1]]) as usize;
+                let frames = u32::from_be_bytes(data[2..6].try_into().unwrap()) as u64;
+                let bits = u16::from_be_bytes([data[6], data[7]]);
+                let sample_rate = AiffParser::read_ieee754_extended(&mut file)? as u32;
+                let compression = if is_aifc {
+                    let mut compression = [0u8; 4];
+                    file.read_exact(&mut compression)?;
+                    compression
+                } else {
+                    *b"NONE"
+                };
+                let (float, big_endian) = match &compression {
+                    b"NONE" | b"twos" => (false, true),
+                    b"sowt" => (false, false),
+                    b"fl32" | b"FL32" | b"fl64" | b"FL64" => (true, true),
+                    _ => return Err(invalid("Compressed AIFF files can't be analysed")),
+                };
+                if channels == 0 || sample_rate == 0 || !(8..=64).contains(&bits) {
+                    return Err(invalid("Invalid AIFF format"));
+                }
+                let format = SampleFormat {
+                    bytes: bits.div_ceil(8) as usize,
+                    float,
+                    big_endian,
+                    unsigned: false,
+                };
+                comm = Some((channels, frames, sample_rate, format));
+            }
+            b"SSND" => {
+                let (channels, frames, sample_rate, format) =
+                    comm.ok_or_else(|| invalid("SSND chunk before COMM chunk"))?;
+                // offset (4), block size (4)
+                let mut data = [0u8; 8];
+                file.read_exact(&mut data)?;
+                let offset = u32::from_be_bytes(data[0..4].try_into().unwrap()) as u64;
+                let (skip, wanted) = frame_range(sample_rate, Some(frames));
+                file.seek(SeekFrom::Current(
+                    (offset + skip * (format.bytes * channels) as u64) as i64,
+                ))?;
+                return Ok(Pcm {
+                    sample_rate,
+                    samples: read_interleaved(&mut file, format, channels, wanted)?,
+                });
+            }
+            _ => (),
+        }
+        file.seek(SeekFrom::Start(next))?;
+    }
+}
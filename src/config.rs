@@ -1,7 +1,7 @@
 use std::{
     fs::{read_to_string, File},
     io::Write,
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
@@ -18,6 +18,20 @@ pub(crate) struct Config {
     pub(crate) user: User,
     #[serde(default = "DiskIndex::setup")]
     pub(crate) index: DiskIndex,
+    #[serde(default = "Default::default")]
+    pub(crate) downloads: Downloads,
+    #[serde(default = "Default::default")]
+    pub(crate) shares: Shares,
+    #[serde(default = "Default::default")]
+    pub(crate) users: Users,
+    #[serde(default = "Default::default")]
+    pub(crate) interests: Interests,
+    #[serde(default = "Default::default")]
+    pub(crate) rooms: Rooms,
+    #[serde(default = "Default::default")]
+    pub(crate) wishlist: Wishlist,
+    #[serde(default = "Default::default")]
+    pub(crate) away: Away,
 }
 
 impl Config {
@@ -64,3 +78,91 @@ pub(crate) struct User {
     pub(crate) password: String,
     pub(crate) port: u16,
 }
+
+/// How files are downloaded, settings missing from older configs take their defaults
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Downloads {
+    /// Check finished downloads are intact and match what the uploader said they were
+    pub(crate) verify: bool,
+    /// Where downloads are saved, relative to the working directory if it isn't absolute
+    pub(crate) directory: PathBuf,
+}
+
+impl Default for Downloads {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            directory: PathBuf::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Shares {
+    /// Hash shared files in the background, to find duplicates and downloads of files we already have
+    pub(crate) hash_files: bool,
+}
+
+impl Default for Shares {
+    fn default() -> Self {
+        Self { hash_files: true }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Users {
+    pub(crate) buddies: Vec<String>,
+    /// Users who can't browse, search or download from our shares
+    pub(crate) banned: Vec<String>,
+}
+
+/// Sent to the server at login, it recommends things and users based on them
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Interests {
+    pub(crate) likes: Vec<String>,
+    pub(crate) hates: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Rooms {
+    /// Whether other users can add us to their private rooms
+    pub(crate) private_room_invitations: bool,
+    /// Show a line in the chat when someone joins or leaves a room
+    #[serde(default)]
+    pub(crate) show_joins_and_leaves: bool,
+}
+
+/// Searches that are re-sent every so often, so results from users who come online later are found
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Wishlist {
+    pub(crate) queries: Vec<String>,
+}
+
+impl Default for Rooms {
+    fn default() -> Self {
+        Self {
+            private_room_invitations: true,
+            show_joins_and_leaves: false,
+        }
+    }
+}
+
+/// Telling other users we're away when we haven't touched the client for a while
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Away {
+    /// Minutes without any input before we go away, 0 to never go away by ourselves
+    pub(crate) idle_minutes: u64,
+    /// Sent once to each user who messages us while we're away, unless it's empty
+    pub(crate) auto_reply: String,
+}
+
+impl Default for Away {
+    fn default() -> Self {
+        Self {
+            idle_minutes: 15,
+            auto_reply: String::new(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{iter::Sum, ops::Add};
+use std::{iter::Sum, ops::Add, time::Duration};
 
 use crate::{
     packing::{PackToBytes, UnpackFromBytes},
@@ -8,6 +8,8 @@ use crate::{
 pub(crate) const MAJOR_VERSION: u32 = 160;
 pub(crate) const MINOR_VERSION: u32 = 1;
 pub(crate) const MAX_RESULTS: u32 = 1500;
+/// How many files we upload at once, everything else waits in the queue
+pub(crate) const UPLOAD_SLOTS: usize = 2;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum UserStatusCodes {
@@ -159,21 +161,27 @@ impl UnpackFromBytes for FileAttributeTypes {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum DownloadStatus {
+    /// Complete, but the file turned out to be truncated or corrupt
+    VerifyFailed,
     Failed,
     Queued,
     Starting,
     Downloading,
     Complete,
+    /// Complete, but the spectrum suggests a lossless file was transcoded from a lossy one
+    Suspicious,
 }
 
 impl DownloadStatus {
     pub(crate) fn str(&self) -> &'static str {
         match *self {
+            DownloadStatus::VerifyFailed => "Verify failed",
             DownloadStatus::Failed => "Failed",
             DownloadStatus::Queued => "Queued",
             DownloadStatus::Starting => "Starting",
             DownloadStatus::Downloading => "Downloading",
             DownloadStatus::Complete => "Complete",
+            DownloadStatus::Suspicious => "Suspicious",
         }
     }
 }
@@ -188,6 +196,17 @@ impl Add for DownloadStatus {
     type Output = DownloadStatus;
 
     fn add(self, rhs: Self) -> Self::Output {
+        // a suspicious file is finished, so it only shows for a folder once everything else is
+        match (self, rhs) {
+            (DownloadStatus::Suspicious, DownloadStatus::Complete)
+            | (DownloadStatus::Complete, DownloadStatus::Suspicious) => {
+                return DownloadStatus::Suspicious
+            }
+            (DownloadStatus::Suspicious, other) | (other, DownloadStatus::Suspicious) => {
+                return other
+            }
+            _ => (),
+        }
         if (self == DownloadStatus::Complete) & (rhs != DownloadStatus::Failed) {
             rhs
         } else if (rhs == DownloadStatus::Complete) | (rhs < self) {
@@ -204,6 +223,55 @@ impl Sum for DownloadStatus {
     }
 }
 
+/// Where a file another user queued from us has got to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum UploadStatus {
+    Failed,
+    Queued,
+    /// Offered to the user, waiting for them to accept and connect
+    Starting,
+    Uploading,
+    Complete,
+}
+
+impl UploadStatus {
+    pub(crate) fn str(&self) -> &'static str {
+        match *self {
+            UploadStatus::Failed => "Failed",
+            UploadStatus::Queued => "Queued",
+            UploadStatus::Starting => "Starting",
+            UploadStatus::Uploading => "Uploading",
+            UploadStatus::Complete => "Complete",
+        }
+    }
+}
+
+impl ToString for UploadStatus {
+    fn to_string(&self) -> String {
+        self.str().to_string()
+    }
+}
+
+impl Add for UploadStatus {
+    type Output = UploadStatus;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if (self == UploadStatus::Complete) & (rhs != UploadStatus::Failed) {
+            rhs
+        } else if (rhs == UploadStatus::Complete) | (rhs < self) {
+            self
+        } else {
+            rhs
+        }
+    }
+}
+
+impl Sum for UploadStatus {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(UploadStatus::Complete, |acc, status| acc + status)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) struct ByteSize(pub(crate) u64);
 
@@ -246,3 +314,37 @@ impl ToString for Token {
         self.0.to_string()
     }
 }
+
+/// How far we've got connecting to the server
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ConnectionState {
+    Connecting,
+    /// Connected, but not logged in yet
+    Connected,
+    LoggedIn,
+    /// Disconnected because of `reason`, and connecting again `seconds` after this was sent
+    WaitingToRetry {
+        seconds: u64,
+        reason: String,
+    },
+    /// Disconnected because of `reason`, and waiting for the user before connecting again
+    Offline {
+        reason: String,
+    },
+}
+
+impl ConnectionState {
+    /// Describes the state for the status bar, `elapsed` after we entered it
+    pub(crate) fn describe(&self, elapsed: Duration) -> String {
+        match self {
+            ConnectionState::Connecting => String::from("Connecting..."),
+            ConnectionState::Connected => String::from("Connected"),
+            ConnectionState::LoggedIn => String::from("Logged in"),
+            ConnectionState::WaitingToRetry { seconds, reason } => format!(
+                "{reason}, retrying in {}s",
+                seconds.saturating_sub(elapsed.as_secs())
+            ),
+            ConnectionState::Offline { reason } => format!("Offline: {reason}"),
+        }
+    }
+}
@@ -1,33 +1,172 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use tokio::sync::RwLock;
 
 use crate::{
-    constants::{ByteSize, ConnectionTypes, DownloadStatus, Percentage},
-    messages::UserStats,
+    constants::{
+        ByteSize, ConnectionState, ConnectionTypes, DownloadStatus, Percentage, UploadStatus,
+        UserStatusCodes,
+    },
+    messages::{
+        Directory, File, FileAttribute, SharedFileListResponse, UserInfoResponse, UserStats,
+    },
+    nicotine::NicotineImport,
+    sql::{ShareStats, SharedRoot},
     FileSearchResponse,
 };
 
+/// A file in a folder download: its remote path, status, progress and the attributes it was listed with
+pub(crate) type FolderDownload = (
+    String,
+    Arc<RwLock<DownloadStatus>>,
+    Arc<RwLock<Percentage>>,
+    Vec<FileAttribute>,
+);
+
 #[rustfmt::skip]
 #[derive(Clone, Debug)]
 pub enum SLSKEvents {
     TryLogin { username: String, password: String },
     LoginResult { success: bool, reason: Option<String> },
+    /// We logged in from somewhere else, so the server logged us out here
+    Relogged,
+    /// A message the server admins sent to everyone
+    AdminMessage { message: String },
     Quit { restart: bool },
-    RoomList { rooms_and_num_of_users: Vec<(String, u32)> },
+    /// How far we've got connecting to the server, for the status bar
+    ConnectionState(ConnectionState),
+    /// Sent every so often while we're logged in, so the server doesn't drop an idle connection
+    ServerPing,
+    /// `private_rooms_and_num_of_users` are the private rooms we're a member of, including the ones we own
+    RoomList { rooms_and_num_of_users: Vec<(String, u32)>, private_rooms_and_num_of_users: Vec<(String, u32)>, owned_rooms: Vec<String>, operated_rooms: Vec<String> },
     /// Think of `private` like a boolean. 0 means public, anything else means private.
     JoinRoom { room: String, private: u32 },
     LeaveRoom { room: String },
-    UpdateRoom { room: String, stats: Vec<(String, UserStats)> },
+    /// `owner` and `operators` are only set for private rooms
+    UpdateRoom { room: String, stats: Vec<(String, UserStatusCodes, UserStats)>, owner: Option<String>, operators: Vec<String> },
+    UserJoinedRoom { room: String, username: String, status: UserStatusCodes, stats: UserStats },
+    UserLeftRoom { room: String, username: String },
+    /// Asks the server to tell us whenever the user's status changes
+    WatchUser { username: String },
+    /// Tells everyone we're away or back
+    SetStatus { status: UserStatusCodes },
+    /// A user went away, came back or went offline
+    UserStatus { username: String, status: UserStatusCodes, privileged: bool },
+    /// Whether users joining and leaving rooms is shown in the chat
+    SetShowJoinsAndLeaves { enabled: bool },
     ChatroomMessage { room: String, username: Option<String>, message: String },
+    /// Asks how long we're privileged for
+    CheckPrivileges,
+    PrivilegesLeft { seconds: u32 },
+    /// What the server says about a user's shares and uploads, as everyone else sees it
+    UserStats { username: String, stats: UserStats },
+    /// Gifts some of our privileged days to another user
+    GivePrivileges { username: String, days: u32 },
+    /// Everyone who's privileged, sent by the server at login
+    PrivilegedUsers { usernames: Vec<String> },
+    PrivilegedUserAdded { username: String },
     FileSearch { query: String, token: u32 },
+    /// Searches one user's shares
+    UserSearch { username: String, query: String, token: u32 },
+    /// Searches the shares of the users in a room we're in
+    RoomSearch { room: String, query: String, token: u32 },
+    /// Searches every buddy's shares
+    BuddySearch { query: String, token: u32 },
     SearchResults ( FileSearchResponse ),
+    /// Another user searching everyone's shares, answered from ours unless they're banned
+    SearchRequest { username: String, token: u32, query: String },
+    /// How often the server wants wishlist searches to be sent
+    WishlistInterval { seconds: u32 },
+    /// Results come back like any other search's, with the same `token`
+    WishlistSearch { query: String, token: u32 },
+    SetWishlist { queries: Vec<String> },
     GetInfo ( String ),
     Connect { username: String, token: u32, connection_type: ConnectionTypes},
     QueueMessage { token: u32, message_bytes: Vec<u8> },
-    NewDownloads { username: String, folder: String, files: Vec<(String, ByteSize)>, from_all: bool },
-    NewDownload { username: String, folder: String, filename: String, filesize: ByteSize },
-    UpdateDownload { filename: String, status: Arc<RwLock<DownloadStatus>>, percentage: Arc<RwLock<Percentage>> },
-    UpdateDownloads { files: Vec<(String, Arc<RwLock<DownloadStatus>>, Arc<RwLock<Percentage>>)>, from_all: bool },
+    /// `attributes` are the ones from the search result, finished downloads are checked against them
+    NewDownloads { username: String, folder: String, files: Vec<(String, ByteSize, Vec<FileAttribute>)>, from_all: bool },
+    NewDownload { username: String, folder: String, filename: String, filesize: ByteSize, attributes: Vec<FileAttribute> },
+    UpdateDownload { filename: String, status: Arc<RwLock<DownloadStatus>>, percentage: Arc<RwLock<Percentage>>, attributes: Vec<FileAttribute> },
+    UpdateDownloads { files: Vec<FolderDownload>, from_all: bool },
+    /// A finished download didn't match what it was listed as. It's kept at `path` for the user to retry or delete.
+    VerifyFailed { username: String, filename: String, path: PathBuf, reason: String },
+    /// A user queued one of our files, `path` is where it really is
+    UploadQueued { username: String, filename: String, path: PathBuf, filesize: u64 },
+    NewUpload { username: String, filename: String, filesize: u64, status: Arc<RwLock<UploadStatus>>, percentage: Arc<RwLock<Percentage>> },
+    /// Whether the user accepted the TransferRequest we offered them a queued file with
+    UploadResponse { token: u32, allowed: bool },
+    /// `speed` is how fast it went in bytes per second, `None` if it failed
+    UploadFinished { token: u32, speed: Option<u32> },
     BrowseUser { username: String },
+    UserShares { username: String, file_list: SharedFileListResponse },
+    /// Asks a user what's in one of their folders
+    GetFolderContents { username: String, folder: String },
+    FolderContents { username: String, folder: String, folders: Vec<Directory> },
+    AddShare { path: PathBuf, alias: String, buddy_only: bool },
+    RemoveShare { alias: String },
+    RenameShare { alias: String, new_alias: String },
+    SetShareBuddyOnly { alias: String, buddy_only: bool },
+    RescanShare { alias: String },
+    /// Sent whenever the shares change (and once they've been indexed at startup).
+    /// `error` is set if the change that was asked for failed.
+    SharesUpdated { shares: Vec<SharedRoot>, error: Option<String> },
+    /// Asks for our shares as users browsing them would see them
+    PreviewShares,
+    SharesPreview { file_list: SharedFileListResponse, stats: ShareStats },
+    /// Runs a search against our own shares, to see what users searching for `query` would get
+    PreviewSearch { query: String },
+    SearchPreview { query: String, files: Vec<File>, private_files: Vec<File> },
+    /// Asks which of the files about to be queued are the same size as ones we share, the reply has the same `token`.
+    /// `files` are the remote paths and sizes.
+    FindLocalCopies { token: u32, files: Vec<(String, u64)> },
+    LocalCopies { token: u32, files: Vec<(String, Vec<String>)> },
+    /// Sent once a download's first chunk arrives, to check whether we already share the file
+    CheckLocalCopy { username: String, filename: String, size: u64, head_hash: String },
+    LocalCopyFound { username: String, filename: String, local_paths: Vec<String> },
+    /// Reads a Nicotine+ config, the reply lists what importing it would change, or why it couldn't be read
+    PreviewImport { path: PathBuf },
+    ImportPreview { path: PathBuf, preview: Result<(NicotineImport, Vec<String>), String> },
+    ApplyImport { import: NicotineImport },
+    /// `hate` is whether it's something we hate rather than like
+    AddInterest { item: String, hate: bool },
+    RemoveInterest { item: String, hate: bool },
+    /// `global` asks for what's popular with everyone, rather than what's recommended from our interests
+    GetRecommendations { global: bool },
+    Recommendations { global: bool, recommendations: Vec<(String, i32)>, unrecommendations: Vec<(String, i32)> },
+    GetSimilarUsers,
+    SimilarUsers { users: Vec<(String, u32)> },
+    /// Asks for what's recommended alongside `item`, and who likes it
+    GetItemRecommendations { item: String },
+    ItemRecommendations { item: String, recommendations: Vec<(String, i32)> },
+    ItemSimilarUsers { item: String, usernames: Vec<String> },
+    /// Asks a user for their info, and the server for their interests
+    GetUserInfo { username: String },
+    UserInfo { username: String, info: UserInfoResponse },
+    UserInterests { username: String, likes: Vec<String>, hates: Vec<String> },
+    OpenPrivateChat { username: String },
+    /// `id` is `None` for messages we send, received messages are acknowledged with it
+    PrivateMessage { id: Option<u32>, username: String, message: String },
+    AddRoomMember { room: String, username: String },
+    RemoveRoomMember { room: String, username: String },
+    AddRoomOperator { room: String, username: String },
+    RemoveRoomOperator { room: String, username: String },
+    /// Gives up ownership of a private room, its members stay in it
+    DisownRoom { room: String },
+    /// Removes every member from a private room we own, which closes it
+    DismemberRoom { room: String },
+    SetPrivateRoomInvitations { enabled: bool },
+    /// Members of a private room, not including its owner and operators
+    RoomMembers { room: String, members: Vec<String> },
+    RoomOperators { room: String, operators: Vec<String> },
+    RoomMemberChanged { room: String, username: String, added: bool },
+    RoomOperatorChanged { room: String, username: String, added: bool },
+    /// We were added to or removed from a private room
+    PrivateRoomAccess { room: String, added: bool },
+    PrivateRoomInvitations { enabled: bool },
+    /// Every ticker in a room, by username
+    RoomTickers { room: String, tickers: Vec<(String, String)> },
+    /// `ticker` is `None` when the user's ticker was removed
+    RoomTickerChanged { room: String, username: String, ticker: Option<String> },
+    /// Sets our ticker for a room, an empty ticker clears it
+    SetRoomTicker { room: String, ticker: String },
 }
@@ -1,29 +1,33 @@
 use std::{
     collections::{HashMap, VecDeque},
     fs::{create_dir, create_dir_all},
-    io::Write,
-    path::Path,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
-    sync::{Mutex, RwLock},
+    sync::{broadcast::Sender, Mutex, RwLock},
     time::sleep,
 };
 
 use crate::{
-    constants::{DownloadStatus, Percentage},
-    utils::log,
+    analysis::{analyse, can_analyse, Verdict},
+    constants::{DownloadStatus, Percentage, UploadStatus},
+    events::SLSKEvents,
+    messages::FileAttribute,
+    obfuscation::PeerStream,
+    parsers::verify_download,
+    upload_handling::Upload,
+    utils::{log, md5_digest},
     CHUNK_SIZE,
 };
 
-// TODO: Implement uploads
-/// Handles downloading a file, will handle uploading files in the future
+/// Handles downloading a file
 pub(crate) async fn handle_file_transfer(
-    mut peer_stream: TcpStream,
+    mut peer_stream: PeerStream,
     file_info_map: Arc<Mutex<HashMap<u32, VecDeque<(String, u64)>>>>,
     peer_download_filename_map: Arc<
         Mutex<
@@ -33,11 +37,14 @@ pub(crate) async fn handle_file_transfer(
                     Arc<RwLock<DownloadStatus>>,
                     Arc<RwLock<Percentage>>,
                     Option<bool>,
+                    Option<Vec<FileAttribute>>,
                 )>,
             >,
         >,
     >,
     username: String,
+    write_queue: Sender<SLSKEvents>,
+    download_directory: PathBuf,
 ) {
     let offset = 0;
     let mut percentage = 0u8;
@@ -52,7 +59,7 @@ pub(crate) async fn handle_file_transfer(
             .pop_front()
             .unwrap()
     };
-    let (download_status, download_percentage, download_type) = {
+    let (download_status, download_percentage, download_type, expected_attributes) = {
         loop {
             {
                 let mut locked_peer_download_filename_map = peer_download_filename_map.lock().await;
@@ -73,7 +80,10 @@ pub(crate) async fn handle_file_transfer(
     {
         *download_status.write().await = DownloadStatus::Starting;
     }
-    let mut file_handle = std::fs::File::create({
+    let filepath = {
+        if !download_directory.as_os_str().is_empty() {
+            let _ = create_dir_all(&download_directory);
+        }
         let (prefix, base_name) = filename.rsplit_once("\\").unwrap();
         let filepath = match download_type {
             Some(is_all) => {
@@ -81,24 +91,24 @@ pub(crate) async fn handle_file_transfer(
 
                 {
                     if is_all {
-                        let folder_path = Path::new(&username).join(folder);
-                        if !std::fs::exists(&username).unwrap() {
+                        let folder_path = download_directory.join(&username).join(folder);
+                        if !std::fs::exists(download_directory.join(&username)).unwrap() {
                             create_dir_all(&folder_path).unwrap();
                         } else if !std::fs::exists(&folder_path).unwrap() {
                             create_dir(&folder_path).unwrap();
                         };
                         folder_path
                     } else {
-                        let folder_path = Path::new(&folder);
+                        let folder_path = download_directory.join(folder);
                         if !std::fs::exists(&folder_path).unwrap() {
                             create_dir(&folder_path).unwrap();
                         };
-                        folder_path.to_path_buf()
+                        folder_path
                     }
                 }
                 .join(base_name)
             }
-            None => base_name.into(),
+            None => download_directory.join(base_name),
         };
         if filepath.exists() {
             let mut count = 1;
@@ -121,8 +131,8 @@ pub(crate) async fn handle_file_transfer(
         } else {
             filepath
         }
-    })
-    .unwrap();
+    };
+    let mut file_handle = std::fs::File::create(&filepath).unwrap();
     peer_stream.write_u64_le(offset).await.unwrap();
 
     loop {
@@ -133,6 +143,15 @@ pub(crate) async fn handle_file_transfer(
         }
         match peer_stream.read_exact(&mut buf).await {
             Ok(n) => {
+                if downloaded == 0 {
+                    // the first chunk is enough to tell whether we already share this file
+                    let _ = write_queue.send(SLSKEvents::CheckLocalCopy {
+                        username: username.clone(),
+                        filename: filename.clone(),
+                        size: filesize,
+                        head_hash: md5_digest(&buf),
+                    });
+                }
                 downloaded += n as u64;
                 file_handle.write_all(&buf).unwrap();
                 if downloaded == filesize {
@@ -161,5 +180,105 @@ pub(crate) async fn handle_file_transfer(
     }
     file_handle.flush().unwrap();
     let _ = peer_stream.shutdown().await;
+
+    if let (DownloadStatus::Complete, Some(expected_attributes)) =
+        (*download_status.read().await, expected_attributes)
+    {
+        let verify_path = filepath.clone();
+        let result =
+            tokio::task::spawn_blocking(move || verify_download(&verify_path, &expected_attributes))
+                .await;
+        if let Ok(Err(e)) = result {
+            log(format!("{filename} failed verification: {e}"));
+            // the checks can be wrong about a good file, so it's up to the user whether to keep it
+            *download_status.write().await = DownloadStatus::VerifyFailed;
+            let _ = write_queue.send(SLSKEvents::VerifyFailed {
+                username: username.clone(),
+                filename: filename.clone(),
+                path: filepath.clone(),
+                reason: e.to_string(),
+            });
+        }
+    }
+
+    if (*download_status.read().await == DownloadStatus::Complete) && can_analyse(&filepath) {
+        let verdict = tokio::task::spawn_blocking(move || analyse(&filepath)).await;
+        if let Ok(Ok(verdict @ Verdict::Suspicious { .. })) = verdict {
+            log(format!(
+                "{filename} looks transcoded: {}",
+                verdict.describe()
+            ));
+            *download_status.write().await = DownloadStatus::Suspicious;
+        }
+    }
     return;
 }
+
+/// Sends a file the user accepted over the file connection they opened for `token`.
+/// How fast it went is sent on with the UploadFinished event, to be reported to the server.
+pub(crate) async fn handle_upload(
+    mut peer_stream: PeerStream,
+    token: u32,
+    upload: Upload,
+    write_queue: Sender<SLSKEvents>,
+) {
+    *upload.status.write().await = UploadStatus::Uploading;
+    let speed = match send_file(&mut peer_stream, token, &upload).await {
+        Ok(speed) => {
+            log(format!(
+                "finished uploading {} to {}",
+                upload.filename, upload.username
+            ));
+            *upload.status.write().await = UploadStatus::Complete;
+            *upload.percentage.write().await = Percentage(100);
+            speed
+        }
+        Err(e) => {
+            log(format!(
+                "stopped uploading {} to {} due to {e:?}",
+                upload.filename, upload.username
+            ));
+            *upload.status.write().await = UploadStatus::Failed;
+            None
+        }
+    };
+    let _ = peer_stream.shutdown().await;
+    let _ = write_queue.send(SLSKEvents::UploadFinished { token, speed });
+}
+
+/// Writes the file from wherever the user asks to start, returning the speed in bytes per second.
+/// The speed is `None` if the user already had the whole file.
+async fn send_file(
+    peer_stream: &mut PeerStream,
+    token: u32,
+    upload: &Upload,
+) -> std::io::Result<Option<u32>> {
+    peer_stream.write_u32_le(token).await?;
+    let offset = peer_stream.read_u64_le().await?;
+    let mut file_handle = std::fs::File::open(&upload.path)?;
+    file_handle.seek(SeekFrom::Start(offset))?;
+
+    let started = Instant::now();
+    let mut uploaded = offset;
+    let mut percentage = 0u8;
+    let mut buf = vec![0; CHUNK_SIZE];
+    while uploaded < upload.filesize {
+        let n = file_handle.read(&mut buf)?;
+        if n == 0 {
+            // the file has shrunk since it was indexed
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let n = std::cmp::min(n as u64, upload.filesize - uploaded) as usize;
+        peer_stream.write_all(&buf[..n]).await?;
+        uploaded += n as u64;
+        let new_percentage = ((uploaded * 100) / upload.filesize) as u8;
+        if new_percentage != percentage {
+            *upload.percentage.write().await = Percentage(new_percentage);
+            percentage = new_percentage;
+        }
+    }
+    peer_stream.flush().await?;
+
+    let elapsed = started.elapsed().as_secs_f64();
+    Ok((uploaded > offset).then(|| ((uploaded - offset) as f64 / elapsed.max(0.001)) as u32))
+}
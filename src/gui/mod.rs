@@ -1,16 +1,16 @@
 pub(crate) mod widgets;
 mod windows;
-use crate::gui::widgets::input::InputType;
+use crate::gui::widgets::{input::InputType, notices::Notices};
 use crate::styles::STYLE_DEFAULT;
 use crate::utils::now_as_string;
 use crate::{Config, DownloadStatus, Percentage};
 
 use crate::{
+    constants::{ByteSize, ConnectionState, UserStatusCodes},
     events::SLSKEvents,
-    gui::widgets::chatrooms::ChatroomState,
     styles::{
         STYLE_DEFAULT_HIGHLIGHT_LOW_CONTRAST, STYLE_DEFAULT_LOW_CONTRAST, STYLE_DISABLED_DEFAULT,
-        STYLE_FAIL_DEFAULT,
+        STYLE_FAIL_DEFAULT, STYLE_FAIL_DEFAULT_LOW_CONTRAST,
     },
     utils::{key_events_into_paragraph_tabs, vec_string_to_tabs},
 };
@@ -23,16 +23,20 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::text::Masked;
+use smol::block_on;
 use tokio::sync::RwLock;
-use widgets::list::List;
 
 use self::windows::filesearch::FileSearchWindow;
-use self::{
-    widgets::dropdown::DropdownItem,
-    windows::{
-        chatrooms::ChatroomsWindow, login::LoginWindow, transfers::TransfersWindow,
-        WidgetWithHints, Window,
-    },
+use self::windows::{
+    browse::BrowseWindow,
+    chatrooms::ChatroomsWindow,
+    interests::InterestsWindow,
+    login::{login_failure_explanation, LoginWindow},
+    private_chats::PrivateChatsWindow,
+    settings::SettingsWindow,
+    shares::SharesWindow,
+    transfers::TransfersWindow,
+    WidgetWithHints, Window,
 };
 use ratatui::{
     backend::{Backend, CrosstermBackend},
@@ -40,12 +44,16 @@ use ratatui::{
     prelude::Rect,
     style::{Style, Styled},
     symbols,
-    widgets::{Block, Borders},
+    widgets::{Block, Borders, Paragraph},
     Frame, Terminal,
 };
 use std::sync::atomic::AtomicU16;
 use std::sync::Arc;
-use std::{error::Error, io, time::Duration};
+use std::{
+    error::Error,
+    io,
+    time::{Duration, Instant},
+};
 use tokio::sync::broadcast::{Receiver, Sender};
 
 /// Width, Height
@@ -57,9 +65,40 @@ make_window_enum!(
     ChatroomsWindow ChatroomsWindow get_mut_chatrooms 1 ('a),
     FileSearchWindow FileSearchWindow get_mut_filesearch 2 ('a),
     DownloadsWindow TransfersWindow get_mut_downloads 3 ('a),
-    UploadsWindow TransfersWindow get_mut_uploads 4 ('a),
+    SettingsWindow SettingsWindow get_mut_settings 4 ('a),
+    SharesWindow SharesWindow get_mut_shares 5 ('a),
+    BrowseWindow BrowseWindow get_mut_browse 6 ('a),
+    InterestsWindow InterestsWindow get_mut_interests 7 ('a),
+    PrivateChatsWindow PrivateChatsWindow get_mut_private_chats 8 ('a),
+    UploadsWindow TransfersWindow get_mut_uploads 9 ('a),
 );
 
+/// Whether we've told everyone we're away
+#[derive(Clone, Copy, PartialEq)]
+enum Away {
+    No,
+    /// Because nothing was pressed for a while, so pressing anything brings us back
+    Idle,
+    /// Because the user said so, so only they can bring us back
+    Manual,
+}
+
+/// Tells everyone whether we're away if that's changed, which can only be done while we're logged in
+fn set_away(current: &mut Away, away: Away, logged_in: bool, write_queue: &Sender<SLSKEvents>) {
+    if !logged_in {
+        return;
+    }
+    if (away == Away::No) != (*current == Away::No) {
+        let status = if away == Away::No {
+            UserStatusCodes::Online
+        } else {
+            UserStatusCodes::Away
+        };
+        let _ = write_queue.send(SLSKEvents::SetStatus { status });
+    }
+    *current = away;
+}
+
 #[derive(Clone)]
 struct App<'a> {
     windows: Vec<WindowEnum<'a>>,
@@ -67,6 +106,14 @@ struct App<'a> {
     select_index: u8,
     focused_widget: u8,
     hints: Vec<(Event, String)>,
+    notices: Notices,
+    /// How far we've got connecting to the server, and when we got there
+    connection_state: (ConnectionState, Instant),
+    away: Away,
+    /// How long without input before we go away, if we ever do
+    away_after: Option<Duration>,
+    /// When a key was last pressed
+    last_input: Instant,
 }
 
 impl App<'_> {
@@ -83,10 +130,21 @@ impl<'a> Default for App<'a> {
                 WindowEnum::ChatroomsWindow(ChatroomsWindow::default()),
                 WindowEnum::FileSearchWindow(FileSearchWindow::default()),
                 WindowEnum::DownloadsWindow(TransfersWindow::default()),
+                WindowEnum::SettingsWindow(SettingsWindow::default()),
+                WindowEnum::SharesWindow(SharesWindow::default()),
+                WindowEnum::BrowseWindow(BrowseWindow::default()),
+                WindowEnum::InterestsWindow(InterestsWindow::default()),
+                WindowEnum::PrivateChatsWindow(PrivateChatsWindow::default()),
+                WindowEnum::UploadsWindow(TransfersWindow::uploads()),
             ],
             current_index: 0,
             select_index: 0,
             focused_widget: 0,
+            notices: Notices::default(),
+            connection_state: (ConnectionState::Connecting, Instant::now()),
+            away: Away::No,
+            away_after: None,
+            last_input: Instant::now(),
             hints: vec![
                 (
                     Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::CONTROL)),
@@ -115,6 +173,10 @@ impl<'a> Default for App<'a> {
                     Event::Key(KeyEvent::new(KeyCode::Tab, KeyModifiers::SHIFT)),
                     String::from("Previous widget"),
                 ),
+                (
+                    Event::Key(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL)),
+                    String::from("Toggle away"),
+                ),
             ],
         }
     }
@@ -158,6 +220,23 @@ pub(crate) fn main(
                     .clone()
                     .with_value(Masked::new(&user.password, '*').to_string());
             }
+
+            // the shares may have been indexed before this app was created, so they're loaded here too
+            app.get_mut_settings().set_shares(
+                block_on(config.index.shared_roots()).unwrap_or_default(),
+                None,
+            );
+            app.get_mut_interests().set_interests(
+                config.interests.likes.clone(),
+                config.interests.hates.clone(),
+            );
+            app.get_mut_filesearch()
+                .set_wishlist(config.wishlist.queries.clone());
+            let chatroom_window = app.get_mut_chatrooms();
+            chatroom_window.private_room_invitations = config.rooms.private_room_invitations;
+            chatroom_window.show_joins_and_leaves = config.rooms.show_joins_and_leaves;
+            app.away_after = (config.away.idle_minutes > 0)
+                .then(|| Duration::from_secs(config.away.idle_minutes * 60));
         }
 
         if !run_app(
@@ -208,47 +287,104 @@ fn run_app<B: Backend>(
                         }
                         false => {
                             // Unwrapping is safe here because a failed login will always have a reason
-                            label = String::from(format!("LOG IN FAILED: {}", reason.unwrap()));
+                            label = format!("LOG IN FAILED: {}", reason.as_deref().unwrap());
                             style = STYLE_FAIL_DEFAULT;
                         }
                     };
                     login_window.login_button.set_label(label);
                     login_window.login_button = login_window.login_button.clone().set_style(style);
+                    if success {
+                        let username = login_window.username_input.input.value().to_string();
+                        app.get_mut_chatrooms().username = username;
+                    } else if let Some(reason) = reason {
+                        app.notices.push(
+                            String::from("Couldn't log in"),
+                            login_failure_explanation(&reason),
+                        );
+                    }
                 }
                 SLSKEvents::Quit { restart } => return Ok(restart),
+                SLSKEvents::ConnectionState(state) => {
+                    app.connection_state = (state, Instant::now());
+                }
+                SLSKEvents::ServerPing => (),
+                SLSKEvents::Relogged => {
+                    let login_window = app.get_mut_login();
+                    login_window
+                        .login_button
+                        .set_label(String::from("LOGGED IN ELSEWHERE"));
+                    login_window.login_button = login_window
+                        .login_button
+                        .clone()
+                        .set_style(STYLE_FAIL_DEFAULT);
+                    login_window
+                        .logout_button
+                        .set_label(String::from("RECONNECT"));
+                    app.notices.push(
+                        String::from("Logged in elsewhere"),
+                        String::from(
+                            "You logged in from somewhere else, so the server logged you out here. \
+                            slsk-rs won't reconnect until you press RECONNECT on the login window.",
+                        ),
+                    );
+                }
+                SLSKEvents::AdminMessage { message } => {
+                    app.notices
+                        .push(String::from("Message from the server admins"), message);
+                }
                 SLSKEvents::TryLogin { .. } => (),
                 SLSKEvents::RoomList {
                     mut rooms_and_num_of_users,
+                    mut private_rooms_and_num_of_users,
+                    owned_rooms,
+                    operated_rooms,
                 } => {
                     let chatroom_window = app.get_mut_chatrooms();
 
-                    rooms_and_num_of_users.sort_by_key(|k| k.1);
-                    rooms_and_num_of_users.reverse();
+                    for rooms in [
+                        &mut rooms_and_num_of_users,
+                        &mut private_rooms_and_num_of_users,
+                    ] {
+                        rooms.sort_by_key(|k| k.1);
+                        rooms.reverse();
+                    }
 
-                    chatroom_window.rooms_dropdown.children = rooms_and_num_of_users
-                        .into_iter()
-                        .map(|(s, _num)| {
-                            if !chatroom_window.chatrooms.contains_key(&s) {
-                                chatroom_window
-                                    .chatrooms
-                                    .insert(s.clone(), (ChatroomState::default(), List::default()));
-                            }
-                            DropdownItem::empty(s)
-                        })
-                        .collect();
+                    chatroom_window.set_rooms(
+                        rooms_and_num_of_users
+                            .into_iter()
+                            .map(|(s, _num)| s)
+                            .collect(),
+                        private_rooms_and_num_of_users
+                            .into_iter()
+                            .map(|(s, _num)| s)
+                            .collect(),
+                        owned_rooms,
+                        operated_rooms,
+                    );
                 }
                 SLSKEvents::JoinRoom { .. } => (),
                 SLSKEvents::LeaveRoom { .. } => (),
-                SLSKEvents::UpdateRoom { room, stats } => {
+                SLSKEvents::UpdateRoom {
+                    room,
+                    stats,
+                    owner,
+                    operators,
+                } => {
                     let chatroom_window = app.get_mut_chatrooms();
-
-                    for (user, user_stats) in stats {
-                        chatroom_window
-                            .chatrooms
-                            .get_mut(&room)
-                            .unwrap()
-                            .0
-                            .add_user(user, user_stats);
+                    // private rooms we've just created aren't in the room list yet
+                    let state = chatroom_window.get_or_add_chatroom_state(&room);
+
+                    // we get sent everyone again when we rejoin after reconnecting
+                    state.users.clear();
+                    state.statuses.clear();
+                    for (user, status, user_stats) in stats {
+                        state.add_user(user.clone(), user_stats);
+                        state.set_status(user, status);
+                    }
+                    if owner.is_some() {
+                        state.private = true;
+                        state.owner = owner;
+                        state.operators = operators;
                     }
 
                     chatroom_window.update_sidebar();
@@ -273,6 +409,15 @@ fn run_app<B: Backend>(
                     filesearch_window.add_results(results);
                 }
                 SLSKEvents::FileSearch { .. } => (),
+                SLSKEvents::UserSearch { .. } => (),
+                SLSKEvents::RoomSearch { .. } => (),
+                SLSKEvents::BuddySearch { .. } => (),
+                SLSKEvents::WishlistInterval { seconds } => {
+                    app.get_mut_filesearch().wishlist_interval =
+                        Some(Duration::from_secs(seconds.into()));
+                }
+                SLSKEvents::WishlistSearch { .. } => (),
+                SLSKEvents::SetWishlist { .. } => (),
                 SLSKEvents::QueueMessage { .. } => (),
                 SLSKEvents::GetInfo(_) => (),
                 SLSKEvents::Connect { .. } => (),
@@ -285,24 +430,25 @@ fn run_app<B: Backend>(
                     let downloads_window = app.get_mut_downloads();
                     let files: Vec<_> = files
                         .into_iter()
-                        .map(|(filename, filesize)| {
+                        .map(|(filename, filesize, attributes)| {
                             (
                                 filename,
                                 filesize,
                                 Arc::new(RwLock::new(DownloadStatus::Queued)),
                                 Arc::new(RwLock::new(Percentage(0))),
+                                attributes,
                             )
                         })
                         .collect();
 
-                    downloads_window.add_folder(username, folder.clone(), files.clone());
+                    downloads_window.add_folder(username, folder.clone(), files.clone(), from_all);
 
                     let _ = &write_queue
                         .send(SLSKEvents::UpdateDownloads {
                             files: files
                                 .into_iter()
-                                .map(|(filename, _, status, percentage)| {
-                                    (format!("{folder}{filename}"), status, percentage)
+                                .map(|(filename, _, status, percentage, attributes)| {
+                                    (format!("{folder}{filename}"), status, percentage, attributes)
                                 })
                                 .collect(),
                             from_all,
@@ -314,6 +460,7 @@ fn run_app<B: Backend>(
                     folder,
                     filename,
                     filesize,
+                    attributes,
                 } => {
                     let downloads_window = app.get_mut_downloads();
 
@@ -323,10 +470,13 @@ fn run_app<B: Backend>(
                     downloads_window.add_file(
                         username,
                         folder.clone(),
-                        filename.clone(),
-                        filesize,
-                        status.clone(),
-                        percentage.clone(),
+                        (
+                            filename.clone(),
+                            filesize,
+                            status.clone(),
+                            percentage.clone(),
+                            attributes.clone(),
+                        ),
                     );
 
                     let _ = &write_queue
@@ -334,23 +484,318 @@ fn run_app<B: Backend>(
                             filename: format!("{folder}{filename}"),
                             status,
                             percentage,
+                            attributes,
                         })
                         .unwrap();
                 }
                 SLSKEvents::UpdateDownload { .. } => (),
                 SLSKEvents::UpdateDownloads { .. } => (),
-                SLSKEvents::BrowseUser { .. } => (), // TODO: UI stuff for BrowseUser
+                SLSKEvents::VerifyFailed {
+                    username,
+                    filename,
+                    path,
+                    reason,
+                } => {
+                    app.get_mut_downloads()
+                        .add_verify_failure(username, filename, path, reason);
+                }
+                SLSKEvents::UploadQueued { .. } => (),
+                SLSKEvents::NewUpload {
+                    username,
+                    filename,
+                    filesize,
+                    status,
+                    percentage,
+                } => {
+                    // the folder keeps its trailing backslash, as it does for downloads
+                    let (folder, filename) = match filename.rsplit_once('\\') {
+                        Some((folder, filename)) => (format!("{folder}\\"), filename.to_string()),
+                        None => (String::new(), filename),
+                    };
+                    app.get_mut_uploads().add_upload(
+                        username,
+                        folder,
+                        (filename, ByteSize(filesize), status, percentage),
+                    );
+                }
+                SLSKEvents::UploadResponse { .. } => (),
+                SLSKEvents::UploadFinished { .. } => (),
+                SLSKEvents::BrowseUser { username } => {
+                    app.get_mut_browse()
+                        .set_status(format!("Browsing {username}..."));
+                    app.current_index = 6;
+                    app.select_index = 6;
+                }
+                SLSKEvents::UserShares {
+                    username,
+                    file_list,
+                } => {
+                    app.get_mut_browse().set_listing(username, file_list, None);
+                }
+                SLSKEvents::AddShare { .. } => (),
+                SLSKEvents::RemoveShare { .. } => (),
+                SLSKEvents::RenameShare { .. } => (),
+                SLSKEvents::SetShareBuddyOnly { .. } => (),
+                SLSKEvents::RescanShare { .. } => (),
+                SLSKEvents::SharesUpdated { shares, error } => {
+                    app.get_mut_settings().set_shares(shares, error);
+                    // the preview is only rebuilt while it's being looked at, it's fetched again when it's opened
+                    if let WindowEnum::SharesWindow(_) = app.windows[app.current_index as usize] {
+                        let _ = write_queue.send(SLSKEvents::PreviewShares);
+                    }
+                }
+                SLSKEvents::PreviewShares => (),
+                SLSKEvents::SharesPreview { file_list, stats } => {
+                    app.get_mut_shares().set_preview(file_list, stats);
+                }
+                SLSKEvents::PreviewSearch { .. } => (),
+                SLSKEvents::SearchRequest { .. } => (),
+                SLSKEvents::FindLocalCopies { .. } => (),
+                SLSKEvents::LocalCopies { token, files } => {
+                    app.get_mut_filesearch().show_local_copies(token, files);
+                }
+                SLSKEvents::CheckLocalCopy { .. } => (),
+                SLSKEvents::LocalCopyFound {
+                    username,
+                    filename,
+                    local_paths,
+                } => {
+                    app.get_mut_downloads()
+                        .add_local_copy(username, filename, local_paths);
+                }
+                SLSKEvents::GetFolderContents { .. } => (),
+                SLSKEvents::FolderContents {
+                    username,
+                    folder,
+                    folders,
+                } => {
+                    app.get_mut_filesearch()
+                        .add_folder_contents(username, folder, folders, &write_queue);
+                }
+                SLSKEvents::PreviewImport { .. } => (),
+                SLSKEvents::ImportPreview { path, preview } => {
+                    app.get_mut_settings().show_import_preview(path, preview);
+                }
+                SLSKEvents::ApplyImport { .. } => (),
+                SLSKEvents::SearchPreview {
+                    query,
+                    files,
+                    private_files,
+                } => {
+                    app.get_mut_shares()
+                        .set_search_preview(query, files, private_files);
+                }
+                SLSKEvents::AddInterest { .. } => (),
+                SLSKEvents::RemoveInterest { .. } => (),
+                SLSKEvents::GetRecommendations { .. } => (),
+                SLSKEvents::Recommendations {
+                    global,
+                    recommendations,
+                    unrecommendations,
+                } => {
+                    // unrecommendations have negative scores, so they end up at the bottom
+                    app.get_mut_interests().set_recommendations(
+                        global,
+                        &[recommendations, unrecommendations].concat(),
+                    );
+                }
+                SLSKEvents::GetSimilarUsers => (),
+                SLSKEvents::SimilarUsers { users } => {
+                    app.get_mut_interests().set_similar_users(&users);
+                }
+                SLSKEvents::GetItemRecommendations { .. } => (),
+                SLSKEvents::ItemRecommendations {
+                    item,
+                    recommendations,
+                } => {
+                    app.get_mut_interests()
+                        .set_item_recommendations(item, &recommendations);
+                }
+                SLSKEvents::ItemSimilarUsers { item, usernames } => {
+                    app.get_mut_interests()
+                        .set_item_similar_users(item, &usernames);
+                }
+                SLSKEvents::GetUserInfo { .. } => (),
+                SLSKEvents::UserInfo { username, info } => {
+                    app.get_mut_interests().set_user_info(&username, info);
+                }
+                SLSKEvents::UserInterests {
+                    username,
+                    likes,
+                    hates,
+                } => {
+                    app.get_mut_interests()
+                        .set_user_interests(&username, likes, hates);
+                }
+                SLSKEvents::OpenPrivateChat { username } => {
+                    let _ = write_queue.send(SLSKEvents::WatchUser {
+                        username: username.clone(),
+                    });
+                    app.get_mut_private_chats().open_chat(username);
+                    app.current_index = 8;
+                    app.select_index = 8;
+                }
+                SLSKEvents::UserJoinedRoom {
+                    room,
+                    username,
+                    status,
+                    stats,
+                } => {
+                    app.get_mut_chatrooms().user_joined_or_left(
+                        &room,
+                        username,
+                        Some((status, stats)),
+                    );
+                }
+                SLSKEvents::UserLeftRoom { room, username } => {
+                    app.get_mut_chatrooms()
+                        .user_joined_or_left(&room, username, None);
+                }
+                SLSKEvents::WatchUser { .. } => (),
+                SLSKEvents::SetStatus { .. } => (),
+                SLSKEvents::UserStatus {
+                    username,
+                    status,
+                    privileged,
+                } => {
+                    if privileged {
+                        app.get_mut_uploads().add_privileged_user(username.clone());
+                    }
+                    app.get_mut_chatrooms().set_user_status(&username, status);
+                }
+                SLSKEvents::CheckPrivileges => (),
+                SLSKEvents::GivePrivileges { .. } => (),
+                SLSKEvents::PrivilegesLeft { seconds } => {
+                    app.get_mut_login().privileges_left = Some(seconds);
+                }
+                SLSKEvents::UserStats { username, stats } => {
+                    let login_window = app.get_mut_login();
+                    if username == login_window.username_input.input.value() {
+                        login_window.stats = Some(stats.clone());
+                    }
+                    app.get_mut_chatrooms().set_user_stats(&username, stats);
+                }
+                SLSKEvents::PrivilegedUsers { usernames } => {
+                    app.get_mut_uploads().set_privileged_users(usernames);
+                }
+                SLSKEvents::PrivilegedUserAdded { username } => {
+                    app.get_mut_uploads().add_privileged_user(username);
+                }
+                SLSKEvents::SetShowJoinsAndLeaves { .. } => (),
+                SLSKEvents::AddRoomMember { .. } => (),
+                SLSKEvents::RemoveRoomMember { .. } => (),
+                SLSKEvents::AddRoomOperator { .. } => (),
+                SLSKEvents::RemoveRoomOperator { .. } => (),
+                SLSKEvents::DisownRoom { .. } => (),
+                SLSKEvents::DismemberRoom { .. } => (),
+                SLSKEvents::SetPrivateRoomInvitations { .. } => (),
+                SLSKEvents::RoomMembers { room, members } => {
+                    let chatroom_window = app.get_mut_chatrooms();
+                    let state = chatroom_window.get_or_add_chatroom_state(&room);
+                    state.private = true;
+                    state.members = members;
+                    chatroom_window.update_sidebar();
+                }
+                SLSKEvents::RoomOperators { room, operators } => {
+                    let chatroom_window = app.get_mut_chatrooms();
+                    chatroom_window.get_or_add_chatroom_state(&room).operators = operators;
+                    chatroom_window.update_sidebar();
+                }
+                SLSKEvents::RoomMemberChanged {
+                    room,
+                    username,
+                    added,
+                } => {
+                    let chatroom_window = app.get_mut_chatrooms();
+                    chatroom_window
+                        .get_or_add_chatroom_state(&room)
+                        .set_member(username, added);
+                    chatroom_window.update_sidebar();
+                }
+                SLSKEvents::RoomOperatorChanged {
+                    room,
+                    username,
+                    added,
+                } => {
+                    let chatroom_window = app.get_mut_chatrooms();
+                    chatroom_window
+                        .get_or_add_chatroom_state(&room)
+                        .set_operator(username, added);
+                    chatroom_window.update_sidebar();
+                }
+                SLSKEvents::PrivateRoomAccess { room, added } => {
+                    app.get_mut_chatrooms().set_private_room_access(room, added);
+                }
+                SLSKEvents::PrivateRoomInvitations { enabled } => {
+                    app.get_mut_chatrooms().private_room_invitations = enabled;
+                }
+                SLSKEvents::RoomTickers { room, tickers } => {
+                    let state = app.get_mut_chatrooms().get_or_add_chatroom_state(&room);
+                    state.tickers.clear();
+                    for (username, ticker) in tickers {
+                        state.set_ticker(username, Some(ticker));
+                    }
+                }
+                SLSKEvents::RoomTickerChanged {
+                    room,
+                    username,
+                    ticker,
+                } => {
+                    app.get_mut_chatrooms()
+                        .get_or_add_chatroom_state(&room)
+                        .set_ticker(username, ticker);
+                }
+                SLSKEvents::SetRoomTicker { room, ticker } => {
+                    // shown straight away, rather than waiting for the server to pass it back
+                    let chatroom_window = app.get_mut_chatrooms();
+                    let username = chatroom_window.username.clone();
+                    chatroom_window
+                        .get_or_add_chatroom_state(&room)
+                        .set_ticker(username, Some(ticker));
+                }
+                SLSKEvents::PrivateMessage {
+                    id,
+                    username,
+                    message,
+                } => {
+                    // messages we've sent don't have an id, they're shown as coming from us
+                    let sender = match id {
+                        Some(_) => username.clone(),
+                        None => app.get_mut_login().username_input.input.value().to_string(),
+                    };
+                    app.get_mut_private_chats().add_message(
+                        username,
+                        format!("{sender} [{}] {message}", now_as_string()),
+                    );
+                }
             },
             None => (),
         }
 
         terminal.draw(|f| ui(f, &app))?;
 
+        app.get_mut_filesearch().search_wishlist(&write_queue);
+
+        let logged_in = app.connection_state.0 == ConnectionState::LoggedIn;
+        if app.away == Away::No
+            && app
+                .away_after
+                .is_some_and(|away_after| app.last_input.elapsed() >= away_after)
+        {
+            set_away(&mut app.away, Away::Idle, logged_in, &write_queue);
+        }
+
         let window_count = app.get_window_count();
         let window = &mut app.windows[app.current_index as usize];
         let window: &mut dyn Window<'_> = match window {
             WindowEnum::LoginWindow(login_window) => login_window,
-            WindowEnum::ChatroomsWindow(chatrooms_window) => chatrooms_window,
+            WindowEnum::ChatroomsWindow(chatrooms_window) => {
+                if chatrooms_window.room_dialog.visible {
+                    &mut chatrooms_window.room_dialog
+                } else {
+                    chatrooms_window
+                }
+            }
             WindowEnum::FileSearchWindow(file_search_window) => {
                 // TODO: create generic popup handling system?
                 // if the dialog is visible, we want to treat it as the main window
@@ -361,6 +806,17 @@ fn run_app<B: Backend>(
                 }
             }
             WindowEnum::DownloadsWindow(downloads_window) => downloads_window,
+            WindowEnum::SettingsWindow(settings_window) => {
+                if settings_window.import_dialog.visible {
+                    &mut settings_window.import_dialog
+                } else {
+                    settings_window
+                }
+            }
+            WindowEnum::SharesWindow(shares_window) => shares_window,
+            WindowEnum::BrowseWindow(browse_window) => browse_window,
+            WindowEnum::InterestsWindow(interests_window) => interests_window,
+            WindowEnum::PrivateChatsWindow(private_chats_window) => private_chats_window,
             WindowEnum::UploadsWindow(transfers_window) => transfers_window,
         };
 
@@ -376,6 +832,25 @@ fn run_app<B: Backend>(
                 // If Release isn't ignored, double key presses are registered (on Windows)
                 event::KeyEventKind::Release => continue,
             };
+            app.last_input = Instant::now();
+            if key.modifiers == KeyModifiers::CONTROL && key.code == KeyCode::Char('z') {
+                let away = if app.away == Away::No {
+                    Away::Manual
+                } else {
+                    Away::No
+                };
+                set_away(&mut app.away, away, logged_in, &write_queue);
+                continue;
+            } else if app.away == Away::Idle {
+                set_away(&mut app.away, Away::No, logged_in, &write_queue);
+            }
+            // notices cover the window until they're dismissed, but quitting still works
+            if !app.notices.is_empty()
+                && (key.modifiers != KeyModifiers::SHIFT || key.code != KeyCode::Esc)
+            {
+                app.notices.handle_event(&terminal_event);
+                continue;
+            }
             app.focused_widget = window.get_focused_index();
 
             if key.modifiers == (KeyModifiers::CONTROL) {
@@ -386,6 +861,17 @@ fn run_app<B: Backend>(
                     }
                     KeyCode::Enter => {
                         app.current_index = app.select_index;
+                        match app.windows[app.current_index as usize] {
+                            WindowEnum::SharesWindow(_) => {
+                                let _ = write_queue.send(SLSKEvents::PreviewShares);
+                            }
+                            WindowEnum::InterestsWindow(_) => {
+                                let _ = write_queue
+                                    .send(SLSKEvents::GetRecommendations { global: false });
+                                let _ = write_queue.send(SLSKEvents::GetSimilarUsers);
+                            }
+                            _ => (),
+                        }
                         continue;
                     }
                     _ => (),
@@ -441,7 +927,11 @@ fn ui(f: &mut Frame, app: &App) {
     let current_window = &app_windows[app.current_index as usize];
 
     let hints = app.hints.clone();
-    let current_hints = current_window.get_hints();
+    let current_hints = if app.notices.is_empty() {
+        current_window.get_hints()
+    } else {
+        app.notices.get_hints()
+    };
 
     let divider = symbols::line::VERTICAL;
 
@@ -477,6 +967,28 @@ fn ui(f: &mut Frame, app: &App) {
         .iter()
         .map(|w| w.get_title().to_string())
         .collect();
+    let (state, since) = &app.connection_state;
+    let mut status = state.describe(since.elapsed());
+    if app.away != Away::No {
+        status.push_str(", away");
+    }
+    let status_style = match state {
+        ConnectionState::WaitingToRetry { .. } | ConnectionState::Offline { .. } => {
+            STYLE_FAIL_DEFAULT_LOW_CONTRAST
+        }
+        _ => STYLE_DEFAULT_LOW_CONTRAST,
+    };
+    let top_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(status.chars().count() as u16 + 2),
+        ])
+        .split(chunks[0]);
+    let status = Paragraph::new(status)
+        .alignment(ratatui::prelude::Alignment::Right)
+        .style(status_style);
+
     let titles = vec_string_to_tabs(
         titles,
         STYLE_DEFAULT_LOW_CONTRAST,
@@ -494,8 +1006,12 @@ fn ui(f: &mut Frame, app: &App) {
         std::sync::atomic::Ordering::Release,
     );
 
-    f.render_widget(titles, chunks[0]);
+    f.render_widget(titles, top_chunks[0]);
+    f.render_widget(status, top_chunks[1]);
     f.render_widget(current_hint_paragraph, chunks[2]);
     f.render_widget(hint_paragraph, chunks[3]);
     app.render_current_window_on_frame(f, chunks[1]);
+    if !app.notices.is_empty() {
+        f.render_widget(app.notices.clone(), chunks[1]);
+    }
 }
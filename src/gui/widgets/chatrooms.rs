@@ -1,11 +1,22 @@
 use std::collections::HashMap;
 
-use crate::messages::UserStats;
+use ordered_hash_map::OrderedHashMap;
+
+use crate::{constants::UserStatusCodes, messages::UserStats};
 
 #[derive(Default, Clone)]
 pub(crate) struct ChatroomState {
     pub(crate) messages: Vec<String>,
     pub(crate) users: HashMap<String, UserStats>,
+    /// Users who aren't in here are online
+    pub(crate) statuses: HashMap<String, UserStatusCodes>,
+    pub(crate) private: bool,
+    pub(crate) owner: Option<String>,
+    pub(crate) operators: Vec<String>,
+    /// Everyone who can join a private room, whether they're in it or not
+    pub(crate) members: Vec<String>,
+    /// Short messages users have set for the room, in the order they were set
+    pub(crate) tickers: OrderedHashMap<String, String>,
 }
 
 impl<'a> ChatroomState {
@@ -17,9 +28,44 @@ impl<'a> ChatroomState {
         self.users.insert(user, stats);
     }
 
-    // TODO: Handle users leaving rooms
-    #[allow(dead_code)]
     pub(crate) fn remove_user(&mut self, user: String) {
         self.users.remove(&user);
+        self.statuses.remove(&user);
+    }
+
+    pub(crate) fn set_status(&mut self, user: String, status: UserStatusCodes) {
+        if status == UserStatusCodes::Online {
+            self.statuses.remove(&user);
+        } else {
+            self.statuses.insert(user, status);
+        }
+    }
+
+    /// An empty ticker removes the user's ticker
+    pub(crate) fn set_ticker(&mut self, user: String, ticker: Option<String>) {
+        self.tickers.remove(&user);
+        if let Some(ticker) = ticker.filter(|ticker| !ticker.is_empty()) {
+            self.tickers.insert(user, ticker);
+        }
+    }
+
+    pub(crate) fn is_operator(&self, user: &str) -> bool {
+        self.operators.iter().any(|operator| operator == user)
+    }
+
+    pub(crate) fn set_member(&mut self, user: String, is_member: bool) {
+        self.members.retain(|member| *member != user);
+        if is_member {
+            self.members.push(user);
+        } else {
+            self.operators.retain(|operator| *operator != user);
+        }
+    }
+
+    pub(crate) fn set_operator(&mut self, user: String, is_operator: bool) {
+        self.operators.retain(|operator| *operator != user);
+        if is_operator {
+            self.operators.push(user);
+        }
     }
 }
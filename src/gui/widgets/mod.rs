@@ -5,6 +5,7 @@ pub(crate) mod input;
 pub(crate) mod table;
 pub(crate) mod tabs;
 pub(crate) mod list;
+pub(crate) mod notices;
 pub(crate) mod dialog;
 
 pub(crate) trait SelectItem<Idx = Option<usize>> {
@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::{Buffer, Constraint, Direction, Layout, Rect},
+    widgets::{Block, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::styles::STYLE_DEFAULT;
+
+/// Things the user needs to know about that didn't come from anything they did,
+/// shown one at a time on top of the current window until they're dismissed
+#[derive(Clone, Default)]
+pub(crate) struct Notices {
+    /// Titles and messages, oldest first
+    queue: VecDeque<(String, String)>,
+}
+
+impl Notices {
+    pub(crate) fn push(&mut self, title: String, message: String) {
+        self.queue.push_back((title, message));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Dismisses the notice being shown if `event` is a key that does that
+    pub(crate) fn handle_event(&mut self, event: &Event) {
+        if [KeyCode::Enter, KeyCode::Esc]
+            .into_iter()
+            .any(|code| *event == Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+        {
+            self.queue.pop_front();
+        }
+    }
+
+    pub(crate) fn get_hints(&self) -> Vec<(Event, String)> {
+        vec![(
+            Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            String::from("Dismiss"),
+        )]
+    }
+}
+
+impl Widget for Notices {
+    fn render(mut self, area: Rect, buf: &mut Buffer) {
+        let remaining = self.queue.len();
+        let Some((title, message)) = self.queue.pop_front() else {
+            return;
+        };
+        let center_constraints = [
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+        ];
+        let vertical_center = Layout::new(Direction::Vertical, center_constraints).split(area)[1];
+        let area = Layout::new(Direction::Horizontal, center_constraints).split(vertical_center)[1];
+
+        let title = if remaining > 1 {
+            format!("{title} (1 of {remaining})")
+        } else {
+            title
+        };
+        Clear.render(area, buf);
+        Paragraph::new(message)
+            .style(STYLE_DEFAULT)
+            .centered()
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::new()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .style(STYLE_DEFAULT),
+            )
+            .render(area, buf);
+    }
+}
@@ -11,7 +11,7 @@ use tokio::sync::RwLock;
 use tui_input::{backend::crossterm::EventHandler, StateChanged};
 
 use crate::{
-    constants::{ByteSize, DownloadStatus, Percentage, Token},
+    constants::{ByteSize, DownloadStatus, Percentage, Token, UploadStatus},
     gui::windows::{FocusableWidget, SLSKWidget, WidgetWithHints},
     styles::STYLE_DEFAULT,
     utils::num_as_str,
@@ -31,6 +31,8 @@ pub(crate) enum ColumnData {
     ByteSize(Arc<RwLock<ByteSize>>),
     DownloadStatus(Arc<RwLock<DownloadStatus>>),
     DownloadStatuses(Vec<Arc<RwLock<DownloadStatus>>>),
+    UploadStatus(Arc<RwLock<UploadStatus>>),
+    UploadStatuses(Vec<Arc<RwLock<UploadStatus>>>),
     Percentage(Arc<RwLock<Percentage>>),
     // we use u64 because this is used for filesizes
     Percentages(Vec<(Arc<RwLock<Percentage>>, u64)>),
@@ -89,6 +91,28 @@ impl Add for ColumnData {
                             )
                 }
             }
+            ColumnData::UploadStatus(u) => {
+                if let ColumnData::UploadStatus(u2) = rhs {
+                    (*u.blocking_read() + *u2.blocking_read()).into()
+                } else {
+                    unimplemented!(
+                                "UploadStatus variant can only be added to UploadStatus and Empty, you tried to add {rhs:?}"
+                            )
+                }
+            }
+            ColumnData::UploadStatuses(mut statuses) => {
+                if let ColumnData::UploadStatuses(mut statuses2) = rhs {
+                    statuses.append(&mut statuses2);
+                    ColumnData::UploadStatuses(statuses)
+                } else if let ColumnData::UploadStatus(status) = rhs {
+                    statuses.push(status);
+                    ColumnData::UploadStatuses(statuses)
+                } else {
+                    unimplemented!(
+                                "UploadStatuses variant can only be added to UploadStatuses, UploadStatus and Empty, you tried to add {rhs:?}"
+                            )
+                }
+            }
             ColumnData::Percentage(p) => {
                 if let ColumnData::Percentage(p2) = rhs {
                     (*p.blocking_read() + *p2.blocking_read()).into()
@@ -128,6 +152,13 @@ impl PartialEq for ColumnData {
                 .iter()
                 .zip(r0)
                 .all(|(s, s2)| *s.blocking_read() == *s2.blocking_read()),
+            (Self::UploadStatus(l0), Self::UploadStatus(r0)) => {
+                *l0.blocking_read() == *r0.blocking_read()
+            }
+            (Self::UploadStatuses(l0), Self::UploadStatuses(r0)) => l0
+                .iter()
+                .zip(r0)
+                .all(|(s, s2)| *s.blocking_read() == *s2.blocking_read()),
             (Self::Percentage(l0), Self::Percentage(r0)) => {
                 *l0.blocking_read() == *r0.blocking_read()
             }
@@ -164,6 +195,13 @@ impl PartialOrd for ColumnData {
                 self.merge_download_statuses()
                     .cmp(&Self::merge_download_statuses(&other)),
             ),
+            (ColumnData::UploadStatus(a), ColumnData::UploadStatus(b)) => {
+                Some(a.blocking_read().cmp(&b.blocking_read()))
+            }
+            (ColumnData::UploadStatuses(_), ColumnData::UploadStatuses(_)) => Some(
+                self.merge_upload_statuses()
+                    .cmp(&Self::merge_upload_statuses(other)),
+            ),
             (ColumnData::Percentage(a), ColumnData::Percentage(b)) => {
                 Some(a.blocking_read().cmp(&b.blocking_read()))
             }
@@ -194,6 +232,8 @@ impl ToString for ColumnData {
             ColumnData::ByteSize(v) => v.blocking_read().to_string(),
             ColumnData::DownloadStatus(v) => v.blocking_read().to_string(),
             ColumnData::DownloadStatuses(_) => self.merge_download_statuses().to_string(),
+            ColumnData::UploadStatus(v) => v.blocking_read().to_string(),
+            ColumnData::UploadStatuses(_) => self.merge_upload_statuses().to_string(),
             ColumnData::Percentage(v) => v.blocking_read().to_string(),
             ColumnData::Percentages(_) => self.merge_percentages().to_string(),
             ColumnData::Token(v) => v.to_string(),
@@ -237,6 +277,12 @@ impl From<DownloadStatus> for ColumnData {
     }
 }
 
+impl From<UploadStatus> for ColumnData {
+    fn from(value: UploadStatus) -> Self {
+        ColumnData::UploadStatus(Arc::new(RwLock::new(value)))
+    }
+}
+
 impl From<Percentage> for ColumnData {
     fn from(value: Percentage) -> Self {
         ColumnData::Percentage(Arc::new(RwLock::new(value)))
@@ -254,6 +300,8 @@ pub(crate) struct TableItem {
     pub(crate) content: Vec<ColumnData>,
     pub(crate) children: Vec<TableItem>,
     is_open: bool,
+    /// Overrides the table's style for this row
+    style: Option<Style>,
 }
 
 impl Default for TableItem {
@@ -262,6 +310,7 @@ impl Default for TableItem {
             content: Vec::new(),
             children: Vec::new(),
             is_open: false,
+            style: None,
         }
     }
 }
@@ -272,6 +321,7 @@ impl TableItem {
             content,
             children,
             is_open: false,
+            style: None,
         }
     }
 
@@ -333,6 +383,19 @@ impl TableItem {
         self.is_open = true;
         self
     }
+
+    pub(crate) fn style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    /// Goes back to the table's style, for this row and all of its children
+    pub(crate) fn clear_style(&mut self) {
+        self.style = None;
+        for child in &mut self.children {
+            child.clear_style();
+        }
+    }
 }
 
 impl ColumnData {
@@ -346,6 +409,16 @@ impl ColumnData {
         }
     }
 
+    fn merge_upload_statuses(&self) -> UploadStatus {
+        match self {
+            ColumnData::UploadStatuses(statuses) => statuses
+                .iter()
+                .map(|s| *s.blocking_read())
+                .sum::<UploadStatus>(),
+            _ => unimplemented!("Only use this on UploadStatuses"),
+        }
+    }
+
     fn merge_percentages(&self) -> Percentage {
         match self {
             ColumnData::Percentages(percentages) => {
@@ -668,11 +741,12 @@ impl TableWidget<'_> {
                     };
                     content.insert(0, toggle.to_string());
 
+                    let style = item.style.unwrap_or(self.style);
                     rows.push(Row::new(content).style(
                         if self.selected_row == current_row as usize {
-                            self.style.add_modifier(Modifier::REVERSED)
+                            style.add_modifier(Modifier::REVERSED)
                         } else {
-                            self.style
+                            style
                         },
                     ));
                     rows_rendered += 1;
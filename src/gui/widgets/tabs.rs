@@ -76,7 +76,13 @@ impl<'a> Tabs<'a> {
 
     pub(crate) fn remove_selected_tab(&mut self) {
         if !self.tabs.is_empty() {
-            self.tabs.remove(self.selected);
+            self.removed_tab = Some(self.tabs.remove(self.selected));
+            // keep the current tab pointing at the same tab, or the nearest one if it was removed
+            if (self.current > self.selected)
+                || (self.current != 0 && self.current >= self.tabs.len())
+            {
+                self.current -= 1;
+            }
             if (self.selected != 0) && (self.selected >= self.tabs.len()) {
                 self.selected -= 1;
             }
@@ -214,6 +220,9 @@ impl EventHandler for Tabs<'_> {
                     return Some(TAB_CHANGED);
                 } else if key.code == KeyCode::Backspace {
                     self.remove_selected_tab();
+                    if self.removed_tab.is_some() {
+                        return Some(TAB_REMOVED);
+                    }
                 }
             }
         }
@@ -0,0 +1,322 @@
+pub(crate) use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::{Constraint, Direction, Layout, Rect},
+    text::Line,
+    widgets::Widget,
+};
+use std::path::Path;
+use tokio::sync::broadcast::Sender;
+use tui_input::backend::crossterm::EventHandler;
+
+use crate::{
+    events::SLSKEvents,
+    gui::widgets::{input::Input, table::TableWidget},
+    listing::ShareListing,
+    messages::{Directory, SharedFileListResponse},
+    sql::ShareStats,
+    styles::{STYLE_DEFAULT, STYLE_FAIL_DEFAULT},
+    utils::{num_as_bytes, num_as_str},
+};
+
+use super::{shares::browse_table, FocusableWidget, SLSKWidget, WidgetWithHints, Window};
+
+/// Keeps the files whose path contains every word of `query`, ignoring case
+fn filter_directories(directories: &[Directory], query: &str) -> Vec<Directory> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    directories
+        .iter()
+        .filter_map(|directory| {
+            let files: Vec<_> = directory
+                .files
+                .iter()
+                .filter(|file| {
+                    let path = format!("{}\\{}", directory.path, file.filename).to_lowercase();
+                    words.iter().all(|word| path.contains(word))
+                })
+                .cloned()
+                .collect();
+            (!files.is_empty()).then(|| Directory {
+                path: directory.path.clone(),
+                files,
+            })
+        })
+        .collect()
+}
+
+/// Browses other users' shares, or listings of them that were exported earlier
+#[derive(Clone)]
+pub(crate) struct BrowseWindow<'a> {
+    title: String,
+    focus_index: u8,
+    pub(crate) browse_input: Input<'a>,
+    pub(crate) filter_input: Input<'a>,
+    /// Whose shares these are, and when they were exported if they were loaded from a file
+    source: Option<(String, Option<String>)>,
+    file_list: Option<SharedFileListResponse>,
+    stats: ShareStats,
+    /// The filter being applied and how many files it matched
+    filter: Option<(String, usize)>,
+    table: TableWidget<'a>,
+    /// The last thing that was saved or went wrong, and whether it was an error
+    status: Option<(String, bool)>,
+}
+
+impl Default for BrowseWindow<'_> {
+    fn default() -> Self {
+        Self {
+            title: String::from(" Browse "),
+            focus_index: 0,
+            browse_input: Input::default().title(String::from("Browse User or Open Listing")),
+            filter_input: Input::default().title(String::from("Filter")),
+            source: None,
+            file_list: None,
+            stats: ShareStats::default(),
+            filter: None,
+            table: browse_table(&[], &[]),
+            status: None,
+        }
+    }
+}
+
+impl BrowseWindow<'_> {
+    pub(crate) fn set_listing(
+        &mut self,
+        username: String,
+        file_list: SharedFileListResponse,
+        exported_at: Option<String>,
+    ) {
+        self.stats = ShareStats::new(&file_list);
+        self.table = browse_table(&file_list.directories, &file_list.priv_directories);
+        self.source = Some((username, exported_at));
+        self.file_list = Some(file_list);
+        self.filter = None;
+        self.status = None;
+    }
+
+    pub(crate) fn set_status(&mut self, status: String) {
+        self.status = Some((status, false));
+    }
+
+    /// Opens `value` if it's a listing, otherwise asks the user called `value` for their shares
+    fn browse(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let value = self.browse_input.input.value().trim().to_string();
+        if value.is_empty() {
+            return;
+        }
+        let path = Path::new(&value);
+        if path.is_file() {
+            match ShareListing::load(path) {
+                Ok(listing) => {
+                    let username = listing.username.clone().unwrap_or_else(|| {
+                        path.file_stem()
+                            .map(|stem| stem.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    });
+                    self.set_listing(
+                        username,
+                        listing.file_list(),
+                        Some(listing.exported_at.clone()),
+                    );
+                }
+                Err(e) => self.status = Some((format!("Couldn't open {value}: {e}"), true)),
+            }
+        } else {
+            let _ = write_queue.send(SLSKEvents::BrowseUser { username: value });
+        }
+        self.browse_input.clear();
+    }
+
+    fn apply_filter(&mut self) {
+        let Some(file_list) = &self.file_list else {
+            return;
+        };
+        let query = self.filter_input.input.value().trim().to_string();
+        if query.is_empty() {
+            self.clear_filter();
+            return;
+        }
+        let directories = filter_directories(&file_list.directories, &query);
+        let priv_directories = filter_directories(&file_list.priv_directories, &query);
+        let matches = directories
+            .iter()
+            .chain(&priv_directories)
+            .map(|directory| directory.files.len())
+            .sum();
+        self.table = browse_table(&directories, &priv_directories);
+        self.filter = Some((query, matches));
+    }
+
+    fn clear_filter(&mut self) {
+        self.filter_input.clear();
+        self.filter = None;
+        if let Some(file_list) = &self.file_list {
+            self.table = browse_table(&file_list.directories, &file_list.priv_directories);
+        }
+    }
+
+    /// Saves shares that were browsed live, listings that were opened are already saved
+    fn export(&mut self) {
+        let (Some((username, None)), Some(file_list)) = (&self.source, &self.file_list) else {
+            return;
+        };
+        self.status = Some(
+            match ShareListing::new(Some(username.clone()), file_list).export() {
+                Ok(path) => (format!("Saved to {}", path.to_string_lossy()), false),
+                Err(e) => (format!("Couldn't save the listing: {e}"), true),
+            },
+        );
+    }
+
+    fn table_title(&self) -> Line<'static> {
+        if let Some((status, is_error)) = &self.status {
+            return Line::styled(
+                status.clone(),
+                if *is_error {
+                    STYLE_FAIL_DEFAULT
+                } else {
+                    STYLE_DEFAULT
+                },
+            );
+        }
+        let Some((username, exported_at)) = &self.source else {
+            return Line::from("Shares");
+        };
+        let mut title = format!(
+            "{username}: {} folders, {} files, {}",
+            num_as_str(self.stats.folders),
+            num_as_str(self.stats.files),
+            num_as_bytes(self.stats.size)
+        );
+        if let Some(exported_at) = exported_at {
+            title.push_str(&format!(" (exported {exported_at})"));
+        }
+        if let Some((query, matches)) = &self.filter {
+            title.push_str(&format!(
+                ", \"{query}\" matches {} files",
+                num_as_str(*matches)
+            ));
+        }
+        Line::from(title)
+    }
+}
+
+impl Widget for BrowseWindow<'_> {
+    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let chunks = Layout::new(
+            Direction::Vertical,
+            [Constraint::Length(3), Constraint::Min(0)],
+        )
+        .split(area);
+        let input_chunks = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Fill(1), Constraint::Fill(1)],
+        )
+        .split(chunks[0]);
+
+        let title = self.table_title();
+        let mut table = self.table.title(title);
+        render_widgets!(
+            SELF: self,
+            BUFFER: buf,
+            0 = (self.browse_input) => input_chunks[0],
+            1 = (self.filter_input) => input_chunks[1],
+            2 = (table) => chunks[1],
+        );
+    }
+}
+
+impl WidgetWithHints for BrowseWindow<'_> {
+    fn get_hints(&self) -> Vec<(Event, String)> {
+        let mut hints = self
+            .get_widget(self.focus_index)
+            .map(|w| w.get_hints())
+            .unwrap_or_default();
+        match self.focus_index {
+            0 => hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Browse user or open listing"),
+            )),
+            1 => {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                    String::from("Filter"),
+                ));
+                if self.filter.is_some() {
+                    hints.push((
+                        Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+                        String::from("Clear filter"),
+                    ));
+                }
+            }
+            2 => {
+                if let Some((_, None)) = self.source {
+                    hints.push((
+                        Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE)),
+                        String::from("Export listing"),
+                    ));
+                }
+            }
+            _ => (),
+        }
+        hints
+    }
+}
+
+impl Window<'_> for BrowseWindow<'_> {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn perform_action(&mut self, focus_index: u8, event: Event, write_queue: &Sender<SLSKEvents>) {
+        let key = match event {
+            Event::Key(key) if key.modifiers == KeyModifiers::NONE => Some(key.code),
+            _ => None,
+        };
+        match focus_index {
+            0 => match key {
+                Some(KeyCode::Enter) => self.browse(write_queue),
+                _ => {
+                    self.browse_input.handle_event(&event);
+                }
+            },
+            1 => match key {
+                Some(KeyCode::Enter) => self.apply_filter(),
+                Some(KeyCode::Esc) => self.clear_filter(),
+                _ => {
+                    self.filter_input.handle_event(&event);
+                }
+            },
+            2 => match key {
+                Some(KeyCode::Char('x')) => self.export(),
+                _ => {
+                    self.table.handle_event(&event);
+                }
+            },
+            _ => unimplemented!("perform_action({focus_index}, {event:?})"),
+        };
+    }
+
+    fn number_of_widgets(&self) -> u8 {
+        3
+    }
+
+    fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
+        match index {
+            0 => Some(&self.browse_input),
+            1 => Some(&self.filter_input),
+            2 => Some(&self.table),
+            _ => unimplemented!(
+                "There are only {} widgets, it's impossible to get the widget with index {index}",
+                self.number_of_widgets()
+            ),
+        }
+    }
+
+    fn get_focused_index(&self) -> u8 {
+        self.focus_index
+    }
+
+    fn set_focused_index(&mut self, index: u8) {
+        self.focus_index = index;
+    }
+}
@@ -1,30 +1,44 @@
-use std::mem;
+use std::{
+    mem,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use ordered_hash_map::OrderedHashMap;
 use ratatui::{
     prelude::{Constraint, Direction, Layout, Rect},
-    widgets::Widget,
+    widgets::{Paragraph, Widget},
 };
 use tokio::sync::broadcast::Sender;
 use tui_input::backend::crossterm::EventHandler;
 
 use crate::{
-    constants::ByteSize,
+    constants::{ByteSize, UserStatusCodes},
     events::SLSKEvents,
     gui::widgets::{
         chatrooms::ChatroomState,
-        dropdown::{Dropdown, DropdownHeader},
+        dialog::Dialog,
+        dropdown::{Dropdown, DropdownHeader, DropdownItem, DropwdownTrait},
         input::Input,
         list::List,
-        table::{TableItem, TableWidget},
+        table::{ColumnData, TableItem, TableWidget},
         tabs::{Tabs, TAB_CHANGED, TAB_REMOVED},
     },
     messages::UserStats,
+    styles::{STYLE_DEFAULT_LOW_CONTRAST, STYLE_DISABLED_DEFAULT},
+    utils::now_as_string,
 };
 
 use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
 
+/// The rooms dropdown lists private rooms under this item
+const PRIVATE_ROOMS: &str = "Private Rooms";
+/// Typed in the message input to set our ticker for the room, on its own it clears it
+const TICKER_COMMAND: &str = "/ticker";
+/// How long each ticker is shown for before moving on to the next one
+const TICKER_SECONDS: u64 = 5;
+
 #[derive(Clone)]
 pub(crate) struct ChatroomsWindow<'a> {
     pub(crate) title: String,
@@ -32,39 +46,153 @@ pub(crate) struct ChatroomsWindow<'a> {
     pub(crate) room_name_tabs: Tabs<'a>,
     pub(crate) room_sidebar: TableWidget<'a>,
     pub(crate) rooms_dropdown: Dropdown<'a>,
+    pub(crate) new_room_input: Input<'a>,
     pub(crate) message_input: Input<'a>,
+    /// Only shown for private rooms
+    pub(crate) members_table: TableWidget<'a>,
+    pub(crate) member_input: Input<'a>,
+    /// Asks before disowning or dismembering a room, and sends the event it's holding if the answer's yes
+    pub(crate) room_dialog: Dialog<'a, SLSKEvents>,
     pub(crate) focus_index: u8,
+    /// Our username, to know what we're allowed to do in private rooms
+    pub(crate) username: String,
+    public_rooms: Vec<String>,
+    private_rooms: Vec<String>,
+    pub(crate) private_room_invitations: bool,
+    pub(crate) show_joins_and_leaves: bool,
+}
+
+fn members_table<'a>(state: Option<&ChatroomState>) -> TableWidget<'a> {
+    let mut rows = Vec::new();
+    if let Some(state) = state {
+        let mut members: Vec<&String> = state
+            .owner
+            .iter()
+            .chain(&state.operators)
+            .chain(&state.members)
+            .collect();
+        members.sort_by_key(|member| member.to_ascii_lowercase());
+        members.dedup();
+        rows = members
+            .into_iter()
+            .map(|member| {
+                let role = if state.owner.as_ref() == Some(member) {
+                    "Owner"
+                } else if state.is_operator(member) {
+                    "Operator"
+                } else {
+                    "Member"
+                };
+                TableItem::new(
+                    vec![member.clone().into(), String::from(role).into()],
+                    Vec::new(),
+                )
+            })
+            .collect();
+    }
+    TableWidget::new(
+        vec![String::from("Member"), String::from("Role")],
+        rows,
+        None,
+        Some(vec![Constraint::Length(30), Constraint::Length(8)]),
+    )
 }
 
 impl ChatroomsWindow<'_> {
     pub(crate) fn update_sidebar(&mut self) {
+        self.members_table = members_table(
+            self.get_current_chatroom_state()
+                .filter(|state| state.private),
+        );
+        // the private room widgets disappear when a public room is selected
+        if self.focus_index >= self.number_of_widgets() {
+            self.focus_index = 4;
+        }
+
         if !self.room_name_tabs.tabs.is_empty() {
-            let mut users = self
+            let state = &self
                 .chatrooms
                 .get(&self.room_name_tabs.tabs[self.room_name_tabs.current])
                 .unwrap()
-                .0
-                .users
-                .iter()
-                .collect::<Vec<(&String, &UserStats)>>();
+                .0;
+            let mut users = state.users.iter().collect::<Vec<(&String, &UserStats)>>();
             users.sort_by_key(|(username, _)| username.to_ascii_lowercase());
 
             self.room_sidebar.items = users
                 .iter()
                 .map(|(user, stats)| {
-                    TableItem::new(
+                    let item = TableItem::new(
                         vec![
                             user.to_string().into(),
                             ByteSize(stats.avg_speeds as u64).into(),
                             (stats.num_of_files as usize).into(),
                         ],
                         Vec::new(),
-                    )
+                    );
+                    match state.statuses.get(*user) {
+                        Some(UserStatusCodes::Away) => item.style(STYLE_DEFAULT_LOW_CONTRAST),
+                        Some(UserStatusCodes::Offline) => item.style(STYLE_DISABLED_DEFAULT),
+                        _ => item,
+                    }
                 })
                 .collect()
         } else {
             self.room_sidebar.items = Vec::new();
         }
+        // users leave, so the selected row might not be there any more
+        self.room_sidebar.length = self.room_sidebar.items.len();
+        self.room_sidebar.selected_row = self
+            .room_sidebar
+            .selected_row
+            .min(self.room_sidebar.length.saturating_sub(1));
+    }
+
+    /// Applies a user's status to every room they're in
+    pub(crate) fn set_user_status(&mut self, username: &str, status: UserStatusCodes) {
+        for (_, (state, _)) in self.chatrooms.iter_mut() {
+            if state.users.contains_key(username) {
+                state.set_status(username.to_string(), status.clone());
+            }
+        }
+        self.update_sidebar();
+    }
+
+    /// Applies a user's stats to every room they're in
+    pub(crate) fn set_user_stats(&mut self, username: &str, stats: UserStats) {
+        for (_, (state, _)) in self.chatrooms.iter_mut() {
+            if let Some(user_stats) = state.users.get_mut(username) {
+                *user_stats = stats.clone();
+            }
+        }
+        self.update_sidebar();
+    }
+
+    /// Keeps a room's user list up to date as users join and leave it
+    pub(crate) fn user_joined_or_left(
+        &mut self,
+        room: &str,
+        username: String,
+        joined: Option<(UserStatusCodes, UserStats)>,
+    ) {
+        let show_joins_and_leaves = self.show_joins_and_leaves;
+        let state = self.get_or_add_chatroom_state(room);
+        let line = match joined {
+            Some((status, stats)) => {
+                state.add_user(username.clone(), stats);
+                state.set_status(username.clone(), status);
+                format!("[{}] {username} joined the room", now_as_string())
+            }
+            None => {
+                state.remove_user(username.clone());
+                format!("[{}] {username} left the room", now_as_string())
+            }
+        };
+        if show_joins_and_leaves {
+            state.add_message(line);
+        }
+        if self.room_name_tabs.current_tab().map(String::as_str) == Some(room) {
+            self.update_sidebar();
+        }
     }
 
     pub(crate) fn get_current_chatroom_state(&self) -> Option<&ChatroomState> {
@@ -86,6 +214,205 @@ impl ChatroomsWindow<'_> {
             None => None,
         }
     }
+
+    /// Gets the state for `room`, adding it if it's a room we haven't seen before
+    pub(crate) fn get_or_add_chatroom_state(&mut self, room: &str) -> &mut ChatroomState {
+        if !self.chatrooms.contains_key(room) {
+            self.chatrooms.insert(
+                room.to_string(),
+                (ChatroomState::default(), List::default()),
+            );
+        }
+        &mut self.chatrooms.get_mut(room).unwrap().0
+    }
+
+    /// `rooms` and `private_rooms` should be sorted the way they're going to be listed
+    pub(crate) fn set_rooms(
+        &mut self,
+        rooms: Vec<String>,
+        private_rooms: Vec<String>,
+        owned_rooms: Vec<String>,
+        operated_rooms: Vec<String>,
+    ) {
+        for room in &rooms {
+            self.get_or_add_chatroom_state(room);
+        }
+        let username = self.username.clone();
+        for room in &private_rooms {
+            let state = self.get_or_add_chatroom_state(room);
+            state.private = true;
+            if owned_rooms.contains(room) {
+                state.owner = Some(username.clone());
+            }
+            if operated_rooms.contains(room) {
+                state.set_operator(username.clone(), true);
+            }
+        }
+        self.public_rooms = rooms;
+        self.private_rooms = private_rooms;
+        self.update_rooms_dropdown();
+    }
+
+    /// We were added to or removed from a private room
+    pub(crate) fn set_private_room_access(&mut self, room: String, added: bool) {
+        self.private_rooms
+            .retain(|private_room| *private_room != room);
+        let state = self.get_or_add_chatroom_state(&room);
+        state.private = true;
+        if added {
+            state.add_message(String::from("You were added to this room"));
+            self.private_rooms.push(room);
+        } else {
+            state.add_message(String::from("You were removed from this room"));
+        }
+        self.update_rooms_dropdown();
+        self.update_sidebar();
+    }
+
+    fn update_rooms_dropdown(&mut self) {
+        let mut children: Vec<DropdownItem> =
+            self.public_rooms.iter().map(DropdownItem::empty).collect();
+        if !self.private_rooms.is_empty() {
+            children.push(DropdownItem::flat(
+                PRIVATE_ROOMS,
+                self.private_rooms.clone(),
+            ));
+        }
+        self.rooms_dropdown.children = children;
+    }
+
+    fn join_room(&mut self, room: String, private: bool, write_queue: &Sender<SLSKEvents>) {
+        let state = self.get_or_add_chatroom_state(&room);
+        state.private |= private;
+        if !self.room_name_tabs.tabs.contains(&room) {
+            self.room_name_tabs.add_tab(room.clone());
+            let _ = write_queue.send(SLSKEvents::JoinRoom {
+                room,
+                private: private as u32,
+            });
+        }
+    }
+
+    /// Joining a private room that doesn't exist creates it, owned by us
+    fn create_room(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let room = self.new_room_input.input.value().trim().to_string();
+        if room.is_empty() {
+            return;
+        }
+        if !self.private_rooms.contains(&room) {
+            self.private_rooms.push(room.clone());
+            self.update_rooms_dropdown();
+        }
+        self.join_room(room, true, write_queue);
+        self.new_room_input.clear();
+    }
+
+    /// The ticker to show above the chat, they take turns if the room has more than one
+    fn current_ticker(&self) -> Option<String> {
+        let tickers = &self.get_current_chatroom_state()?.tickers;
+        if tickers.is_empty() {
+            return None;
+        }
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let index = (seconds / TICKER_SECONDS) as usize % tickers.len();
+        let (username, ticker) = tickers.iter().nth(index)?;
+        Some(if tickers.len() > 1 {
+            format!("[{}/{}] {username}: {ticker}", index + 1, tickers.len())
+        } else {
+            format!("{username}: {ticker}")
+        })
+    }
+
+    fn send_message(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let Some(room) = self.room_name_tabs.current_tab() else {
+            return;
+        };
+        // we don't need to handle password values, because message_input will never be a password input
+        let message = self.message_input.input.value().to_string();
+        let event = match message.strip_prefix(TICKER_COMMAND) {
+            Some(ticker) if ticker.is_empty() || ticker.starts_with(' ') => {
+                SLSKEvents::SetRoomTicker {
+                    room: room.to_string(),
+                    ticker: ticker.trim().to_string(),
+                }
+            }
+            _ => SLSKEvents::ChatroomMessage {
+                room: room.to_string(),
+                username: None,
+                message,
+            },
+        };
+        let _ = write_queue.send(event);
+        self.message_input.clear();
+    }
+
+    /// Whether we own the current room, and whether we can manage its members
+    fn permissions(&self) -> (bool, bool) {
+        match self.get_current_chatroom_state() {
+            Some(state) if state.private => {
+                let owner = state.owner.as_deref() == Some(self.username.as_str());
+                (owner, owner || state.is_operator(&self.username))
+            }
+            _ => (false, false),
+        }
+    }
+
+    fn selected_member(&self) -> Option<(String, ColumnData)> {
+        self.members_table
+            .current_row()
+            .map(|row| (row.content[0].to_string(), row.content[1].clone()))
+    }
+
+    fn ask(&mut self, question: String, event: SLSKEvents) {
+        self.room_dialog.set_question(question);
+        self.room_dialog.state = Some(event);
+        self.room_dialog.show();
+    }
+
+    fn manage_members(&mut self, event: &Event, write_queue: &Sender<SLSKEvents>) {
+        let key = match event {
+            Event::Key(key) if key.modifiers == KeyModifiers::NONE => Some(key.code),
+            _ => None,
+        };
+        let (Some(room), (owner, manager)) = (
+            self.room_name_tabs.current_tab().cloned(),
+            self.permissions(),
+        ) else {
+            return;
+        };
+        match key {
+            Some(KeyCode::Delete) if manager => {
+                if let Some((username, role)) = self.selected_member() {
+                    // operators can only remove ordinary members
+                    if owner || role.to_string() == "Member" {
+                        let _ = write_queue.send(SLSKEvents::RemoveRoomMember { room, username });
+                    }
+                }
+            }
+            Some(KeyCode::Char('o')) if owner => {
+                if let Some((username, role)) = self.selected_member() {
+                    let _ = write_queue.send(match role.to_string().as_str() {
+                        "Operator" => SLSKEvents::RemoveRoomOperator { room, username },
+                        _ => SLSKEvents::AddRoomOperator { room, username },
+                    });
+                }
+            }
+            Some(KeyCode::Char('d')) if owner => self.ask(
+                format!("Give up ownership of {room}? Its members can still join it."),
+                SLSKEvents::DisownRoom { room },
+            ),
+            Some(KeyCode::Char('x')) if owner => self.ask(
+                format!("Remove everyone from {room}? This closes the room."),
+                SLSKEvents::DismemberRoom { room },
+            ),
+            _ => {
+                self.members_table.handle_event(event);
+            }
+        }
+    }
 }
 
 impl Default for ChatroomsWindow<'_> {
@@ -109,8 +436,22 @@ impl Default for ChatroomsWindow<'_> {
                 ]),
             ),
             rooms_dropdown: Dropdown::new(DropdownHeader::Title("All Rooms"), Vec::new()),
+            new_room_input: Input::default().title(String::from("New Private Room")),
             message_input: Input::default().title(String::from("Message Input")),
+            members_table: members_table(None),
+            member_input: Input::default().title(String::from("Add Member")),
+            room_dialog: Dialog::default().yes_no_funcs(
+                Some(Rc::new(|_, (write_queue, event)| {
+                    let _ = write_queue.send(event);
+                })),
+                None,
+            ),
             focus_index: 0,
+            username: String::new(),
+            public_rooms: Vec::new(),
+            private_rooms: Vec::new(),
+            private_room_invitations: true,
+            show_joins_and_leaves: false,
         }
     }
 }
@@ -145,9 +486,22 @@ impl<'a> Widget for ChatroomsWindow<'a> {
 
         let above_room_area = Layout::new(
             Direction::Horizontal,
-            [Constraint::Min(0), Constraint::Length(13)],
+            [
+                Constraint::Min(0),
+                Constraint::Length(13),
+                Constraint::Length(24),
+            ],
         )
         .split(message_area[0]);
+        let sidebar_area = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+                Constraint::Length(3),
+            ],
+        )
+        .split(main_area[1]);
 
         let message_len: Option<usize>;
         let mut messages = List::new(match self.get_current_chatroom_state() {
@@ -161,26 +515,102 @@ impl<'a> Widget for ChatroomsWindow<'a> {
             }
         });
         messages.state.select(message_len);
-        messages.render(message_area[1], buf);
-
-        render_widgets!(
-            SELF: self,
-            BUFFER: buf,
-            0 = (self.room_name_tabs) => above_room_area[0],
-            1 = (self.rooms_dropdown) => above_room_area[1],
-            2 = (self.message_input) => message_area[2],
-            3 = (self.room_sidebar) => main_area[1],
-        );
+        match self.current_ticker() {
+            Some(ticker) => {
+                let ticker_area = Layout::new(
+                    Direction::Vertical,
+                    [Constraint::Length(1), Constraint::Min(0)],
+                )
+                .split(message_area[1]);
+                Paragraph::new(ticker)
+                    .style(STYLE_DEFAULT_LOW_CONTRAST)
+                    .render(ticker_area[0], buf);
+                messages.render(ticker_area[1], buf);
+            }
+            None => messages.render(message_area[1], buf),
+        }
+
+        if self.number_of_widgets() == 7 {
+            render_widgets!(
+                SELF: self,
+                BUFFER: buf,
+                0 = (self.room_name_tabs) => above_room_area[0],
+                1 = (self.rooms_dropdown) => above_room_area[1],
+                2 = (self.new_room_input) => above_room_area[2],
+                3 = (self.message_input) => message_area[2],
+                4 = (self.room_sidebar) => sidebar_area[0],
+                5 = (self.members_table) => sidebar_area[1],
+                6 = (self.member_input) => sidebar_area[2],
+            );
+        } else {
+            render_widgets!(
+                SELF: self,
+                BUFFER: buf,
+                0 = (self.room_name_tabs) => above_room_area[0],
+                1 = (self.rooms_dropdown) => above_room_area[1],
+                2 = (self.new_room_input) => above_room_area[2],
+                3 = (self.message_input) => message_area[2],
+                4 = (self.room_sidebar) => main_area[1],
+            );
+        }
+
+        if self.room_dialog.visible {
+            self.room_dialog.render(area, buf);
+        }
     }
 }
 
 impl WidgetWithHints for ChatroomsWindow<'_> {
     fn get_hints(&self) -> Vec<(Event, String)> {
-        if let Some(widget) = self.get_widget(self.focus_index) {
+        if self.room_dialog.visible {
+            return self.room_dialog.get_hints();
+        }
+        let mut hints = if let Some(widget) = self.get_widget(self.focus_index) {
             widget.get_hints()
         } else {
             Vec::new()
+        };
+        let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let (owner, manager) = self.permissions();
+        match self.focus_index {
+            1 => hints.push((
+                key(KeyCode::Char('i')),
+                String::from(if self.private_room_invitations {
+                    "Refuse private room invitations"
+                } else {
+                    "Accept private room invitations"
+                }),
+            )),
+            2 => hints.push((
+                key(KeyCode::Enter),
+                String::from("Create or join private room"),
+            )),
+            3 => hints.push((
+                key(KeyCode::Enter),
+                format!("Send message ({TICKER_COMMAND} [text] sets or clears your ticker)"),
+            )),
+            4 => hints.push((
+                key(KeyCode::Char('j')),
+                String::from(if self.show_joins_and_leaves {
+                    "Hide joins and leaves"
+                } else {
+                    "Show joins and leaves"
+                }),
+            )),
+            5 => {
+                if manager {
+                    hints.push((key(KeyCode::Delete), String::from("Remove member")));
+                }
+                if owner {
+                    hints.push((key(KeyCode::Char('o')), String::from("Toggle operator")));
+                    hints.push((key(KeyCode::Char('d')), String::from("Disown room")));
+                    hints.push((key(KeyCode::Char('x')), String::from("Dismember room")));
+                }
+            }
+            6 if manager => hints.push((key(KeyCode::Enter), String::from("Add member"))),
+            _ => (),
         }
+        hints
     }
 }
 
@@ -206,54 +636,99 @@ impl Window<'_> for ChatroomsWindow<'_> {
                 None
             }
             1 => {
+                if event == Event::Key(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE)) {
+                    self.private_room_invitations = !self.private_room_invitations;
+                    let _ = write_queue.send(SLSKEvents::SetPrivateRoomInvitations {
+                        enabled: self.private_room_invitations,
+                    });
+                    return;
+                }
                 let out = self.rooms_dropdown.handle_event(&event);
-                if self.rooms_dropdown.fetched_text.is_some() {
-                    let room = self.rooms_dropdown.fetched_text.as_ref().unwrap();
-                    if !self.room_name_tabs.tabs.contains(&room) {
-                        self.room_name_tabs.add_tab(room.to_string());
-                        let _ = write_queue.send(SLSKEvents::JoinRoom {
-                            room: room.to_string(),
-                            private: 0,
-                        });
+                if let Some(room) = self.rooms_dropdown.fetched_text.take() {
+                    // the private rooms heading is the only item with children, a public room can share its name
+                    let on_heading = self.rooms_dropdown.depth == 1
+                        && self
+                            .rooms_dropdown
+                            .children
+                            .get(self.rooms_dropdown.selected)
+                            .is_some_and(|item| !item.get_children().is_empty());
+                    if !on_heading {
+                        let private = self.private_rooms.contains(&room);
+                        self.join_room(room, private, write_queue);
                     }
-                    self.rooms_dropdown.fetched_text = None;
                 };
                 out
             }
             2 => {
                 if event == Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)) {
-                    match self.room_name_tabs.current_tab() {
-                        Some(room) => {
-                            let _ = write_queue.send(SLSKEvents::ChatroomMessage {
-                                room: room.to_string(),
-                                username: None,
-                                // we don't need to handle password values, because message_input will never be a password input
-                                message: self.message_input.input.value().to_string(),
+                    self.create_room(write_queue);
+                    None
+                } else {
+                    self.new_room_input.handle_event(&event)
+                }
+            }
+            3 => {
+                if event == Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)) {
+                    self.send_message(write_queue);
+                    None
+                } else {
+                    self.message_input.handle_event(&event)
+                }
+            }
+            4 => {
+                if event == Event::Key(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE)) {
+                    self.show_joins_and_leaves = !self.show_joins_and_leaves;
+                    let _ = write_queue.send(SLSKEvents::SetShowJoinsAndLeaves {
+                        enabled: self.show_joins_and_leaves,
+                    });
+                    None
+                } else {
+                    self.room_sidebar.handle_event(&event)
+                }
+            }
+            5 => {
+                self.manage_members(&event, write_queue);
+                None
+            }
+            6 => {
+                if event == Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)) {
+                    let username = self.member_input.input.value().trim().to_string();
+                    if let (Some(room), (_, true)) =
+                        (self.room_name_tabs.current_tab(), self.permissions())
+                    {
+                        if !username.is_empty() {
+                            let _ = write_queue.send(SLSKEvents::AddRoomMember {
+                                room: room.clone(),
+                                username,
                             });
-                            self.message_input.clear();
-                            None
                         }
-                        None => None,
                     }
+                    self.member_input.clear();
+                    None
                 } else {
-                    self.message_input.handle_event(&event)
+                    self.member_input.handle_event(&event)
                 }
             }
-            3 => self.room_sidebar.handle_event(&event),
             _ => unimplemented!("perform_action({focus_index}, {event:?})"),
         };
     }
 
     fn number_of_widgets(&self) -> u8 {
-        4
+        match self.get_current_chatroom_state() {
+            Some(state) if state.private => 7,
+            _ => 5,
+        }
     }
 
     fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
         match index {
             0 => Some(&self.room_name_tabs),
             1 => Some(&self.rooms_dropdown),
-            2 => Some(&self.message_input),
-            3 => Some(&self.room_sidebar),
+            2 => Some(&self.new_room_input),
+            3 => Some(&self.message_input),
+            4 => Some(&self.room_sidebar),
+            5 => Some(&self.members_table),
+            6 => Some(&self.member_input),
             _ => unimplemented!(
                 "There are only {} widgets, it's impossible to get the widget with index {index}",
                 self.number_of_widgets()
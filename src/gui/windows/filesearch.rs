@@ -1,9 +1,15 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 pub(crate) use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ordered_hash_map::OrderedHashMap;
 use rand::random;
 use ratatui::{
     prelude::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     widgets::Widget,
 };
 use tokio::sync::broadcast::Sender;
@@ -18,12 +24,214 @@ use crate::{
         table::{ColumnData, TableItem, TableWidget, ITEM_INTERACTED},
         tabs::{Tabs, TAB_REMOVED},
     },
+    messages::{Directory, File, FileAttribute},
+    parsers::encoder_name,
+    styles::STYLE_DEFAULT,
     utils::default_results_table,
     FileSearchResponse, MessageTrait, QueueUpload, MAX_RESULTS,
 };
 
 use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
 
+/// Wishlist searches' tabs are named after the query with this in front
+const WISHLIST_PREFIX: &str = "Wishlist: ";
+/// Results a wishlist search hadn't found before
+const STYLE_NEW_RESULT: Style = STYLE_DEFAULT.add_modifier(Modifier::BOLD);
+
+/// Who a search is sent to
+enum SearchScope {
+    Everyone,
+    User(String),
+    Room(String),
+    Buddies,
+}
+
+impl SearchScope {
+    /// Splits `user:name query`, `room:name query` and `buddies: query` into who to search and what for.
+    /// Names with spaces in them can be quoted, like `user:"some one" query`.
+    fn parse(input: &str) -> (Self, String) {
+        let trimmed = input.trim_start();
+        if let Some(query) = trimmed.strip_prefix("buddies:") {
+            return (Self::Buddies, query.trim().to_string());
+        }
+        for (prefix, scope) in [
+            ("user:", Self::User as fn(String) -> Self),
+            ("room:", Self::Room),
+        ] {
+            if let Some(rest) = trimmed.strip_prefix(prefix) {
+                let (name, query) =
+                    match rest.strip_prefix('"').and_then(|rest| rest.split_once('"')) {
+                        Some(split) => split,
+                        None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+                    };
+                if !name.is_empty() {
+                    return (scope(name.to_string()), query.trim().to_string());
+                }
+            }
+        }
+        (Self::Everyone, input.to_string())
+    }
+
+    fn tab_name(&self, query: &str) -> String {
+        match self {
+            Self::Everyone => query.to_string(),
+            Self::User(username) => format!("{query} (user {username})"),
+            Self::Room(room) => format!("{query} (room {room})"),
+            Self::Buddies => format!("{query} (buddies)"),
+        }
+    }
+
+    fn search(self, query: String, token: u32) -> SLSKEvents {
+        match self {
+            Self::Everyone => SLSKEvents::FileSearch { query, token },
+            Self::User(username) => SLSKEvents::UserSearch {
+                username,
+                query,
+                token,
+            },
+            Self::Room(room) => SLSKEvents::RoomSearch { room, query, token },
+            Self::Buddies => SLSKEvents::BuddySearch { query, token },
+        }
+    }
+}
+
+fn wishlist_tab(query: &str) -> String {
+    format!("{WISHLIST_PREFIX}{query}")
+}
+
+/// The username and path of every file in a results table
+fn found_files(table: &TableWidget) -> HashSet<(String, String)> {
+    table
+        .items
+        .iter()
+        .flat_map(|user| &user.children)
+        .flat_map(|folder| &folder.children)
+        .map(|file| {
+            (
+                file.content[0].to_string(),
+                file.content[7].to_string() + &file.content[4].to_string(),
+            )
+        })
+        .collect()
+}
+
+/// The duration and bitrate a result row was listed with
+fn expected_attributes(item: &TableItem) -> Vec<FileAttribute> {
+    let value = |column: Option<&ColumnData>| match column {
+        Some(ColumnData::Usize(value)) => Some(*value.blocking_read() as u32),
+        _ => None,
+    };
+    [
+        value(item.content.get(8)).map(FileAttribute::Duration),
+        value(item.content.get(9)).map(FileAttribute::Bitrate),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// The columns of a file's result row, `folder` ends with its separator
+fn file_result_row(
+    username: String,
+    avg_speed: ByteSize,
+    queue_length: usize,
+    folder: String,
+    filename: String,
+    file: &File,
+) -> Vec<ColumnData> {
+    let encoder = file
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            FileAttribute::Encoder(id) => Some(
+                encoder_name(*id)
+                    .map(String::from)
+                    .unwrap_or_else(|| id.to_string())
+                    .into(),
+            ),
+            _ => None,
+        })
+        .unwrap_or(ColumnData::Empty);
+    // kept so finished downloads can be checked against them
+    let attribute = |wanted: fn(&FileAttribute) -> Option<u32>| {
+        file.attributes
+            .iter()
+            .find_map(wanted)
+            .map(|value| ColumnData::from(value as usize))
+            .unwrap_or(ColumnData::Empty)
+    };
+    let duration = attribute(|attribute| match attribute {
+        FileAttribute::Duration(duration) => Some(*duration),
+        _ => None,
+    });
+    let bitrate = attribute(|attribute| match attribute {
+        FileAttribute::Bitrate(bitrate) => Some(*bitrate),
+        _ => None,
+    });
+
+    vec![
+        username.into(),                 // username
+        avg_speed.into(),                // average speed
+        queue_length.into(),             // queue length
+        ColumnData::Empty,               // folder
+        filename.into(),                 // file name
+        ByteSize(file.file_size).into(), // file size
+        encoder,                         // encoder
+        folder.into(),
+        duration,
+        bitrate,
+    ]
+}
+
+/// The other results from the same user and folder as a file result
+fn result_siblings<'a>(table: &'a TableWidget, item: &TableItem) -> Vec<&'a TableItem> {
+    let (username, folder) = (item.content[0].to_string(), item.content[7].to_string());
+    table
+        .items
+        .iter()
+        .filter(|user| user.content[0].to_string() == username)
+        .flat_map(|user| &user.children)
+        .flat_map(|folder| &folder.children)
+        .filter(|file| file.content.get(7).map(|f| f.to_string()) == Some(folder.clone()))
+        .collect()
+}
+
+/// The CUE sheets and rip logs among the files in the same folder as a file result.
+/// Album images are hard to use without their sheet, so these get downloaded with them.
+fn companion_files(item: &TableItem, siblings: &[&TableItem]) -> Vec<TableItem> {
+    let filename = item.content[4].to_string();
+    let stem = filename.rsplit_once('.').map_or(filename.as_str(), |(stem, _)| stem);
+
+    let mut companions = Vec::new();
+    for extension in ["cue", "log"] {
+        let matches: Vec<&TableItem> = siblings
+            .iter()
+            .copied()
+            .filter(|file| {
+                file.content[4]
+                    .to_string()
+                    .rsplit_once('.')
+                    .is_some_and(|(_, e)| e.eq_ignore_ascii_case(extension))
+            })
+            .collect();
+        // prefer the one named after the file, but a lone sheet/log in the folder is for the whole album
+        let same_stem = matches.iter().copied().find(|file| {
+            file.content[4]
+                .to_string()
+                .rsplit_once('.')
+                .is_some_and(|(s, _)| s == stem)
+        });
+        let companion = same_stem.or(match matches.as_slice() {
+            [file] => Some(*file),
+            _ => None,
+        });
+        if let Some(file) = companion.filter(|file| file.content[4].to_string() != filename) {
+            companions.push(file.clone());
+        }
+    }
+    companions
+}
+
 #[derive(Clone)]
 pub(crate) struct FileSearchWindow<'a> {
     pub(crate) title: String,
@@ -32,7 +240,22 @@ pub(crate) struct FileSearchWindow<'a> {
     pub(crate) search_bar: Input<'a>,
     pub(crate) token_query_map: HashMap<String, u32>,
     pub(crate) results: HashMap<u32, (u32, TableWidget<'a>)>,
-    pub(crate) dialog: Dialog<'a, (TableItem, Option<bool>)>,
+    /// The item to download, the download type, and any files that should be downloaded alongside it
+    pub(crate) dialog: Dialog<'a, (TableItem, Option<bool>, Vec<TableItem>)>,
+    /// The question the dialog was opened with, and the token of the request for local copies of its files
+    dialog_question: (String, u32),
+    /// The tokens of searches that weren't sent to everyone, these can't be added to the wishlist
+    targeted_searches: HashSet<u32>,
+    /// Wishlist queries, and the username and path of every file their searches have found
+    wishlist: OrderedHashMap<String, HashSet<(String, String)>>,
+    /// The tokens of the wishlist searches' tabs
+    wishlist_tabs: HashSet<u32>,
+    /// The query each wishlist search was sent for
+    wishlist_tokens: HashMap<u32, String>,
+    /// How long to wait between wishlist searches, the server tells us this after logging in
+    pub(crate) wishlist_interval: Option<Duration>,
+    last_wishlist_search: Option<Instant>,
+    next_wishlist_index: usize,
 }
 
 impl FileSearchWindow<'_> {
@@ -54,7 +277,123 @@ impl FileSearchWindow<'_> {
         self.results.insert(token, (0, table));
     }
 
+    fn new_search_token(&self) -> u32 {
+        loop {
+            let token = random();
+            if !self.results.contains_key(&token) && !self.wishlist_tokens.contains_key(&token) {
+                break token;
+            }
+        }
+    }
+
+    /// Adds the wishlist from the config, each query with an empty tab for its results
+    pub(crate) fn set_wishlist(&mut self, queries: Vec<String>) {
+        for query in queries {
+            let token = self.new_search_token();
+            self.new_table(token, wishlist_tab(&query));
+            self.wishlist_tabs.insert(token);
+            self.wishlist.insert(query, HashSet::new());
+        }
+    }
+
+    fn save_wishlist(&self, write_queue: &Sender<SLSKEvents>) {
+        let _ = write_queue.send(SLSKEvents::SetWishlist {
+            queries: self.wishlist.keys().cloned().collect(),
+        });
+    }
+
+    /// Turns the selected search into a wishlist search, or back into a normal one.
+    /// The files it's already found aren't counted as new when a wishlist search finds them again.
+    fn toggle_wishlist(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let Some(tab) = self.search_tabs.selected_tab().cloned() else {
+            return;
+        };
+        let Some(&token) = self.token_query_map.get(&tab) else {
+            return;
+        };
+        if self.targeted_searches.contains(&token) {
+            return;
+        }
+        let is_wishlist = self.wishlist_tabs.contains(&token);
+        let (query, new_tab) = match tab.strip_prefix(WISHLIST_PREFIX).filter(|_| is_wishlist) {
+            Some(query) => (query.to_string(), query.to_string()),
+            None => (tab.clone(), wishlist_tab(&tab)),
+        };
+        if self.token_query_map.contains_key(&new_tab) {
+            return;
+        }
+        if is_wishlist {
+            self.wishlist_tabs.remove(&token);
+            self.wishlist.remove(&query);
+        } else {
+            let Some((_, table)) = self.results.get(&token) else {
+                return;
+            };
+            self.wishlist.insert(query, found_files(table));
+            self.wishlist_tabs.insert(token);
+        }
+        self.token_query_map.remove(&tab);
+        self.token_query_map.insert(new_tab.clone(), token);
+        self.search_tabs.tabs[self.search_tabs.selected] = new_tab;
+        self.save_wishlist(write_queue);
+    }
+
+    /// Sends the next wishlist search if it's been long enough since the last one.
+    /// Only one is sent each interval, going through the wishlist in turn.
+    pub(crate) fn search_wishlist(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let Some(interval) = self.wishlist_interval else {
+            return;
+        };
+        if self.wishlist.is_empty()
+            || self
+                .last_wishlist_search
+                .is_some_and(|last_search| last_search.elapsed() < interval)
+        {
+            return;
+        }
+        self.next_wishlist_index %= self.wishlist.len();
+        let query = self
+            .wishlist
+            .keys()
+            .nth(self.next_wishlist_index)
+            .unwrap()
+            .clone();
+        self.next_wishlist_index += 1;
+
+        // what the last search found isn't new any more
+        let table_token = self.token_query_map.get(&wishlist_tab(&query)).copied();
+        if let Some((_, table)) = table_token.and_then(|token| self.results.get_mut(&token)) {
+            for item in &mut table.items {
+                item.clear_style();
+            }
+        }
+        let token = self.new_search_token();
+        self.wishlist_tokens.insert(token, query.clone());
+        self.last_wishlist_search = Some(Instant::now());
+        let _ = write_queue.send(SLSKEvents::WishlistSearch { query, token });
+    }
+
     pub(crate) fn add_results(&mut self, mut results: FileSearchResponse) {
+        let mut style = None;
+        if let Some(query) = self.wishlist_tokens.get(&results.token) {
+            // the query might have been taken off the wishlist since
+            let (Some(found), Some(table_token)) = (
+                self.wishlist.get_mut(query),
+                self.token_query_map.get(&wishlist_tab(query)),
+            ) else {
+                return;
+            };
+            results
+                .files
+                .retain(|file| found.insert((results.username.clone(), file.filename.clone())));
+            results.token = *table_token;
+            style = Some(STYLE_NEW_RESULT);
+        }
+        let styled = |item: TableItem| match style {
+            Some(style) => item.style(style),
+            None => item,
+        };
+
         let token = results.token;
         if let Some((total, table)) = self.results.get_mut(&token) {
             let username = results.username;
@@ -102,30 +441,29 @@ impl FileSearchWindow<'_> {
                             },
                             filename.to_string(),
                         ),
-                        None => (String::new(), result.filename),
+                        None => (String::new(), result.filename.clone()),
                     },
                 };
 
                 if current_folder.is_some() && current_folder != Some(folder.clone()) {
                     let rows_for_folder = rows
                         .into_iter()
-                        .map(|row| TableItem::new(row, Vec::new()).opened())
+                        .map(|row| styled(TableItem::new(row, Vec::new()).opened()))
                         .collect();
-                    folder_rows.push(
+                    folder_rows.push(styled(
                         TableItem::new(folder_header(current_folder), rows_for_folder).opened(),
-                    );
+                    ));
                     rows = Vec::new(); // Reset rows for new folder
                 }
 
-                rows.push(vec![
-                    username.clone().into(),           // username
-                    avg_speed.into(),                  // average speed
-                    queue_length.into(),               // queue length
-                    ColumnData::Empty,                 // folder
-                    filename.into(),                   // file name
-                    ByteSize(result.file_size).into(), // file size
-                    folder.clone().into(),
-                ]);
+                rows.push(file_result_row(
+                    username.clone(),
+                    avg_speed,
+                    queue_length,
+                    folder.clone(),
+                    filename,
+                    &result,
+                ));
                 *total += 1;
 
                 current_folder = Some(folder);
@@ -135,32 +473,169 @@ impl FileSearchWindow<'_> {
             if !rows.is_empty() {
                 let rows_for_folder = rows
                     .into_iter()
-                    .map(|row| TableItem::new(row, Vec::new()).opened())
+                    .map(|row| styled(TableItem::new(row, Vec::new()).opened()))
                     .collect();
 
-                folder_rows
-                    .push(TableItem::new(folder_header(current_folder), rows_for_folder).opened());
+                folder_rows.push(styled(
+                    TableItem::new(folder_header(current_folder), rows_for_folder).opened(),
+                ));
             }
 
             // Add to results if there are any folder rows
             if !folder_rows.is_empty() {
-                table.insert_item(TableItem::new(header_text, folder_rows).opened());
+                table.insert_item(styled(TableItem::new(header_text, folder_rows).opened()));
             }
         }
     }
 }
 
+impl FileSearchWindow<'_> {
+    /// Asks whether to download what's in the dialog's state,
+    /// and checks whether we already share any of it
+    fn ask_to_download(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let Some((item, download_type, companions)) = &self.dialog.state else {
+            return;
+        };
+        let username = item.content[0].to_string();
+        let question = match download_type {
+            None => format!("Download all files from {username}?"),
+            Some(true) => format!("Download {} from {username}?", item.content[3].to_string()),
+            Some(false) if companions.is_empty() => {
+                format!("Download {} from {username}?", item.content[4].to_string())
+            }
+            Some(false) => format!(
+                "Download {} (and {}) from {username}?",
+                item.content[4].to_string(),
+                companions
+                    .iter()
+                    .map(|file| file.content[4].to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        };
+
+        let files: Vec<&TableItem> = match download_type {
+            None => item.children.iter().flat_map(|f| &f.children).collect(),
+            Some(true) => item.children.iter().collect(),
+            Some(false) => std::iter::once(item).chain(companions).collect(),
+        };
+        let token = random();
+        let _ = write_queue.send(SLSKEvents::FindLocalCopies {
+            token,
+            files: files
+                .into_iter()
+                .map(|file| {
+                    let size: ByteSize = file.content[5].clone().try_into().unwrap();
+                    (
+                        file.content[7].to_string() + &file.content[4].to_string(),
+                        size.0,
+                    )
+                })
+                .collect(),
+        });
+
+        self.dialog.set_question(question.clone());
+        self.dialog_question = (question, token);
+    }
+
+    /// Adds the CUE sheet and log from a folder to the file being asked about in it,
+    /// if the search results didn't have them and the dialog is still open
+    pub(crate) fn add_folder_contents(
+        &mut self,
+        username: String,
+        folder: String,
+        folders: Vec<Directory>,
+        write_queue: &Sender<SLSKEvents>,
+    ) {
+        let Some((item, Some(false), companions)) = &mut self.dialog.state else {
+            return;
+        };
+        let item_folder = item.content[7].to_string();
+        if !self.dialog.visible
+            || item.content[0].to_string() != username
+            || item_folder.trim_end_matches(['\\', '/']) != folder
+        {
+            return;
+        }
+        let avg_speed = item.content[1].clone().try_into().unwrap_or(ByteSize(0));
+        let queue_length = match &item.content[2] {
+            ColumnData::Usize(queue_length) => *queue_length.blocking_read(),
+            _ => 0,
+        };
+        let files: Vec<TableItem> = folders
+            .iter()
+            .filter(|directory| directory.path == folder)
+            .flat_map(|directory| &directory.files)
+            .map(|file| {
+                TableItem::new(
+                    file_result_row(
+                        username.clone(),
+                        avg_speed,
+                        queue_length,
+                        item_folder.clone(),
+                        file.filename.clone(),
+                        file,
+                    ),
+                    Vec::new(),
+                )
+                .opened()
+            })
+            .collect();
+
+        let mut added = false;
+        for file in companion_files(item, &files.iter().collect::<Vec<_>>()) {
+            let filename = file.content[4].to_string();
+            if !companions
+                .iter()
+                .any(|companion| companion.content[4].to_string() == filename)
+            {
+                companions.push(file);
+                added = true;
+            }
+        }
+        if added {
+            self.ask_to_download(write_queue);
+        }
+    }
+
+    /// Warns about files we already share the same size of, if the dialog they were asked about is still open
+    pub(crate) fn show_local_copies(&mut self, token: u32, files: Vec<(String, Vec<String>)>) {
+        let (question, dialog_token) = &self.dialog_question;
+        if !self.dialog.visible || *dialog_token != token || files.is_empty() {
+            return;
+        }
+        let mut question = format!(
+            "{question}\n\nYou might already have {} of these, files the same size are shared as:",
+            files.len()
+        );
+        for (_, local_paths) in &files {
+            question.push('\n');
+            question.push_str(&local_paths.join(", "));
+        }
+        self.dialog.set_question(question);
+    }
+}
+
 impl Default for FileSearchWindow<'_> {
     fn default() -> Self {
         Self {
             title: String::from(" File Search "),
             focus_index: 0,
             search_tabs: Tabs::default().title(String::from("Search Results")),
-            search_bar: Input::default().title("File Search".to_string()),
+            search_bar: Input::default()
+                .title("File Search (or user:name, room:name, buddies: before it)".to_string()),
             results: HashMap::default(),
             token_query_map: HashMap::default(),
+            dialog_question: (String::new(), 0),
+            targeted_searches: HashSet::new(),
+            wishlist: OrderedHashMap::new(),
+            wishlist_tabs: HashSet::new(),
+            wishlist_tokens: HashMap::new(),
+            wishlist_interval: None,
+            last_wishlist_search: None,
+            next_wishlist_index: 0,
             dialog: Dialog::default().yes_no_funcs(
-                Some(Rc::new(|_, (write_queue, (item, download_type))| {
+                Some(Rc::new(|_, (write_queue, (item, download_type, companions))| {
                     let (is_all, is_folder) =
                         (download_type.is_none(), download_type.is_some_and(|b| b));
                     let username = item.content[0].to_string();
@@ -199,7 +674,7 @@ impl Default for FileSearchWindow<'_> {
                                             queue_file(&filename, &folder);
                                             let filesize =
                                                 item.content[5].clone().try_into().unwrap();
-                                            (filename, filesize)
+                                            (filename, filesize, expected_attributes(item))
                                         })
                                         .collect(),
                                     folder,
@@ -208,18 +683,21 @@ impl Default for FileSearchWindow<'_> {
                                 .unwrap();
                         }
                     } else {
-                        let filename = item.content[4].to_string();
-                        let filesize = item.content[5].clone().try_into().unwrap();
-                        let folder = item.content[6].to_string();
-                        queue_file(&filename, &folder);
-                        write_queue
-                            .send(SLSKEvents::NewDownload {
-                                username: username.clone(),
-                                folder,
-                                filename,
-                                filesize,
-                            })
-                            .unwrap();
+                        for item in std::iter::once(&item).chain(&companions) {
+                            let filename = item.content[4].to_string();
+                            let filesize = item.content[5].clone().try_into().unwrap();
+                            let folder = item.content[7].to_string();
+                            queue_file(&filename, &folder);
+                            write_queue
+                                .send(SLSKEvents::NewDownload {
+                                    username: username.clone(),
+                                    folder,
+                                    filename,
+                                    filesize,
+                                    attributes: expected_attributes(item),
+                                })
+                                .unwrap();
+                        }
                     };
 
                     write_queue
@@ -272,11 +750,29 @@ impl Widget for FileSearchWindow<'_> {
 
 impl WidgetWithHints for FileSearchWindow<'_> {
     fn get_hints(&self) -> Vec<(Event, String)> {
-        if let Some(widget) = self.get_widget(self.focus_index) {
+        let mut hints = if let Some(widget) = self.get_widget(self.focus_index) {
             widget.get_hints()
         } else {
             Vec::new()
+        };
+        if !self.dialog.visible && self.focus_index == 1 {
+            if let Some(token) = self
+                .search_tabs
+                .selected_tab()
+                .and_then(|tab| self.token_query_map.get(tab))
+                .filter(|token| !self.targeted_searches.contains(token))
+            {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE)),
+                    String::from(if self.wishlist_tabs.contains(token) {
+                        "Remove from wishlist"
+                    } else {
+                        "Add to wishlist"
+                    }),
+                ));
+            }
         }
+        hints
     }
 }
 
@@ -290,30 +786,42 @@ impl Window<'_> for FileSearchWindow<'_> {
             0 => {
                 if event == Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)) {
                     // this is guaranteed not to be a password input
-                    let query = self.search_bar.input.value().to_string();
-                    if !self.search_tabs.tabs.contains(&query) {
-                        let _ = write_queue.send(SLSKEvents::FileSearch {
-                            query: query.clone(),
-                            token: {
-                                loop {
-                                    let token = random();
-                                    if !self.results.contains_key(&token) {
-                                        self.new_table(token, query);
-                                        break token;
-                                    }
-                                }
-                            },
-                        });
+                    let (scope, query) = SearchScope::parse(self.search_bar.input.value());
+                    let tab_name = scope.tab_name(&query);
+                    // those names are kept for wishlist searches' tabs
+                    if !query.is_empty()
+                        && !query.starts_with(WISHLIST_PREFIX)
+                        && !self.search_tabs.tabs.contains(&tab_name)
+                    {
+                        let token = self.new_search_token();
+                        self.new_table(token, tab_name);
+                        if !matches!(scope, SearchScope::Everyone) {
+                            self.targeted_searches.insert(token);
+                        }
+                        let _ = write_queue.send(scope.search(query, token));
                     }
                 } else {
                     self.search_bar.handle_event(&event);
                 }
             }
             1 => {
-                if let Some(selected_tab) = self.search_tabs.selected_tab().cloned() {
+                if event == Event::Key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE)) {
+                    self.toggle_wishlist(write_queue);
+                } else if let Some(selected_tab) = self.search_tabs.selected_tab().cloned() {
                     if self.search_tabs.handle_event(&event) == Some(TAB_REMOVED) {
-                        let token = self.token_query_map.remove(&selected_tab).unwrap();
-                        self.results.remove(&token).unwrap();
+                        if let Some(token) = self.token_query_map.remove(&selected_tab) {
+                            self.results.remove(&token);
+                            self.targeted_searches.remove(&token);
+                            // closing a wishlist search's tab takes it off the wishlist
+                            if self.wishlist_tabs.remove(&token) {
+                                let query = selected_tab
+                                    .strip_prefix(WISHLIST_PREFIX)
+                                    .unwrap_or(&selected_tab);
+                                if self.wishlist.remove(query).is_some() {
+                                    self.save_wishlist(write_queue);
+                                }
+                            }
+                        }
                     };
                 };
             }
@@ -323,27 +831,26 @@ impl Window<'_> for FileSearchWindow<'_> {
                     let table = &mut self.results.get_mut(token).unwrap().1;
                     if table.handle_event(&event) == Some(ITEM_INTERACTED) {
                         if let Some(item) = table.current_row() {
-                            let username = &item.content[0].to_string();
-                            let question;
-                            if item.content.len() < 4 {
-                                question = format!("Download all files from {username}?");
-                                self.dialog.state = Some((item.clone(), None));
+                            let state = if item.content.len() < 4 {
+                                (item.clone(), None, Vec::new())
+                            } else if item.content.len() > 4 {
+                                // the results might not include the sheet or log, so ask for the whole folder too
+                                let folder = item.content[7].to_string();
+                                let folder = folder.trim_end_matches(['\\', '/']);
+                                if !folder.is_empty() {
+                                    let _ = write_queue.send(SLSKEvents::GetFolderContents {
+                                        username: item.content[0].to_string(),
+                                        folder: folder.to_string(),
+                                    });
+                                }
+                                let companions =
+                                    companion_files(item, &result_siblings(table, item));
+                                (item.clone(), Some(false), companions)
                             } else {
-                                let (is_folder, download_fp) = if item.content.len() > 4 {
-                                    (Some(false), &item.content[4])
-                                } else {
-                                    (Some(true), &item.content[3])
-                                };
-                                question = format!(
-                                    "Download {} from {}?",
-                                    download_fp.to_string(),
-                                    username.to_string()
-                                );
-                                self.dialog.state = Some((item.clone(), is_folder));
-                            }
-
-                            self.dialog.set_question(question);
-
+                                (item.clone(), Some(true), Vec::new())
+                            };
+                            self.dialog.state = Some(state);
+                            self.ask_to_download(write_queue);
                             self.dialog.show();
                         };
                     }
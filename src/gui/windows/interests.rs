@@ -0,0 +1,483 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::{Constraint, Direction, Layout, Rect},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+};
+use tokio::sync::broadcast::Sender;
+use tui_input::backend::crossterm::EventHandler;
+
+use crate::{
+    events::SLSKEvents,
+    gui::widgets::{
+        input::Input,
+        table::{TableItem, TableWidget},
+    },
+    messages::UserInfoResponse,
+    styles::STYLE_DEFAULT,
+};
+
+use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
+
+fn interests_table<'a>(likes: &[String], hates: &[String]) -> TableWidget<'a> {
+    let rows = likes
+        .iter()
+        .map(|item| (item, "Like"))
+        .chain(hates.iter().map(|item| (item, "Hate")))
+        .map(|(item, kind)| {
+            TableItem::new(
+                vec![item.clone().into(), String::from(kind).into()],
+                Vec::new(),
+            )
+        })
+        .collect();
+    TableWidget::new(
+        vec![String::from("Interest"), String::from("")],
+        rows,
+        None,
+        Some(vec![Constraint::Min(0), Constraint::Length(4)]),
+    )
+}
+
+fn recommendations_table<'a>(recommendations: &[(String, i32)]) -> TableWidget<'a> {
+    let mut recommendations = recommendations.to_vec();
+    recommendations.sort_by_key(|(_, score)| -score);
+    TableWidget::new(
+        vec![String::from("Item"), String::from("Score")],
+        recommendations
+            .into_iter()
+            .map(|(item, score)| {
+                TableItem::new(vec![item.into(), score.to_string().into()], Vec::new())
+            })
+            .collect(),
+        None,
+        Some(vec![Constraint::Min(0), Constraint::Length(6)]),
+    )
+}
+
+fn users_table<'a>(users: &[(String, u32)]) -> TableWidget<'a> {
+    let mut users = users.to_vec();
+    users.sort_by_key(|(_, rating)| u32::MAX - rating);
+    TableWidget::new(
+        vec![String::from("User"), String::from("Rating")],
+        users
+            .into_iter()
+            .map(|(username, rating)| {
+                TableItem::new(vec![username.into(), (rating as usize).into()], Vec::new())
+            })
+            .collect(),
+        None,
+        Some(vec![Constraint::Min(0), Constraint::Length(6)]),
+    )
+}
+
+/// The selected row's first column, which is the item or username in every table here
+fn selected_name(table: &TableWidget) -> Option<String> {
+    table
+        .current_row()
+        .and_then(|row| row.content.first())
+        .map(|column| column.to_string())
+}
+
+/// What we like and hate, and the things and users the server recommends because of it
+#[derive(Clone)]
+pub(crate) struct InterestsWindow<'a> {
+    title: String,
+    focus_index: u8,
+    pub(crate) like_input: Input<'a>,
+    pub(crate) hate_input: Input<'a>,
+    likes: Vec<String>,
+    hates: Vec<String>,
+    interests: TableWidget<'a>,
+    recommendations: TableWidget<'a>,
+    /// The item the recommendations and users are for, if they aren't based on our interests
+    item: Option<String>,
+    global: bool,
+    users: TableWidget<'a>,
+    /// The user whose info is shown, along with their info and interests once they arrive
+    selected_user: Option<String>,
+    user_info: Option<UserInfoResponse>,
+    user_interests: Option<(Vec<String>, Vec<String>)>,
+}
+
+impl Default for InterestsWindow<'_> {
+    fn default() -> Self {
+        Self {
+            title: String::from(" Interests "),
+            focus_index: 0,
+            like_input: Input::default().title(String::from("I Like")),
+            hate_input: Input::default().title(String::from("I Hate")),
+            likes: Vec::new(),
+            hates: Vec::new(),
+            interests: interests_table(&[], &[]),
+            recommendations: recommendations_table(&[]),
+            item: None,
+            global: false,
+            users: users_table(&[]),
+            selected_user: None,
+            user_info: None,
+            user_interests: None,
+        }
+    }
+}
+
+impl InterestsWindow<'_> {
+    pub(crate) fn set_interests(&mut self, likes: Vec<String>, hates: Vec<String>) {
+        self.interests = interests_table(&likes, &hates);
+        self.likes = likes;
+        self.hates = hates;
+    }
+
+    pub(crate) fn set_recommendations(&mut self, global: bool, recommendations: &[(String, i32)]) {
+        self.recommendations = recommendations_table(recommendations);
+        self.global = global;
+        self.item = None;
+    }
+
+    pub(crate) fn set_item_recommendations(
+        &mut self,
+        item: String,
+        recommendations: &[(String, i32)],
+    ) {
+        self.recommendations = recommendations_table(recommendations);
+        self.global = false;
+        self.item = Some(item);
+    }
+
+    pub(crate) fn set_similar_users(&mut self, users: &[(String, u32)]) {
+        self.users = users_table(users);
+        self.item = None;
+    }
+
+    pub(crate) fn set_item_similar_users(&mut self, item: String, usernames: &[String]) {
+        let users: Vec<_> = usernames
+            .iter()
+            .map(|username| (username.clone(), 0))
+            .collect();
+        self.users = users_table(&users);
+        self.item = Some(item);
+    }
+
+    pub(crate) fn set_user_info(&mut self, username: &str, info: UserInfoResponse) {
+        if self.selected_user.as_deref() == Some(username) {
+            self.user_info = Some(info);
+        }
+    }
+
+    pub(crate) fn set_user_interests(
+        &mut self,
+        username: &str,
+        likes: Vec<String>,
+        hates: Vec<String>,
+    ) {
+        if self.selected_user.as_deref() == Some(username) {
+            self.user_interests = Some((likes, hates));
+        }
+    }
+
+    fn add_interest(&mut self, hate: bool, write_queue: &Sender<SLSKEvents>) {
+        let input = if hate {
+            &mut self.hate_input
+        } else {
+            &mut self.like_input
+        };
+        let item = input.input.value().trim().to_string();
+        input.clear();
+        self.add_item(item, hate, write_queue);
+    }
+
+    /// Likes or hates `item`, which stops it being in the other list
+    fn add_item(&mut self, item: String, hate: bool, write_queue: &Sender<SLSKEvents>) {
+        if item.is_empty() {
+            return;
+        }
+        let (list, other) = if hate {
+            (&mut self.hates, &mut self.likes)
+        } else {
+            (&mut self.likes, &mut self.hates)
+        };
+        if list.contains(&item) {
+            return;
+        }
+        if let Some(index) = other.iter().position(|other| *other == item) {
+            other.remove(index);
+            let _ = write_queue.send(SLSKEvents::RemoveInterest {
+                item: item.clone(),
+                hate: !hate,
+            });
+        }
+        list.push(item.clone());
+        let _ = write_queue.send(SLSKEvents::AddInterest { item, hate });
+        self.set_interests(self.likes.clone(), self.hates.clone());
+    }
+
+    fn remove_selected_interest(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let Some(row) = self.interests.current_row() else {
+            return;
+        };
+        let item = row.content[0].to_string();
+        let hate = row.content[1].to_string() == "Hate";
+        if hate {
+            self.hates.retain(|hated| *hated != item);
+        } else {
+            self.likes.retain(|liked| *liked != item);
+        }
+        let _ = write_queue.send(SLSKEvents::RemoveInterest { item, hate });
+        self.set_interests(self.likes.clone(), self.hates.clone());
+    }
+
+    fn show_user(&mut self, username: String, write_queue: &Sender<SLSKEvents>) {
+        self.selected_user = Some(username.clone());
+        self.user_info = None;
+        self.user_interests = None;
+        let _ = write_queue.send(SLSKEvents::GetUserInfo { username });
+    }
+
+    fn recommendations_title(&self) -> String {
+        match (&self.item, self.global) {
+            (Some(item), _) => format!("Recommended with {item}"),
+            (None, true) => String::from("Global Recommendations"),
+            (None, false) => String::from("Recommendations"),
+        }
+    }
+
+    fn users_title(&self) -> String {
+        match &self.item {
+            Some(item) => format!("Users Who Like {item}"),
+            None => String::from("Similar Users"),
+        }
+    }
+
+    fn user_summary(&self) -> Paragraph<'_> {
+        let mut lines = Vec::new();
+        if let Some(info) = &self.user_info {
+            lines.extend(
+                info.description
+                    .lines()
+                    .map(|line| Line::from(line.to_string())),
+            );
+            lines.push(Line::from(format!(
+                "{} uploads, {} queued, {}",
+                info.upload_num,
+                info.queue_size,
+                if info.slots_free {
+                    "slots free"
+                } else {
+                    "no slots free"
+                }
+            )));
+        }
+        if let Some((likes, hates)) = &self.user_interests {
+            lines.push(Line::from(format!("Likes: {}", likes.join(", "))));
+            lines.push(Line::from(format!("Hates: {}", hates.join(", "))));
+        }
+        if lines.is_empty() && self.selected_user.is_some() {
+            lines.push(Line::from("Waiting for info..."));
+        }
+        Paragraph::new(lines)
+            .style(STYLE_DEFAULT)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::new()
+                    .title(
+                        self.selected_user
+                            .clone()
+                            .unwrap_or(String::from("User Info")),
+                    )
+                    .style(STYLE_DEFAULT)
+                    .borders(Borders::ALL),
+            )
+    }
+}
+
+impl Widget for InterestsWindow<'_> {
+    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let columns = Layout::new(
+            Direction::Horizontal,
+            [
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ],
+        )
+        .split(area);
+        let interests_area = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ],
+        )
+        .split(columns[0]);
+        let users_area = Layout::new(
+            Direction::Vertical,
+            [Constraint::Fill(1), Constraint::Fill(1)],
+        )
+        .split(columns[2]);
+
+        self.user_summary().render(users_area[1], buf);
+
+        let mut recommendations = self.recommendations.title(self.recommendations_title());
+        let mut users = self.users.title(self.users_title());
+        render_widgets!(
+            SELF: self,
+            BUFFER: buf,
+            0 = (self.like_input) => interests_area[0],
+            1 = (self.hate_input) => interests_area[1],
+            2 = (self.interests) => interests_area[2],
+            3 = (recommendations) => columns[1],
+            4 = (users) => users_area[0],
+        );
+    }
+}
+
+impl WidgetWithHints for InterestsWindow<'_> {
+    fn get_hints(&self) -> Vec<(Event, String)> {
+        let mut hints = self
+            .get_widget(self.focus_index)
+            .map(|w| w.get_hints())
+            .unwrap_or_default();
+        let key = |c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        match self.focus_index {
+            0 | 1 => hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Add"),
+            )),
+            2 => {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE)),
+                    String::from("Remove"),
+                ));
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                    String::from("Recommendations for item"),
+                ));
+            }
+            3 => {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                    String::from("Recommendations for item"),
+                ));
+                hints.push((key('l'), String::from("Like")));
+                hints.push((key('h'), String::from("Hate")));
+                hints.push((key('p'), String::from("Personal")));
+                hints.push((key('g'), String::from("Global")));
+            }
+            4 => {
+                hints.push((key('i'), String::from("View info")));
+                hints.push((key('b'), String::from("Browse")));
+                hints.push((key('m'), String::from("Message")));
+                hints.push((key('r'), String::from("Refresh")));
+            }
+            _ => (),
+        }
+        hints
+    }
+}
+
+impl Window<'_> for InterestsWindow<'_> {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn perform_action(&mut self, focus_index: u8, event: Event, write_queue: &Sender<SLSKEvents>) {
+        let key = match event {
+            Event::Key(key) if key.modifiers == KeyModifiers::NONE => Some(key.code),
+            _ => None,
+        };
+        match focus_index {
+            0 => match key {
+                Some(KeyCode::Enter) => self.add_interest(false, write_queue),
+                _ => {
+                    self.like_input.handle_event(&event);
+                }
+            },
+            1 => match key {
+                Some(KeyCode::Enter) => self.add_interest(true, write_queue),
+                _ => {
+                    self.hate_input.handle_event(&event);
+                }
+            },
+            2 => match key {
+                Some(KeyCode::Delete) => self.remove_selected_interest(write_queue),
+                Some(KeyCode::Enter) => {
+                    if let Some(item) = selected_name(&self.interests) {
+                        let _ = write_queue.send(SLSKEvents::GetItemRecommendations { item });
+                    }
+                }
+                _ => {
+                    self.interests.handle_event(&event);
+                }
+            },
+            3 => match key {
+                Some(KeyCode::Enter) => {
+                    if let Some(item) = selected_name(&self.recommendations) {
+                        let _ = write_queue.send(SLSKEvents::GetItemRecommendations { item });
+                    }
+                }
+                Some(KeyCode::Char(c @ ('l' | 'h'))) => {
+                    if let Some(item) = selected_name(&self.recommendations) {
+                        self.add_item(item, c == 'h', write_queue);
+                    }
+                }
+                Some(KeyCode::Char(c @ ('p' | 'g'))) => {
+                    let _ = write_queue.send(SLSKEvents::GetRecommendations { global: c == 'g' });
+                }
+                _ => {
+                    self.recommendations.handle_event(&event);
+                }
+            },
+            4 => match key {
+                Some(KeyCode::Char('i')) => {
+                    if let Some(username) = selected_name(&self.users) {
+                        self.show_user(username, write_queue);
+                    }
+                }
+                Some(KeyCode::Char('b')) => {
+                    if let Some(username) = selected_name(&self.users) {
+                        let _ = write_queue.send(SLSKEvents::BrowseUser { username });
+                    }
+                }
+                Some(KeyCode::Char('m')) => {
+                    if let Some(username) = selected_name(&self.users) {
+                        let _ = write_queue.send(SLSKEvents::OpenPrivateChat { username });
+                    }
+                }
+                Some(KeyCode::Char('r')) => {
+                    let _ = write_queue.send(SLSKEvents::GetSimilarUsers);
+                }
+                _ => {
+                    self.users.handle_event(&event);
+                }
+            },
+            _ => unimplemented!("perform_action({focus_index}, {event:?})"),
+        };
+    }
+
+    fn number_of_widgets(&self) -> u8 {
+        5
+    }
+
+    fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
+        match index {
+            0 => Some(&self.like_input),
+            1 => Some(&self.hate_input),
+            2 => Some(&self.interests),
+            3 => Some(&self.recommendations),
+            4 => Some(&self.users),
+            _ => unimplemented!(
+                "There are only {} widgets, it's impossible to get the widget with index {index}",
+                self.number_of_widgets()
+            ),
+        }
+    }
+
+    fn get_focused_index(&self) -> u8 {
+        self.focus_index
+    }
+
+    fn set_focused_index(&mut self, index: u8) {
+        self.focus_index = index;
+    }
+}
@@ -3,22 +3,59 @@ use std::rc::Rc;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     prelude::{Constraint, Direction, Layout, Rect},
-    widgets::{Block, Borders, Widget},
+    widgets::{Block, Borders, Paragraph, Widget},
 };
 use tokio::sync::broadcast::Sender;
 use tui_input::backend::crossterm::EventHandler;
 
 use crate::{
+    constants::ByteSize,
     events::SLSKEvents,
     gui::widgets::{
         button::Button,
         input::{Input, InputType},
     },
-    styles::{STYLE_DEFAULT, STYLE_DISABLED_DEFAULT},
+    messages::UserStats,
+    styles::{STYLE_DEFAULT, STYLE_DEFAULT_LOW_CONTRAST, STYLE_DISABLED_DEFAULT},
 };
 
 use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
 
+/// What the server's reason for not letting us log in means
+pub(crate) fn login_failure_explanation(reason: &str) -> String {
+    match reason {
+        "INVALIDUSERNAME" => String::from(
+            "The server doesn't allow that username. \
+            It might be too long, or have characters in it that aren't allowed.",
+        ),
+        "INVALIDPASS" => String::from(
+            "That's the wrong password for this username. \
+            If you haven't used this username before, someone else has already registered it.",
+        ),
+        "INVALIDVERSION" => String::from(
+            "The server doesn't accept this version of slsk-rs any more, \
+            check for an update.",
+        ),
+        reason => format!("The server said: {reason}"),
+    }
+}
+
+/// Like "3 days, 4 hours", or "12 minutes" when there's less than an hour left
+fn time_left(seconds: u32) -> String {
+    let (days, hours, minutes) = (
+        seconds / 86_400,
+        seconds % 86_400 / 3_600,
+        seconds % 3_600 / 60,
+    );
+    let plural = |n: u32, unit: &str| format!("{n} {unit}{}", if n == 1 { "" } else { "s" });
+    match (days, hours) {
+        (0, 0) => plural(minutes, "minute"),
+        (0, hours) => plural(hours, "hour"),
+        (days, 0) => plural(days, "day"),
+        (days, hours) => format!("{}, {}", plural(days, "day"), plural(hours, "hour")),
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct LoginWindow<'a> {
     pub(crate) title: String,
@@ -26,6 +63,12 @@ pub(crate) struct LoginWindow<'a> {
     pub(crate) password_input: Input<'a>,
     pub(crate) login_button: Button<'a, (String, String, Sender<SLSKEvents>), ()>,
     pub(crate) logout_button: Button<'a, Sender<SLSKEvents>, ()>,
+    /// Takes a username then a number of days
+    pub(crate) gift_input: Input<'a>,
+    /// How long we're privileged for, none until the server says
+    pub(crate) privileges_left: Option<u32>,
+    /// What the server tells other users about us, none until it says
+    pub(crate) stats: Option<UserStats>,
     pub(crate) focus_index: u8,
 }
 
@@ -63,14 +106,39 @@ impl Default for LoginWindow<'_> {
                 }),
                 disabled: false,
             },
+            gift_input: Input::default().title(String::from("Gift Privileges (username days)")),
+            privileges_left: None,
+            stats: None,
             focus_index: 0,
         }
     }
 }
 
+impl LoginWindow<'_> {
+    fn gift_privileges(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let value = self.gift_input.input.value().trim().to_string();
+        let Some((username, days)) = value.rsplit_once(char::is_whitespace) else {
+            return;
+        };
+        let (username, Ok(days)) = (username.trim(), days.parse::<u32>()) else {
+            return;
+        };
+        if username.is_empty() || days == 0 {
+            return;
+        }
+        let _ = write_queue.send(SLSKEvents::GivePrivileges {
+            username: username.to_string(),
+            days,
+        });
+        // the days come out of ours
+        let _ = write_queue.send(SLSKEvents::CheckPrivileges);
+        self.gift_input.clear();
+    }
+}
+
 impl Widget for LoginWindow<'_> {
     fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        let padding = (area.height - 12) / 2;
+        let padding = area.height.saturating_sub(17) / 2;
         let columns = Layout::new(
             Direction::Horizontal,
             [
@@ -88,11 +156,36 @@ impl Widget for LoginWindow<'_> {
                 Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(3),
                 Constraint::Length(padding),
             ],
         )
         .split(columns[1]);
 
+        Paragraph::new(match self.privileges_left {
+            None => String::new(),
+            Some(0) => String::from("Not privileged"),
+            Some(seconds) => format!("Privileged for {}", time_left(seconds)),
+        })
+        .style(STYLE_DEFAULT_LOW_CONTRAST)
+        .centered()
+        .render(chunks[5], buf);
+
+        if let Some(stats) = &self.stats {
+            Paragraph::new(format!(
+                "Sharing {} files in {} folders, {} uploads at {}/s",
+                stats.num_of_files,
+                stats.num_of_dirs,
+                stats.upload_num,
+                ByteSize(stats.avg_speeds as u64).to_string()
+            ))
+            .style(STYLE_DEFAULT_LOW_CONTRAST)
+            .centered()
+            .render(chunks[6], buf);
+        }
+
         render_widgets!(
             SELF: self,
             BUFFER: buf,
@@ -100,17 +193,25 @@ impl Widget for LoginWindow<'_> {
             1 = (self.password_input) => chunks[2],
             2 = (self.login_button) => chunks[3],
             3 = (self.logout_button) => chunks[4],
+            4 = (self.gift_input) => chunks[7],
         );
     }
 }
 
 impl WidgetWithHints for LoginWindow<'_> {
     fn get_hints(&self) -> Vec<(Event, String)> {
-        if let Some(widget) = self.get_widget(self.focus_index) {
+        let mut hints = if let Some(widget) = self.get_widget(self.focus_index) {
             widget.get_hints()
         } else {
             Vec::new()
+        };
+        if self.focus_index == 4 {
+            hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Gift privileges"),
+            ));
         }
+        hints
     }
 }
 
@@ -147,12 +248,20 @@ impl Window<'_> for LoginWindow<'_> {
                 (self.logout_button.func)(&self.logout_button, write_queue.clone());
                 None
             }
+            4 => {
+                if event == Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)) {
+                    self.gift_privileges(write_queue);
+                    None
+                } else {
+                    self.gift_input.handle_event(&event)
+                }
+            }
             _ => unimplemented!("perform_action({focus_index}, {event:?})"),
         };
     }
 
     fn number_of_widgets(&self) -> u8 {
-        4
+        5
     }
 
     fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
@@ -161,6 +270,7 @@ impl Window<'_> for LoginWindow<'_> {
             1 => Some(&self.password_input),
             2 => Some(&self.login_button),
             3 => Some(&self.logout_button),
+            4 => Some(&self.gift_input),
             _ => unimplemented!(
                 "There are only {} widgets, it's impossible to get the widget with index {index}",
                 self.number_of_widgets()
@@ -3,18 +3,20 @@
 // File windows:
 // UploadWindow
 
-// Social windows:
-// MessageWindow
-
 use crossterm::event::Event;
 use ratatui::widgets::Widget;
 use tokio::sync::broadcast::Sender;
 
 use crate::events::SLSKEvents;
 
+pub(crate) mod browse;
 pub(crate) mod chatrooms;
 pub(crate) mod filesearch;
+pub(crate) mod interests;
 pub(crate) mod login;
+pub(crate) mod private_chats;
+pub(crate) mod settings;
+pub(crate) mod shares;
 pub(crate) mod transfers;
 
 /// A widget that has assosciated shortcut hints
@@ -0,0 +1,203 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ordered_hash_map::OrderedHashMap;
+use ratatui::{
+    prelude::{Constraint, Direction, Layout, Rect},
+    widgets::Widget,
+};
+use tokio::sync::broadcast::Sender;
+use tui_input::backend::crossterm::EventHandler;
+
+use crate::{
+    events::SLSKEvents,
+    gui::widgets::{
+        input::Input,
+        list::List,
+        tabs::{Tabs, TAB_REMOVED},
+    },
+};
+
+use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
+
+/// Conversations with other users, one tab per user
+#[derive(Clone)]
+pub(crate) struct PrivateChatsWindow<'a> {
+    title: String,
+    chats: OrderedHashMap<String, Vec<String>>,
+    chat_tabs: Tabs<'a>,
+    pub(crate) username_input: Input<'a>,
+    pub(crate) message_input: Input<'a>,
+    focus_index: u8,
+}
+
+impl Default for PrivateChatsWindow<'_> {
+    fn default() -> Self {
+        Self {
+            title: String::from(" Private Chats "),
+            chats: OrderedHashMap::new(),
+            chat_tabs: Tabs::default().title(String::from("Chats")),
+            username_input: Input::default().title(String::from("Chat With")),
+            message_input: Input::default().title(String::from("Message Input")),
+            focus_index: 0,
+        }
+    }
+}
+
+impl PrivateChatsWindow<'_> {
+    /// Opens a tab for `username` if there isn't one already, and switches to it
+    pub(crate) fn open_chat(&mut self, username: String) {
+        if !self.chats.contains_key(&username) {
+            self.chats.insert(username.clone(), Vec::new());
+            self.chat_tabs.add_tab(username.clone());
+        }
+        if let Some(index) = self.chat_tabs.tabs.iter().position(|tab| *tab == username) {
+            self.chat_tabs.current = index;
+            self.chat_tabs.selected = index;
+        }
+    }
+
+    /// Adds a line to the chat with `username`, opening a tab for it without switching to it
+    pub(crate) fn add_message(&mut self, username: String, line: String) {
+        match self.chats.get_mut(&username) {
+            Some(messages) => messages.push(line),
+            None => {
+                self.chat_tabs.add_tab(username.clone());
+                self.chats.insert(username, vec![line]);
+            }
+        }
+    }
+}
+
+impl Widget for PrivateChatsWindow<'_> {
+    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let chunks = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ],
+        )
+        .split(area);
+        let above_chat_area = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Min(0), Constraint::Length(32)],
+        )
+        .split(chunks[0]);
+
+        let messages = self
+            .chat_tabs
+            .current_tab()
+            .and_then(|username| self.chats.get(username))
+            .cloned()
+            .unwrap_or_default();
+        let last_message = messages.len().checked_sub(1);
+        let mut messages = List::new(messages);
+        messages.state.select(last_message);
+        messages.render(chunks[1], buf);
+
+        render_widgets!(
+            SELF: self,
+            BUFFER: buf,
+            0 = (self.chat_tabs) => above_chat_area[0],
+            1 = (self.username_input) => above_chat_area[1],
+            2 = (self.message_input) => chunks[2],
+        );
+    }
+}
+
+impl WidgetWithHints for PrivateChatsWindow<'_> {
+    fn get_hints(&self) -> Vec<(Event, String)> {
+        let mut hints = self
+            .get_widget(self.focus_index)
+            .map(|w| w.get_hints())
+            .unwrap_or_default();
+        match self.focus_index {
+            1 => hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Open chat"),
+            )),
+            2 => hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Send message"),
+            )),
+            _ => (),
+        }
+        hints
+    }
+}
+
+impl Window<'_> for PrivateChatsWindow<'_> {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn perform_action(&mut self, focus_index: u8, event: Event, write_queue: &Sender<SLSKEvents>) {
+        let enter = event == Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        match focus_index {
+            0 => {
+                if self.chat_tabs.handle_event(&event) == Some(TAB_REMOVED) {
+                    if let Some(username) = self.chat_tabs.removed_tab.take() {
+                        self.chats.remove(&username);
+                    }
+                }
+            }
+            1 => {
+                if enter {
+                    let username = self.username_input.input.value().trim().to_string();
+                    if !username.is_empty() {
+                        // so we hear about them going away, and keep hearing after reconnecting
+                        let _ = write_queue.send(SLSKEvents::WatchUser {
+                            username: username.clone(),
+                        });
+                        self.open_chat(username);
+                        self.username_input.clear();
+                    }
+                } else {
+                    self.username_input.handle_event(&event);
+                }
+            }
+            2 => {
+                if enter {
+                    if let Some(username) = self.chat_tabs.current_tab() {
+                        let message = self.message_input.input.value().to_string();
+                        if !message.is_empty() {
+                            let _ = write_queue.send(SLSKEvents::PrivateMessage {
+                                id: None,
+                                username: username.to_string(),
+                                message,
+                            });
+                            self.message_input.clear();
+                        }
+                    }
+                } else {
+                    self.message_input.handle_event(&event);
+                }
+            }
+            _ => unimplemented!("perform_action({focus_index}, {event:?})"),
+        };
+    }
+
+    fn number_of_widgets(&self) -> u8 {
+        3
+    }
+
+    fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
+        match index {
+            0 => Some(&self.chat_tabs),
+            1 => Some(&self.username_input),
+            2 => Some(&self.message_input),
+            _ => unimplemented!(
+                "There are only {} widgets, it's impossible to get the widget with index {index}",
+                self.number_of_widgets()
+            ),
+        }
+    }
+
+    fn get_focused_index(&self) -> u8 {
+        self.focus_index
+    }
+
+    fn set_focused_index(&mut self, index: u8) {
+        self.focus_index = index;
+    }
+}
@@ -0,0 +1,379 @@
+pub(crate) use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::{Constraint, Direction, Layout, Rect},
+    text::Line,
+    widgets::Widget,
+};
+use std::{path::PathBuf, rc::Rc};
+use tokio::sync::broadcast::Sender;
+use tui_input::backend::crossterm::EventHandler;
+
+use crate::{
+    events::SLSKEvents,
+    gui::widgets::{
+        dialog::Dialog,
+        input::Input,
+        table::{TableItem, TableWidget},
+    },
+    nicotine::{self, NicotineImport},
+    sql::SharedRoot,
+    styles::{STYLE_DEFAULT, STYLE_FAIL_DEFAULT},
+    utils::complete_directory,
+};
+
+use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
+
+#[derive(Clone)]
+pub(crate) struct SettingsWindow<'a> {
+    title: String,
+    focus_index: u8,
+    pub(crate) path_input: Input<'a>,
+    pub(crate) alias_input: Input<'a>,
+    shares: Vec<SharedRoot>,
+    shares_table: TableWidget<'a>,
+    /// The last thing that happened to the shares, and whether it was an error
+    status: Option<(String, bool)>,
+    pub(crate) import_input: Input<'a>,
+    /// Lists what importing a Nicotine+ config would change, nothing is written until it's accepted
+    pub(crate) import_dialog: Dialog<'a, NicotineImport>,
+}
+
+fn shares_table<'a>(shares: &[SharedRoot]) -> TableWidget<'a> {
+    TableWidget::new(
+        vec![
+            String::from("Alias"),
+            String::from("Path"),
+            String::from("Buddy-only"),
+            String::from("Folders"),
+            String::from("Files"),
+            String::from("Suspicious"),
+        ],
+        shares
+            .iter()
+            .map(|share| {
+                TableItem::new(
+                    vec![
+                        share.alias.clone().into(),
+                        share.path.to_string_lossy().to_string().into(),
+                        String::from(if share.is_buddy_only { "Yes" } else { "No" }).into(),
+                        (share.folders as usize).into(),
+                        (share.files as usize).into(),
+                        (share.suspicious as usize).into(),
+                    ],
+                    Vec::new(),
+                )
+            })
+            .collect(),
+        None,
+        Some(vec![
+            Constraint::Fill(1), // alias
+            Constraint::Fill(2), // path
+            Constraint::Max(10), // buddy-only
+            Constraint::Max(10), // folders
+            Constraint::Max(10), // files
+            Constraint::Max(10), // suspicious
+        ]),
+    )
+}
+
+impl Default for SettingsWindow<'_> {
+    fn default() -> Self {
+        Self {
+            title: String::from(" Settings "),
+            focus_index: 0,
+            path_input: Input::default().title(String::from("Share Path")),
+            alias_input: Input::default().title(String::from("Alias")),
+            shares: Vec::new(),
+            shares_table: shares_table(&[]),
+            status: None,
+            import_input: Input::default().title(String::from(
+                "Import Nicotine+ Config (leave empty for the default location)",
+            )),
+            import_dialog: Dialog::default().yes_no_funcs(
+                Some(Rc::new(|_, (write_queue, import)| {
+                    let _ = write_queue.send(SLSKEvents::ApplyImport { import });
+                })),
+                None,
+            ),
+        }
+    }
+}
+
+impl SettingsWindow<'_> {
+    pub(crate) fn set_shares(&mut self, shares: Vec<SharedRoot>, error: Option<String>) {
+        let selected_row = self.shares_table.selected_row;
+        self.shares_table = shares_table(&shares);
+        self.shares_table.selected_row = selected_row.min(shares.len().saturating_sub(1));
+        self.status = match error {
+            Some(error) => Some((error, true)),
+            None => Some((
+                format!(
+                    "{} shared folders, {} files",
+                    shares.iter().map(|share| share.folders).sum::<u32>(),
+                    shares.iter().map(|share| share.files).sum::<u32>()
+                ),
+                false,
+            )),
+        };
+        self.shares = shares;
+    }
+
+    fn selected_share(&self) -> Option<&SharedRoot> {
+        let alias = self.shares_table.current_row()?.content[0].to_string();
+        self.shares.iter().find(|share| share.alias == alias)
+    }
+
+    fn add_share(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let path = PathBuf::from(self.path_input.input.value().trim());
+        if path.as_os_str().is_empty() {
+            return;
+        }
+        let alias = match self.alias_input.input.value().trim() {
+            "" => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            alias => alias.to_string(),
+        };
+        self.status = Some((format!("Indexing {}...", path.to_string_lossy()), false));
+        let _ = write_queue.send(SLSKEvents::AddShare {
+            path,
+            alias,
+            buddy_only: false,
+        });
+        self.path_input.clear();
+        self.alias_input.clear();
+    }
+
+    fn preview_import(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let path = match self.import_input.input.value().trim() {
+            "" => nicotine::default_path(),
+            path => Some(PathBuf::from(path)),
+        };
+        self.status = match path {
+            Some(path) => {
+                let status = format!("Reading {}...", path.to_string_lossy());
+                let _ = write_queue.send(SLSKEvents::PreviewImport { path });
+                Some((status, false))
+            }
+            None => Some((
+                String::from("Couldn't work out where Nicotine+ keeps its config"),
+                true,
+            )),
+        };
+    }
+
+    pub(crate) fn show_import_preview(
+        &mut self,
+        path: PathBuf,
+        preview: Result<(NicotineImport, Vec<String>), String>,
+    ) {
+        let path = path.to_string_lossy();
+        match preview {
+            Err(e) => self.status = Some((format!("Couldn't read {path}: {e}"), true)),
+            Ok((_, changes)) if changes.is_empty() => {
+                self.status = Some((format!("Nothing to import from {path}"), false));
+            }
+            Ok((import, changes)) => {
+                self.import_dialog
+                    .set_question(format!("Import from {path}?\n\n{}", changes.join("\n")));
+                self.import_dialog.state = Some(import);
+                self.import_dialog.show();
+                self.import_input.clear();
+            }
+        }
+    }
+}
+
+impl Widget for SettingsWindow<'_> {
+    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let chunks = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ],
+        )
+        .split(area);
+        let input_chunks = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Fill(3), Constraint::Fill(1)],
+        )
+        .split(chunks[0]);
+
+        let mut table = match &self.status {
+            Some((status, is_error)) => self.shares_table.title(Line::styled(
+                status.clone(),
+                if *is_error {
+                    STYLE_FAIL_DEFAULT
+                } else {
+                    STYLE_DEFAULT
+                },
+            )),
+            None => self.shares_table.title("Shares"),
+        };
+        render_widgets!(
+            SELF: self,
+            BUFFER: buf,
+            0 = (self.path_input) => input_chunks[0],
+            1 = (self.alias_input) => input_chunks[1],
+            2 = (table) => chunks[1],
+            3 = (self.import_input) => chunks[2],
+        );
+
+        if self.import_dialog.visible {
+            self.import_dialog.render(area, buf);
+        }
+    }
+}
+
+impl WidgetWithHints for SettingsWindow<'_> {
+    fn get_hints(&self) -> Vec<(Event, String)> {
+        if self.import_dialog.visible {
+            return self.import_dialog.get_hints();
+        }
+        let mut hints = self
+            .get_widget(self.focus_index)
+            .map(|w| w.get_hints())
+            .unwrap_or_default();
+        match self.focus_index {
+            0 => {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE)),
+                    String::from("Complete directory"),
+                ));
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                    String::from("Add share"),
+                ));
+            }
+            1 => hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Add share"),
+            )),
+            2 => hints.extend([
+                (
+                    Event::Key(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE)),
+                    String::from("Remove share"),
+                ),
+                (
+                    Event::Key(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE)),
+                    String::from("Toggle buddy-only"),
+                ),
+                (
+                    Event::Key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE)),
+                    String::from("Rename to alias"),
+                ),
+                (
+                    Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)),
+                    String::from("Rescan"),
+                ),
+            ]),
+            3 => hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Preview import"),
+            )),
+            _ => (),
+        }
+        hints
+    }
+}
+
+impl Window<'_> for SettingsWindow<'_> {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn perform_action(&mut self, focus_index: u8, event: Event, write_queue: &Sender<SLSKEvents>) {
+        let key = match event {
+            Event::Key(key) if key.modifiers == KeyModifiers::NONE => Some(key.code),
+            _ => None,
+        };
+        match focus_index {
+            0 => match key {
+                Some(KeyCode::Enter) => self.add_share(write_queue),
+                Some(KeyCode::Right)
+                    if self.path_input.input.cursor()
+                        == self.path_input.input.value().chars().count() =>
+                {
+                    if let Some(completed) = complete_directory(self.path_input.input.value()) {
+                        self.path_input.input = self.path_input.input.clone().with_value(completed);
+                    }
+                }
+                _ => {
+                    self.path_input.handle_event(&event);
+                }
+            },
+            1 => match key {
+                Some(KeyCode::Enter) => self.add_share(write_queue),
+                _ => {
+                    self.alias_input.handle_event(&event);
+                }
+            },
+            2 => {
+                let share = self.selected_share().cloned();
+                match (key, share) {
+                    (Some(KeyCode::Delete), Some(share)) => {
+                        let _ = write_queue.send(SLSKEvents::RemoveShare { alias: share.alias });
+                    }
+                    (Some(KeyCode::Char('b')), Some(share)) => {
+                        let _ = write_queue.send(SLSKEvents::SetShareBuddyOnly {
+                            alias: share.alias,
+                            buddy_only: !share.is_buddy_only,
+                        });
+                    }
+                    (Some(KeyCode::Char('e')), Some(share)) => {
+                        let new_alias = self.alias_input.input.value().trim().to_string();
+                        if !new_alias.is_empty() {
+                            let _ = write_queue.send(SLSKEvents::RenameShare {
+                                alias: share.alias,
+                                new_alias,
+                            });
+                            self.alias_input.clear();
+                        }
+                    }
+                    (Some(KeyCode::Char('r')), Some(share)) => {
+                        self.status = Some((format!("Rescanning {}...", share.alias), false));
+                        let _ = write_queue.send(SLSKEvents::RescanShare { alias: share.alias });
+                    }
+                    _ => {
+                        self.shares_table.handle_event(&event);
+                    }
+                }
+            }
+            3 => match key {
+                Some(KeyCode::Enter) => self.preview_import(write_queue),
+                _ => {
+                    self.import_input.handle_event(&event);
+                }
+            },
+            _ => unimplemented!("perform_action({focus_index}, {event:?})"),
+        };
+    }
+
+    fn number_of_widgets(&self) -> u8 {
+        4
+    }
+
+    fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
+        match index {
+            0 => Some(&self.path_input),
+            1 => Some(&self.alias_input),
+            2 => Some(&self.shares_table),
+            3 => Some(&self.import_input),
+            _ => unimplemented!(
+                "There are only {} widgets, it's impossible to get the widget with index {index}",
+                self.number_of_widgets()
+            ),
+        }
+    }
+
+    fn get_focused_index(&self) -> u8 {
+        self.focus_index
+    }
+
+    fn set_focused_index(&mut self, index: u8) {
+        self.focus_index = index;
+    }
+}
@@ -0,0 +1,538 @@
+pub(crate) use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    prelude::{Constraint, Direction, Layout, Rect},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+use tokio::sync::broadcast::Sender;
+use tui_input::backend::crossterm::EventHandler;
+
+use crate::{
+    analysis::Verdict,
+    constants::ByteSize,
+    events::SLSKEvents,
+    gui::widgets::{
+        input::Input,
+        table::{ColumnData, TableItem, TableWidget},
+    },
+    listing::ShareListing,
+    messages::{Directory, File, FileAttribute, SharedFileListResponse},
+    parsers::{can_parse, encoder_name},
+    sql::{ImageTrack, ShareStats},
+    styles::{STYLE_DEFAULT, STYLE_FAIL_DEFAULT},
+    utils::{num_as_bytes, num_as_str},
+};
+
+use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
+
+/// How a file's attributes read to someone browsing or searching us
+fn describe_attributes(attributes: &[FileAttribute]) -> String {
+    let mut description = Vec::new();
+    for attribute in attributes {
+        match *attribute {
+            FileAttribute::Bitrate(bitrate) => description.push(format!("{bitrate} kbps")),
+            FileAttribute::Duration(duration) => description.push(duration_text(duration)),
+            FileAttribute::VBR(true) => description.push(String::from("VBR")),
+            FileAttribute::VBR(false) => (),
+            FileAttribute::Encoder(id) => description.push(
+                encoder_name(id)
+                    .map(String::from)
+                    .unwrap_or_else(|| id.to_string()),
+            ),
+            FileAttribute::SampleRate(sample_rate) => {
+                description.push(format!("{} kHz", sample_rate as f64 / 1000.0))
+            }
+            FileAttribute::BitDepth(bit_depth) => description.push(format!("{bit_depth} bit")),
+        }
+    }
+    description.join(", ")
+}
+
+fn duration_text(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// A row for a track inside an album image
+fn track_row(track: &ImageTrack) -> TableItem {
+    let name = match (&track.performer, &track.title) {
+        (Some(performer), Some(title)) => format!("{:02}. {performer} - {title}", track.number),
+        (None, Some(title)) => format!("{:02}. {title}", track.number),
+        _ => format!("Track {:02}", track.number),
+    };
+    TableItem::new(
+        vec![
+            name.into(),
+            ColumnData::Empty,
+            ColumnData::Empty,
+            track
+                .duration
+                .map(|duration| duration_text(duration.round() as u32).into())
+                .unwrap_or(ColumnData::Empty),
+        ],
+        Vec::new(),
+    )
+}
+
+/// `verdict` is set if the file looks transcoded, which is shown after its attributes.
+/// Album images open to show the `tracks` their CUE sheet lists.
+fn file_row(file: &File, verdict: Option<&Verdict>, tracks: Option<&Vec<ImageTrack>>) -> TableItem {
+    let attributes = if !file.attributes.is_empty() {
+        describe_attributes(&file.attributes)
+    } else if can_parse(&file.extension) {
+        String::from("Unreadable")
+    } else {
+        String::new()
+    };
+    let attributes = match verdict {
+        Some(verdict) if attributes.is_empty() => verdict.describe(),
+        Some(verdict) => format!("{attributes}, {}", verdict.describe()),
+        None => attributes,
+    };
+    let row = TableItem::new(
+        vec![
+            file.filename.clone().into(),
+            ColumnData::Empty,
+            ByteSize(file.file_size).into(),
+            if attributes.is_empty() {
+                ColumnData::Empty
+            } else {
+                attributes.into()
+            },
+        ],
+        tracks.map_or_else(Vec::new, |tracks| tracks.iter().map(track_row).collect()),
+    );
+    match verdict {
+        Some(_) => row.style(STYLE_FAIL_DEFAULT),
+        None => row,
+    }
+}
+
+/// A row for each directory, which opens to show its files, under a row for the whole group.
+/// What `stats` has from the index about each file is shown with it.
+fn directories_row(name: &str, directories: &[Directory], stats: &ShareStats) -> TableItem {
+    let files = directories.iter().map(|d| d.files.len()).sum::<usize>();
+    let size = directories
+        .iter()
+        .flat_map(|d| &d.files)
+        .map(|f| f.file_size)
+        .sum();
+    TableItem::new(
+        vec![
+            format!("{name} ({} folders)", num_as_str(directories.len())).into(),
+            files.into(),
+            ByteSize(size).into(),
+            ColumnData::Empty,
+        ],
+        directories
+            .iter()
+            .map(|directory| {
+                TableItem::new(
+                    vec![
+                        directory.path.clone().into(),
+                        directory.files.len().into(),
+                        ByteSize(directory.files.iter().map(|f| f.file_size).sum()).into(),
+                        ColumnData::Empty,
+                    ],
+                    directory
+                        .files
+                        .iter()
+                        .map(|file| {
+                            let path = format!("{}\\{}", directory.path, file.filename);
+                            file_row(
+                                file,
+                                stats.suspicious.get(&path),
+                                stats.image_tracks.get(&path),
+                            )
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+    .opened()
+}
+
+/// A row for each set of identical files, which opens to show where they are
+fn duplicates_row(duplicates: &[Vec<String>]) -> TableItem {
+    let path_row = |path: &String| {
+        TableItem::new(
+            vec![
+                path.clone().into(),
+                ColumnData::Empty,
+                ColumnData::Empty,
+                ColumnData::Empty,
+            ],
+            Vec::new(),
+        )
+    };
+    TableItem::new(
+        vec![
+            format!("Duplicates ({} sets)", num_as_str(duplicates.len())).into(),
+            duplicates.iter().map(Vec::len).sum::<usize>().into(),
+            ColumnData::Empty,
+            ColumnData::Empty,
+        ],
+        duplicates
+            .iter()
+            .map(|paths| {
+                let filename = paths[0]
+                    .rsplit_once('\\')
+                    .map_or(paths[0].as_str(), |(_, f)| f);
+                TableItem::new(
+                    vec![
+                        filename.to_string().into(),
+                        paths.len().into(),
+                        ColumnData::Empty,
+                        ColumnData::Empty,
+                    ],
+                    paths.iter().map(path_row).collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+pub(super) fn browse_table<'a>(
+    directories: &[Directory],
+    priv_directories: &[Directory],
+) -> TableWidget<'a> {
+    shares_table(directories, priv_directories, &ShareStats::default())
+}
+
+/// A `browse_table` of our own shares, with what the index knows about the files that the file list doesn't:
+/// which ones look transcoded, and the tracks in album images
+fn shares_table<'a>(
+    directories: &[Directory],
+    priv_directories: &[Directory],
+    stats: &ShareStats,
+) -> TableWidget<'a> {
+    TableWidget::new(
+        vec![
+            String::from("Name"),
+            String::from("Files"),
+            String::from("Size"),
+            String::from("Attributes"),
+        ],
+        vec![
+            directories_row("Public", directories, stats),
+            directories_row("Buddy-only", priv_directories, stats),
+        ],
+        Some(0..1),
+        Some(vec![
+            Constraint::Fill(3), // name
+            Constraint::Max(8),  // files
+            Constraint::Max(10), // size
+            Constraint::Fill(1), // attributes
+        ]),
+    )
+}
+
+fn extensions_table<'a>(stats: &ShareStats) -> TableWidget<'a> {
+    TableWidget::new(
+        vec![
+            String::from("Extension"),
+            String::from("Files"),
+            String::from("Size"),
+        ],
+        stats
+            .extensions
+            .iter()
+            .map(|(extension, files, size)| {
+                TableItem::new(
+                    vec![
+                        extension.clone().into(),
+                        (*files as usize).into(),
+                        ByteSize(*size).into(),
+                    ],
+                    Vec::new(),
+                )
+            })
+            .collect(),
+        None,
+        Some(vec![
+            Constraint::Fill(1), // extension
+            Constraint::Max(8),  // files
+            Constraint::Max(10), // size
+        ]),
+    )
+}
+
+/// Search results are full paths, this puts them back into their folders like a browse response
+fn group_into_directories(files: Vec<File>) -> Vec<Directory> {
+    let mut directories: Vec<Directory> = Vec::new();
+    for mut file in files {
+        let (path, filename) = match file.filename.rsplit_once('\\') {
+            Some((path, filename)) => (path.to_string(), filename.to_string()),
+            None => (String::new(), file.filename.clone()),
+        };
+        file.filename = filename;
+        match directories.last_mut() {
+            Some(directory) if directory.path == path => directory.files.push(file),
+            _ => directories.push(Directory {
+                path,
+                files: vec![file],
+            }),
+        }
+    }
+    directories
+}
+
+/// Shows our shares the way other users see them when they browse or search us
+#[derive(Clone)]
+pub(crate) struct SharesWindow<'a> {
+    title: String,
+    focus_index: u8,
+    pub(crate) search_input: Input<'a>,
+    file_list: Option<SharedFileListResponse>,
+    stats: ShareStats,
+    /// The query being previewed and how many results it had, if the browse table is showing search results
+    search: Option<(String, usize, usize)>,
+    browse_table: TableWidget<'a>,
+    extensions_table: TableWidget<'a>,
+    /// Where the last export went or why it failed, and whether it was an error
+    export_status: Option<(String, bool)>,
+}
+
+impl Default for SharesWindow<'_> {
+    fn default() -> Self {
+        Self {
+            title: String::from(" My Shares "),
+            focus_index: 0,
+            search_input: Input::default().title(String::from("Preview Search")),
+            file_list: None,
+            stats: ShareStats::default(),
+            search: None,
+            browse_table: browse_table(&[], &[]),
+            extensions_table: extensions_table(&ShareStats::default()),
+            export_status: None,
+        }
+    }
+}
+
+impl SharesWindow<'_> {
+    pub(crate) fn set_preview(&mut self, file_list: SharedFileListResponse, stats: ShareStats) {
+        self.extensions_table = extensions_table(&stats);
+        self.file_list = Some(file_list);
+        self.stats = stats;
+        if self.search.is_none() {
+            self.browse_table = self.listing_table();
+        }
+    }
+
+    pub(crate) fn set_search_preview(
+        &mut self,
+        query: String,
+        files: Vec<File>,
+        private_files: Vec<File>,
+    ) {
+        self.search = Some((query, files.len(), private_files.len()));
+        self.browse_table = shares_table(
+            &group_into_directories(files),
+            &group_into_directories(private_files),
+            &self.stats,
+        );
+    }
+
+    fn stop_search_preview(&mut self) {
+        self.search = None;
+        self.browse_table = self.listing_table();
+    }
+
+    /// Our shares as they're browsed, followed by any duplicate files in them
+    fn listing_table<'b>(&self) -> TableWidget<'b> {
+        let mut table = match &self.file_list {
+            Some(file_list) => shares_table(
+                &file_list.directories,
+                &file_list.priv_directories,
+                &self.stats,
+            ),
+            None => browse_table(&[], &[]),
+        };
+        if !self.stats.duplicates.is_empty() {
+            table.insert_item(duplicates_row(&self.stats.duplicates));
+        }
+        table
+    }
+
+    fn export(&mut self) {
+        let Some(file_list) = &self.file_list else {
+            return;
+        };
+        self.export_status = Some(match ShareListing::new(None, file_list).export() {
+            Ok(path) => (format!("Saved to {}", path.to_string_lossy()), false),
+            Err(e) => (format!("Couldn't save the listing: {e}"), true),
+        });
+    }
+
+    fn summary(&self) -> Paragraph<'_> {
+        let stats = &self.stats;
+        let mut lines = vec![
+            Line::from(format!(
+                "{} folders, {} files",
+                num_as_str(stats.folders),
+                num_as_str(stats.files)
+            )),
+            Line::from(format!("{} in total", num_as_bytes(stats.size))),
+            Line::from(format!(
+                "Metadata for {} of {} audio files",
+                num_as_str(stats.with_metadata),
+                num_as_str(stats.audio_files)
+            )),
+            Line::from(format!("{} unreadable", num_as_str(stats.unreadable.len()))),
+        ];
+        lines.extend(
+            stats
+                .unreadable
+                .iter()
+                .map(|path| Line::from(format!("  {path}"))),
+        );
+        Paragraph::new(lines).style(STYLE_DEFAULT).block(
+            Block::new()
+                .title("Statistics")
+                .style(STYLE_DEFAULT)
+                .borders(Borders::ALL),
+        )
+    }
+}
+
+impl Widget for SharesWindow<'_> {
+    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let chunks = Layout::new(
+            Direction::Vertical,
+            [Constraint::Length(3), Constraint::Min(0)],
+        )
+        .split(area);
+        let table_chunks = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Fill(3), Constraint::Fill(1)],
+        )
+        .split(chunks[1]);
+        let stats_chunks = Layout::new(
+            Direction::Vertical,
+            [Constraint::Fill(1), Constraint::Fill(1)],
+        )
+        .split(table_chunks[1]);
+
+        self.summary().render(stats_chunks[0], buf);
+        let mut browse_table = match &self.search {
+            Some((query, files, private_files)) => self.browse_table.title(format!(
+                "Searching \"{query}\" finds {} public and {} buddy-only files",
+                num_as_str(*files),
+                num_as_str(*private_files)
+            )),
+            None => match &self.export_status {
+                Some((status, is_error)) => self.browse_table.title(Line::styled(
+                    status.clone(),
+                    if *is_error {
+                        STYLE_FAIL_DEFAULT
+                    } else {
+                        STYLE_DEFAULT
+                    },
+                )),
+                None => self.browse_table.title("Browse"),
+            },
+        };
+        let mut extensions_table = self.extensions_table.title("Extensions");
+        render_widgets!(
+            SELF: self,
+            BUFFER: buf,
+            0 = (self.search_input) => chunks[0],
+            1 = (browse_table) => table_chunks[0],
+            2 = (extensions_table) => stats_chunks[1],
+        );
+    }
+}
+
+impl WidgetWithHints for SharesWindow<'_> {
+    fn get_hints(&self) -> Vec<(Event, String)> {
+        let mut hints = self
+            .get_widget(self.focus_index)
+            .map(|w| w.get_hints())
+            .unwrap_or_default();
+        if self.focus_index == 1 && self.search.is_none() {
+            hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE)),
+                String::from("Export listing"),
+            ));
+        }
+        if self.focus_index == 0 {
+            hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                String::from("Preview search"),
+            ));
+            if self.search.is_some() {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)),
+                    String::from("Back to browsing"),
+                ));
+            }
+        }
+        hints
+    }
+}
+
+impl Window<'_> for SharesWindow<'_> {
+    fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    fn perform_action(&mut self, focus_index: u8, event: Event, write_queue: &Sender<SLSKEvents>) {
+        let key = match event {
+            Event::Key(key) if key.modifiers == KeyModifiers::NONE => Some(key.code),
+            _ => None,
+        };
+        match focus_index {
+            0 => match key {
+                Some(KeyCode::Enter) => {
+                    let query = self.search_input.input.value().trim().to_string();
+                    if query.is_empty() {
+                        self.stop_search_preview();
+                    } else {
+                        let _ = write_queue.send(SLSKEvents::PreviewSearch { query });
+                    }
+                }
+                Some(KeyCode::Esc) => {
+                    self.search_input.clear();
+                    self.stop_search_preview();
+                }
+                _ => {
+                    self.search_input.handle_event(&event);
+                }
+            },
+            1 => match key {
+                Some(KeyCode::Char('x')) if self.search.is_none() => self.export(),
+                _ => {
+                    self.browse_table.handle_event(&event);
+                }
+            },
+            2 => {
+                self.extensions_table.handle_event(&event);
+            }
+            _ => unimplemented!("perform_action({focus_index}, {event:?})"),
+        };
+    }
+
+    fn number_of_widgets(&self) -> u8 {
+        3
+    }
+
+    fn get_widget(&self, index: u8) -> Option<&dyn SLSKWidget> {
+        match index {
+            0 => Some(&self.search_input),
+            1 => Some(&self.browse_table),
+            2 => Some(&self.extensions_table),
+            _ => unimplemented!(
+                "There are only {} widgets, it's impossible to get the widget with index {index}",
+                self.number_of_widgets()
+            ),
+        }
+    }
+
+    fn get_focused_index(&self) -> u8 {
+        self.focus_index
+    }
+
+    fn set_focused_index(&mut self, index: u8) {
+        self.focus_index = index;
+    }
+}
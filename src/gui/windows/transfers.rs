@@ -1,6 +1,11 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
-pub(crate) use crossterm::event::Event;
+pub(crate) use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use rand::random;
 use ratatui::{
     prelude::{Constraint, Direction, Layout, Rect},
     widgets::Widget,
@@ -9,16 +14,44 @@ use tokio::sync::{broadcast::Sender, RwLock};
 use tui_input::backend::crossterm::EventHandler;
 
 use crate::{
-    constants::{ByteSize, DownloadStatus, Percentage}, events::SLSKEvents, table::{ColumnData, TableItem, TableWidget}
+    constants::{ByteSize, ConnectionTypes, DownloadStatus, Percentage, UploadStatus}, events::SLSKEvents, messages::FileAttribute, table::{ColumnData, TableItem, TableWidget}, MessageTrait, QueueUpload
 };
 
 use super::{FocusableWidget, SLSKWidget, WidgetWithHints, Window};
 
+/// A file that's been queued for download: filename, size, status, progress and the attributes it was listed with
+pub(crate) type QueuedFile = (
+    String,
+    ByteSize,
+    Arc<RwLock<DownloadStatus>>,
+    Arc<RwLock<Percentage>>,
+    Vec<FileAttribute>,
+);
+
+/// A file another user has queued from us: filename, size, status and progress
+pub(crate) type QueuedUpload = (
+    String,
+    ByteSize,
+    Arc<RwLock<UploadStatus>>,
+    Arc<RwLock<Percentage>>,
+);
+
 #[derive(Clone)]
 pub(crate) struct TransfersWindow<'a> {
     title: String,
     focus_index: u8,
     downloads: TableWidget<'a>,
+    /// What's needed to queue a download again, by (username, remote path).
+    /// The download type is `None` for single files, otherwise whether it was part of a "download all".
+    retries: HashMap<(String, String), (Option<bool>, Vec<FileAttribute>)>,
+    /// Downloads that turned out to be files we already share, by (username, remote path)
+    local_copies: HashMap<(String, String), Vec<String>>,
+    /// Why downloads failed verification and where they were saved, by (username, remote path).
+    /// The path is `None` once the user has deleted the file.
+    verify_failures: HashMap<(String, String), (String, Option<PathBuf>)>,
+    /// Users the server says are privileged, the upload task sends their files first.
+    /// Only the uploads window is told about them, so it lists them in the order they'll be sent.
+    privileged_users: HashSet<String>,
 }
 
 impl Default for TransfersWindow<'_> {
@@ -47,31 +80,75 @@ impl Default for TransfersWindow<'_> {
                 ]),
             ),
             focus_index: 0,
+            retries: HashMap::new(),
+            local_copies: HashMap::new(),
+            verify_failures: HashMap::new(),
+            privileged_users: HashSet::new(),
+        }
+    }
+}
+
+impl TransfersWindow<'_> {
+    /// Files other users have queued from us
+    pub(crate) fn uploads() -> Self {
+        Self {
+            title: String::from(" Uploads "),
+            ..Default::default()
         }
     }
 }
 
 impl Widget for TransfersWindow<'_> {
-    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(0)
             .constraints([Constraint::Fill(1)])
             .split(area);
 
+        let mut downloads = match (self.selected_verify_failure(), self.selected_local_copy()) {
+            (Some((reason, Some(_))), _) => self
+                .downloads
+                .title(format!("Failed verification: {reason}")),
+            (Some((reason, None)), _) => self
+                .downloads
+                .title(format!("Failed verification: {reason} (deleted)")),
+            (None, Some(local_paths)) => self
+                .downloads
+                .title(format!("Already shared as {}", local_paths.join(", "))),
+            (None, None) if !self.local_copies.is_empty() => self.downloads.title(format!(
+                "{} downloads are files you already share",
+                self.local_copies.len()
+            )),
+            (None, None) => self.downloads.clone(),
+        };
         render_widgets!(
             SELF: self,
             BUFFER: buf,
-            0 = (self.downloads )=> chunks[0],
+            0 = (downloads) => chunks[0],
         );
     }
 }
 
 impl WidgetWithHints for TransfersWindow<'_> {
     fn get_hints(&self) -> Vec<(Event, String)> {
-        self.get_widget(self.focus_index)
-            .and_then(|w| Some(w.get_hints()))
-            .unwrap_or_default()
+        let mut hints = self
+            .get_widget(self.focus_index)
+            .map(|w| w.get_hints())
+            .unwrap_or_default();
+        if self.selected_status() == Some(DownloadStatus::VerifyFailed) {
+            hints.push((
+                Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)),
+                String::from("Retry download"),
+            ));
+            if let Some((_, Some(_))) = self.selected_verify_failure() {
+                hints.push((
+                    Event::Key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE)),
+                    String::from("Delete file"),
+                ));
+            }
+        }
+        hints
     }
 }
 
@@ -80,8 +157,16 @@ impl Window<'_> for TransfersWindow<'_> {
         self.title.clone()
     }
 
-    fn perform_action(&mut self, focus_index: u8, key: Event, _write_queue: &Sender<SLSKEvents>) {
+    fn perform_action(&mut self, focus_index: u8, key: Event, write_queue: &Sender<SLSKEvents>) {
         match focus_index {
+            0 if key == Event::Key(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE)) => {
+                self.retry_download(write_queue);
+                None
+            }
+            0 if key == Event::Key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE)) => {
+                self.delete_failed_download();
+                None
+            }
             0 => self.downloads.handle_event(&key),
             _ => unimplemented!("perform_action({focus_index}, {key:?})"),
         };
@@ -111,6 +196,143 @@ impl Window<'_> for TransfersWindow<'_> {
 }
 
 impl TransfersWindow<'_> {
+    fn selected_status(&self) -> Option<DownloadStatus> {
+        match &self.downloads.current_row()?.content[3] {
+            ColumnData::DownloadStatus(status) => Some(*status.blocking_read()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn add_local_copy(
+        &mut self,
+        username: String,
+        filename: String,
+        local_paths: Vec<String>,
+    ) {
+        self.local_copies.insert((username, filename), local_paths);
+    }
+
+    fn selected_local_copy(&self) -> Option<&Vec<String>> {
+        let item = self.downloads.current_row()?;
+        let remote_path = item.content.get(6)?.to_string();
+        self.local_copies.get(&(item.content[0].to_string(), remote_path))
+    }
+
+    pub(crate) fn add_verify_failure(
+        &mut self,
+        username: String,
+        filename: String,
+        path: PathBuf,
+        reason: String,
+    ) {
+        self.verify_failures
+            .insert((username, filename), (reason, Some(path)));
+    }
+
+    /// Why the selected download failed verification and where it was saved, if it did
+    fn selected_verify_failure(&self) -> Option<&(String, Option<PathBuf>)> {
+        if self.selected_status() != Some(DownloadStatus::VerifyFailed) {
+            return None;
+        }
+        let item = self.downloads.current_row()?;
+        let remote_path = item.content.get(6)?.to_string();
+        self.verify_failures
+            .get(&(item.content[0].to_string(), remote_path))
+    }
+
+    /// Deletes the file the selected download was saved to, if it failed verification
+    fn delete_failed_download(&mut self) {
+        if self.selected_status() != Some(DownloadStatus::VerifyFailed) {
+            return;
+        }
+        let Some(item) = self.downloads.current_row() else {
+            return;
+        };
+        let Some(remote_path) = item.content.get(6).map(|path| path.to_string()) else {
+            return;
+        };
+        let key = (item.content[0].to_string(), remote_path);
+        if let Some((_, path)) = self.verify_failures.get_mut(&key) {
+            if path
+                .as_ref()
+                .is_some_and(|path| std::fs::remove_file(path).is_ok())
+            {
+                *path = None;
+            }
+        }
+    }
+
+    /// Queues the selected download again if it failed verification
+    fn retry_download(&mut self, write_queue: &Sender<SLSKEvents>) {
+        let Some(item) = self.downloads.current_row() else {
+            return;
+        };
+        // only file rows have a single status and percentage
+        let (ColumnData::DownloadStatus(status), ColumnData::Percentage(percentage)) =
+            (&item.content[3], &item.content[4])
+        else {
+            return;
+        };
+        if *status.blocking_read() != DownloadStatus::VerifyFailed {
+            return;
+        }
+        let username = item.content[0].to_string();
+        let filename = item.content[6].to_string();
+        let Some((download_type, attributes)) =
+            self.retries.get(&(username.clone(), filename.clone())).cloned()
+        else {
+            return;
+        };
+
+        self.verify_failures
+            .remove(&(username.clone(), filename.clone()));
+        *status.blocking_write() = DownloadStatus::Queued;
+        *percentage.blocking_write() = Percentage(0);
+
+        let token = random::<u32>();
+        let _ = write_queue.send(SLSKEvents::QueueMessage {
+            token,
+            message_bytes: QueueUpload::to_bytes(QueueUpload {
+                filename: filename.clone(),
+            }),
+        });
+        let _ = write_queue.send(match download_type {
+            Some(from_all) => SLSKEvents::UpdateDownloads {
+                files: vec![(filename, Arc::clone(status), Arc::clone(percentage), attributes)],
+                from_all,
+            },
+            None => SLSKEvents::UpdateDownload {
+                filename,
+                status: Arc::clone(status),
+                percentage: Arc::clone(percentage),
+                attributes,
+            },
+        });
+        let _ = write_queue.send(SLSKEvents::Connect {
+            username,
+            token,
+            connection_type: ConnectionTypes::PeerToPeer,
+        });
+    }
+
+    pub(crate) fn set_privileged_users(&mut self, usernames: Vec<String>) {
+        self.privileged_users = usernames.into_iter().collect();
+        self.privileged_first();
+    }
+
+    pub(crate) fn add_privileged_user(&mut self, username: String) {
+        self.privileged_users.insert(username);
+        self.privileged_first();
+    }
+
+    /// Lists privileged users first, keeping everyone else in the order they queued in
+    fn privileged_first(&mut self) {
+        let privileged_users = &self.privileged_users;
+        self.downloads
+            .items
+            .sort_by_key(|item| !privileged_users.contains(&item.content[0].to_string()));
+    }
+
     fn add_item_helper(&mut self, item: TableItem, username: String, filesize: ByteSize) {
         let item_len = item.length(self.downloads.filter().as_deref().map(|f| f.as_str()));
         match self
@@ -144,17 +366,18 @@ impl TransfersWindow<'_> {
                 .open(),
             ),
         }
+        self.privileged_first();
     }
 
     pub(crate) fn add_file(
         &mut self,
         username: String,
         folder: String,
-        filename: String,
-        filesize: ByteSize,
-        status: Arc<RwLock<DownloadStatus>>,
-        percentage: Arc<RwLock<Percentage>>,
+        (filename, filesize, status, percentage, attributes): QueuedFile,
     ) {
+        let remote_path = format!("{folder}{filename}");
+        self.retries
+            .insert((username.clone(), remote_path.clone()), (None, attributes));
         let item = TableItem::new(
             vec![
                 username.clone().into(),
@@ -174,6 +397,7 @@ impl TransfersWindow<'_> {
                         ColumnData::DownloadStatus(status),
                         percentage,
                         filesize.into(),
+                        remote_path.into(),
                     ]
                 },
                 Vec::new(),
@@ -184,27 +408,63 @@ impl TransfersWindow<'_> {
         self.add_item_helper(item, username, filesize);
     }
 
+    /// Adds a file another user queued from us, `folder` ends with a backslash as it does for downloads
+    pub(crate) fn add_upload(
+        &mut self,
+        username: String,
+        folder: String,
+        (filename, filesize, status, percentage): QueuedUpload,
+    ) {
+        let remote_path = format!("{folder}{filename}");
+        let item = TableItem::new(
+            vec![
+                username.clone().into(),
+                folder.into(),
+                ColumnData::Empty,
+                ColumnData::UploadStatuses(vec![Arc::clone(&status)]),
+                ColumnData::Percentages(vec![(Arc::clone(&percentage), filesize.0)]),
+                filesize.into(),
+            ],
+            vec![TableItem::new(
+                vec![
+                    username.clone().into(),
+                    ColumnData::Empty,
+                    filename.into(),
+                    ColumnData::UploadStatus(status),
+                    ColumnData::Percentage(percentage),
+                    filesize.into(),
+                    remote_path.into(),
+                ],
+                Vec::new(),
+            )
+            .open()],
+        )
+        .open();
+        self.add_item_helper(item, username, filesize);
+    }
+
     pub(crate) fn add_folder(
         &mut self,
         username: String,
         folder: String,
-        files: Vec<(
-            String,
-            ByteSize,
-            Arc<RwLock<DownloadStatus>>,
-            Arc<RwLock<Percentage>>,
-        )>,
+        files: Vec<QueuedFile>,
+        from_all: bool,
     ) {
-        let total_filesize = ByteSize(files.iter().map(|(_, b, _, _)| b.0).sum());
+        let total_filesize = ByteSize(files.iter().map(|(_, b, _, _, _)| b.0).sum());
 
         let mut download_statuses = Vec::with_capacity(files.len());
         let mut percentages = Vec::with_capacity(files.len());
 
         let children = files
             .into_iter()
-            .map(|(filename, filesize, status, percentage)| {
+            .map(|(filename, filesize, status, percentage, attributes)| {
                 download_statuses.push(Arc::clone(&status));
                 percentages.push((Arc::clone(&percentage), filesize.0));
+                let remote_path = format!("{folder}{filename}");
+                self.retries.insert(
+                    (username.clone(), remote_path.clone()),
+                    (Some(from_all), attributes),
+                );
 
                 TableItem::new(
                     vec![
@@ -214,6 +474,7 @@ impl TransfersWindow<'_> {
                         ColumnData::DownloadStatus(status),
                         ColumnData::Percentage(percentage),
                         filesize.into(),
+                        remote_path.into(),
                     ],
                     Vec::new(),
                 )
@@ -0,0 +1,198 @@
+//! Share listings saved as JSON, so a user's shares can be browsed when they're offline
+//! or compared with an older copy.
+//!
+//! A listing looks like this. Attributes a file doesn't have are left out, and
+//! `username` is `null` for our own shares.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "username": "someone",
+//!   "exported_at": "2024-01-31 18:00:00",
+//!   "directories": [
+//!     {
+//!       "path": "Music\\Artist\\Album",
+//!       "files": [
+//!         {
+//!           "filename": "01 - Track.flac",
+//!           "size": 31457280,
+//!           "extension": "flac",
+//!           "bitrate": 1024,
+//!           "duration": 245,
+//!           "vbr": false,
+//!           "encoder": 3,
+//!           "sample_rate": 44100,
+//!           "bit_depth": 16
+//!         }
+//!       ]
+//!     }
+//!   ],
+//!   "private_directories": []
+//! }
+//! ```
+//!
+//! `private_directories` are the buddy-only ones. Directories and files are kept in the
+//! order they were shared in, so two listings of the same user diff cleanly.
+
+use std::{
+    fs::{create_dir_all, read_to_string, File as FsFile},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    messages::{Directory, File, FileAttribute, SharedFileListResponse},
+    utils::now_as_string,
+};
+
+/// Bumped whenever a change means older versions can't read the listing
+const LISTING_VERSION: u32 = 1;
+/// Where listings are exported to
+pub(crate) const EXPORTS_PATH: &str = "exports";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ShareListing {
+    pub(crate) version: u32,
+    pub(crate) username: Option<String>,
+    pub(crate) exported_at: String,
+    pub(crate) directories: Vec<ListedDirectory>,
+    pub(crate) private_directories: Vec<ListedDirectory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ListedDirectory {
+    pub(crate) path: String,
+    pub(crate) files: Vec<ListedFile>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct ListedFile {
+    pub(crate) filename: String,
+    pub(crate) size: u64,
+    #[serde(default)]
+    pub(crate) extension: String,
+    /// Kbps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bitrate: Option<u32>,
+    /// Seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) duration: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) vbr: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) encoder: Option<u32>,
+    /// Hz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sample_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) bit_depth: Option<u32>,
+}
+
+impl From<&File> for ListedFile {
+    fn from(file: &File) -> Self {
+        let mut listed = Self {
+            filename: file.filename.clone(),
+            size: file.file_size,
+            extension: file.extension.clone(),
+            ..Default::default()
+        };
+        for attribute in &file.attributes {
+            match *attribute {
+                FileAttribute::Bitrate(bitrate) => listed.bitrate = Some(bitrate),
+                FileAttribute::Duration(duration) => listed.duration = Some(duration),
+                FileAttribute::VBR(vbr) => listed.vbr = Some(vbr),
+                FileAttribute::Encoder(encoder) => listed.encoder = Some(encoder),
+                FileAttribute::SampleRate(sample_rate) => listed.sample_rate = Some(sample_rate),
+                FileAttribute::BitDepth(bit_depth) => listed.bit_depth = Some(bit_depth),
+            }
+        }
+        listed
+    }
+}
+
+impl From<&ListedFile> for File {
+    fn from(file: &ListedFile) -> Self {
+        File {
+            code: 1,
+            filename: file.filename.clone(),
+            file_size: file.size,
+            extension: file.extension.clone(),
+            attributes: FileAttribute::from_parts(
+                file.bitrate,
+                file.duration,
+                file.vbr,
+                file.encoder,
+                file.sample_rate,
+                file.bit_depth,
+            ),
+        }
+    }
+}
+
+fn list_directories(directories: &[Directory]) -> Vec<ListedDirectory> {
+    directories
+        .iter()
+        .map(|directory| ListedDirectory {
+            path: directory.path.clone(),
+            files: directory.files.iter().map(ListedFile::from).collect(),
+        })
+        .collect()
+}
+
+fn unlist_directories(directories: &[ListedDirectory]) -> Vec<Directory> {
+    directories
+        .iter()
+        .map(|directory| Directory {
+            path: directory.path.clone(),
+            files: directory.files.iter().map(File::from).collect(),
+        })
+        .collect()
+}
+
+impl ShareListing {
+    pub(crate) fn new(username: Option<String>, file_list: &SharedFileListResponse) -> Self {
+        Self {
+            version: LISTING_VERSION,
+            username,
+            exported_at: now_as_string(),
+            directories: list_directories(&file_list.directories),
+            private_directories: list_directories(&file_list.priv_directories),
+        }
+    }
+
+    pub(crate) fn file_list(&self) -> SharedFileListResponse {
+        SharedFileListResponse {
+            directories: unlist_directories(&self.directories),
+            _unknown_0: 0,
+            priv_directories: unlist_directories(&self.private_directories),
+        }
+    }
+
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        let listing: Self = serde_json::from_str(&read_to_string(path)?)?;
+        if listing.version > LISTING_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("listing version {} is too new", listing.version),
+            ));
+        }
+        Ok(listing)
+    }
+
+    /// Saves the listing in the exports folder, named after the user and when it was exported
+    pub(crate) fn export(&self) -> io::Result<PathBuf> {
+        create_dir_all(EXPORTS_PATH)?;
+        let name = format!(
+            "{} {}",
+            self.username.as_deref().unwrap_or("my shares"),
+            self.exported_at
+        );
+        // usernames can have slashes in them, and colons aren't allowed in filenames on Windows
+        let path =
+            Path::new(EXPORTS_PATH).join(format!("{}.json", name.replace(['/', '\\', ':'], "-")));
+        FsFile::create(&path)?.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(path)
+    }
+}
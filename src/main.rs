@@ -1,14 +1,20 @@
 #[macro_use]
 mod macros;
+mod analysis;
 mod config;
 mod constants;
 mod events;
 mod gui;
+mod listing;
 mod messages;
+mod nicotine;
+mod obfuscation;
 mod packing;
 mod parsers;
 pub(crate) mod peer_handling;
 pub(crate) mod server_handling;
+pub(crate) mod shares_handling;
+pub(crate) mod upload_handling;
 mod sql;
 #[allow(dead_code)]
 mod styles;
@@ -16,24 +22,29 @@ mod utils;
 pub(crate) mod file_transfer;
 
 use crate::config::{Config, CONFIG_PATH};
-use crate::constants::{DownloadStatus, Percentage};
+use crate::constants::{ConnectionState, DownloadStatus, Percentage};
 use crate::events::SLSKEvents;
 use crate::messages::*;
+use crate::obfuscation::PeerStream;
 use crate::packing::UnpackFromBytes;
-use crate::peer_handling::{start_listener_task, start_peer_task};
-use crate::server_handling::{start_server_read_task, start_server_write_task};
+use crate::peer_handling::{start_listener_task, start_peer_task, PeerAddress};
+use crate::server_handling::{
+    start_server_ping_task, start_server_read_task, start_server_write_task, SessionState,
+};
+use crate::shares_handling::start_shares_task;
 use crate::sql::DiskIndex;
-use crate::utils::keepalive_add_retries;
+use crate::upload_handling::{start_upload_task, UploadMap};
+use crate::utils::{keepalive_add_retries, log};
 
 use constants::{ConnectionTypes, TransferDirections, MAX_RESULTS};
 use crossbeam_deque::Worker;
 use gui::widgets::table;
+use rand::random;
 use serde::Deserialize;
 use smol::Timer;
 use socket2::{SockRef, TcpKeepalive};
 use std::collections::{HashMap, VecDeque};
 use std::io::{read_to_string, Error, ErrorKind, Write};
-use std::net::Ipv4Addr;
 use std::path::Path;
 use std::sync::Arc;
 use std::thread::{self};
@@ -49,6 +60,11 @@ const CHUNK_SIZE: usize = 500_000; // half a MB
 const CONNECTION_TIME: u64 = 5;
 const LOGGING_ENABLED: bool = false;
 
+/// The shortest and longest we wait before reconnecting to the server, in seconds.
+/// The wait doubles with each failure in between.
+const RECONNECT_DELAY_MIN: u64 = 2;
+const RECONNECT_DELAY_MAX: u64 = 300;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     std::env::set_var("RUST_BACKTRACE", "1");
@@ -57,6 +73,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         server: Default::default(),
         user: Default::default(),
         index: DiskIndex::new(".shares").await?,
+        downloads: Default::default(),
+        shares: Default::default(),
+        users: Default::default(),
+        interests: Default::default(),
+        rooms: Default::default(),
+        wishlist: Default::default(),
+        away: Default::default(),
     };
 
     let config_path = Path::new(CONFIG_PATH);
@@ -91,20 +114,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let shares_message = Arc::new(RwLock::new(None));
 
-    // update the file index in the background
-    // this stops the client freezing for ages while the files are being indexed for the first time
-    tokio::task::spawn({
-        let mut index = config.index.clone();
-        let shares_message = Arc::clone(&shares_message);
-        async move {
-            let _ = index.reindex_all().await;
-            *shares_message.write().await = Some(match index.file_list().await {
-                Ok(file_list) => SharedFileListResponse::to_bytes(file_list),
-                Err(_) => Vec::new(),
-            });
-        }
-    });
-
     let config = Arc::new(RwLock::new(config));
     let gui_config = Arc::clone(&config);
     let connection_config = Arc::clone(&config);
@@ -113,6 +122,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let gui_read_queue = read_queue.resubscribe();
     let gui_write_queue = write_queue.clone();
 
+    start_shares_task(
+        Arc::clone(&config),
+        read_queue.resubscribe(),
+        write_queue.clone(),
+        Arc::clone(&shares_message),
+    )
+    .await;
+
     thread::spawn(
         move || match gui::main(gui_write_queue, gui_read_queue, gui_config) {
             Ok(()) => {
@@ -121,103 +138,189 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Err(e) => panic!("{e}"),
         },
     );
-    let mut login_timeout: u64 = 15;
 
-    let mut temp_receiver = read_queue.resubscribe();
-
-    loop {
-        if !'outer: loop {
-            let should_restart = async {
-                let mut quit = None;
-                while quit.is_none() {
-                    let event = temp_receiver.recv().await;
-                    quit = match event {
-                        Ok(event) => match event {
-                            SLSKEvents::Quit { restart } => Some(restart),
-                            _ => None,
-                        },
-                        Err(_) => None,
-                    };
-                }
-                quit
-            };
+    let auto_login = connection_config.read().await.server.auto_connect;
+    Supervisor {
+        config: connection_config,
+        write_queue,
+        quit_receiver: read_queue.resubscribe(),
+        session: Arc::new(RwLock::new(SessionState::default())),
+        auto_login,
+        failures: 0,
+    }
+    .run(read_queue, shares_message)
+    .await;
+    Ok(())
+}
 
-            let server_address = { connection_config.read().await.server.to_string() };
+/// Keeps us connected to the server, reconnecting when the connection drops
+struct Supervisor {
+    config: Arc<RwLock<Config>>,
+    write_queue: Sender<SLSKEvents>,
+    /// Used to notice the GUI quitting while we're not connected
+    quit_receiver: Receiver<SLSKEvents>,
+    session: Arc<RwLock<SessionState>>,
+    /// Whether to log in as soon as we connect
+    auto_login: bool,
+    /// How many times in a row we've failed to connect or stay connected
+    failures: u32,
+}
 
-            // TODO: Make initial connection more robust, handling disconnection and displaying info in the UI properly (rather than printing)
-            let stream: Result<TcpStream, Result<bool, Error>> = tokio::select! {
+impl Supervisor {
+    async fn run(
+        mut self,
+        read_queue: Receiver<SLSKEvents>,
+        shares_message: Arc<RwLock<Option<Vec<u8>>>>,
+    ) {
+        loop {
+            self.set_state(ConnectionState::Connecting);
+            let server_address = { self.config.read().await.server.to_string() };
+            let connect_result = tokio::select! {
                 // Wait for the connection to complete
-                connect_result = TcpStream::connect(server_address) => match connect_result {
-                    Ok(connect_result) => Ok(connect_result),
-                    Err(e) => Err(Err(e)),
-                },
-                // Wait for should_restart to complete
-                restart = should_restart => {
-                    if let Some(restart) = restart {
-                        Err(Ok(restart))
-                    } else {unimplemented!()}
-                }
+                connect_result = TcpStream::connect(server_address) => Ok(connect_result),
+                // Wait for the GUI to quit or restart
+                restart = wait_for_quit(&mut self.quit_receiver) => Err(restart),
             };
-            match stream {
-                Ok(stream) => {
-                    let sock_ref = SockRef::from(&stream);
-                    // These specific settings are based on nicotine+'s settings
-                    let mut ka = TcpKeepalive::new()
-                        .with_time(Duration::from_secs(10))
-                        .with_interval(Duration::from_secs(2));
-                    ka = keepalive_add_retries(ka);
-                    sock_ref.set_tcp_keepalive(&ka)?;
-                    // TODO: Set the number of TCP pings allowed before the connection is assumed to be dead (should be 10)
-                    // on platforms where it's not supported by socket2 (e.g. Windows)
-                    // Not 100% sure this is actually possible as it's an OS limitation, but I imagine it could be implemented manually.
-
-                    let handle = tokio::spawn(handle_client(
-                        stream,
-                        write_queue.clone(),
-                        read_queue.resubscribe(),
-                        Arc::clone(&config),
-                        Arc::clone(&shares_message),
-                    ));
-
-                    match handle.await {
-                        Ok(slskexit) => match slskexit {
-                            SLSKExitCode::LoginFail => (),
-                            SLSKExitCode::IoError(e) => {
-                                if e.kind() == ErrorKind::AddrInUse {
-                                    panic!("The port you tried to use is already in use. You should edit the config file, or free the port.");
-                                } else {
-                                    panic!("{e}");
-                                }
-                            }
-                            _ => {
-                                break true;
-                            }
-                        },
-                        Err(e) => {
-                            panic!("{e}");
-                        }
-                    };
+            let stream = match connect_result {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    if self.retry_later(format!("Couldn't connect ({e})")).await {
+                        continue;
+                    }
+                    break;
                 }
-                Err(Ok(restart)) => break 'outer restart,
-                Err(Err(e)) => {
-                    if e.raw_os_error() == Some(11001) {
-                        // If "no such host is known" (Not connected to the internet?)
+                Err(restart) => {
+                    if self.quit(restart).await {
                         continue;
                     }
-                    println!(
-                        "stream fail: {e}, sleeping for {login_timeout} seconds. {:?}",
-                        e
-                    );
-                    Timer::after(Duration::from_secs(login_timeout)).await;
-                    println!("sleep finished");
-                    login_timeout *= 2;
+                    break;
+                }
+            };
+            self.set_state(ConnectionState::Connected);
+
+            let sock_ref = SockRef::from(&stream);
+            // These specific settings are based on nicotine+'s settings
+            let mut ka = TcpKeepalive::new()
+                .with_time(Duration::from_secs(10))
+                .with_interval(Duration::from_secs(2));
+            ka = keepalive_add_retries(ka);
+            if let Err(e) = sock_ref.set_tcp_keepalive(&ka) {
+                log(format!(
+                    "Couldn't set the server connection's keepalive: {e}"
+                ));
+            }
+            // TODO: Set the number of TCP pings allowed before the connection is assumed to be dead (should be 10)
+            // on platforms where it's not supported by socket2 (e.g. Windows)
+            // Not 100% sure this is actually possible as it's an OS limitation, but I imagine it could be implemented manually.
+
+            let handle = tokio::spawn(handle_client(
+                stream,
+                self.write_queue.clone(),
+                read_queue.resubscribe(),
+                Arc::clone(&self.config),
+                Arc::clone(&shares_message),
+                Arc::clone(&self.session),
+                self.auto_login,
+            ));
+            let exit = handle.await;
+            // any earlier events were dealt with while we were connected
+            self.quit_receiver = self.quit_receiver.resubscribe();
+
+            let carry_on = match exit {
+                // we logged out, so the GUI has started again
+                Ok(SLSKExitCode::Ok) => {
+                    self.restart().await;
+                    true
+                }
+                Ok(SLSKExitCode::Restart) => false,
+                Ok(SLSKExitCode::LoginFail) => {
+                    // the server disconnects us, so connect again for the user to try different details
+                    self.auto_login = false;
+                    true
+                }
+                Ok(SLSKExitCode::OtherLogin) => {
+                    // reconnecting would log the other session out, so wait to be asked
+                    self.wait_to_be_asked(String::from("logged in from somewhere else"))
+                        .await
+                }
+                Ok(SLSKExitCode::ConnectionLost { error, logged_in }) => {
+                    if logged_in {
+                        // log back in without the user noticing, if we can
+                        self.auto_login = true;
+                        self.failures = 0;
+                    }
+                    self.retry_later(format!("Connection lost ({error})")).await
+                }
+                Ok(SLSKExitCode::IoError(e)) if e.kind() == ErrorKind::AddrInUse => {
+                    let port = self.config.read().await.user.port;
+                    self.retry_later(format!("Port {port} is already in use"))
+                        .await
+                }
+                Ok(SLSKExitCode::IoError(e)) => self.retry_later(e.to_string()).await,
+                Ok(SLSKExitCode::JoinError(e)) | Err(e) => {
+                    self.retry_later(format!("Connection failed ({e})")).await
                 }
             };
-        } {
-            break;
+            if !carry_on {
+                break;
+            }
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        let _ = self.write_queue.send(SLSKEvents::ConnectionState(state));
+    }
+
+    /// Forgets everything from before the GUI started again
+    async fn restart(&mut self) {
+        self.session.write().await.clear();
+        self.auto_login = self.config.read().await.server.auto_connect;
+        self.failures = 0;
+    }
+
+    /// Handles the GUI quitting, returning whether to carry on
+    async fn quit(&mut self, restart: bool) -> bool {
+        if restart {
+            self.restart().await;
+        }
+        restart
+    }
+
+    /// Waits longer after each failure before connecting again, returning whether to carry on
+    async fn retry_later(&mut self, reason: String) -> bool {
+        let seconds = reconnect_delay(self.failures);
+        self.failures = self.failures.saturating_add(1);
+        self.set_state(ConnectionState::WaitingToRetry { seconds, reason });
+        let restart = tokio::select! {
+            _ = Timer::after(Duration::from_secs(seconds)) => return true,
+            restart = wait_for_quit(&mut self.quit_receiver) => restart,
+        };
+        self.quit(restart).await
+    }
+
+    /// Waits for the GUI to ask before connecting again, returning whether to carry on
+    async fn wait_to_be_asked(&mut self, reason: String) -> bool {
+        self.set_state(ConnectionState::Offline { reason });
+        let restart = wait_for_quit(&mut self.quit_receiver).await;
+        self.quit(restart).await
+    }
+}
+
+/// How many seconds to wait before connecting again after `failures` failures,
+/// give or take a quarter so everyone disconnected at once doesn't reconnect at once
+fn reconnect_delay(failures: u32) -> u64 {
+    let delay = RECONNECT_DELAY_MIN.saturating_mul(1 << failures.min(16));
+    let jitter = 0.75 + random::<f64>() / 2.0;
+    ((delay as f64 * jitter).round() as u64).clamp(RECONNECT_DELAY_MIN, RECONNECT_DELAY_MAX)
+}
+
+/// Waits for the GUI to quit, returning whether it's restarting
+async fn wait_for_quit(receiver: &mut Receiver<SLSKEvents>) -> bool {
+    loop {
+        if let Ok(SLSKEvents::Quit { restart }) = receiver.recv().await {
+            return restart;
         }
     }
-    Ok(())
 }
 
 #[allow(dead_code)]
@@ -229,6 +332,11 @@ enum SLSKExitCode {
     JoinError(JoinError),
     OtherLogin,
     IoError(Error),
+    /// The server connection dropped, after we'd logged in or before
+    ConnectionLost {
+        error: Error,
+        logged_in: bool,
+    },
 }
 
 async fn handle_client(
@@ -237,6 +345,8 @@ async fn handle_client(
     read_queue: Receiver<SLSKEvents>,
     config: Arc<RwLock<Config>>,
     shares_message: Arc<RwLock<Option<Vec<u8>>>>,
+    session: Arc<RwLock<SessionState>>,
+    auto_login: bool,
 ) -> SLSKExitCode {
     let (reader, writer) = stream.into_split();
 
@@ -247,6 +357,22 @@ async fn handle_client(
     };
 
     let my_port: u32 = listener.local_addr().unwrap().port().into();
+
+    // obfuscated connections are made to the next port up, which we can do without if it's taken
+    let obfuscated_port = if port == 0 { 0 } else { port.saturating_add(1) };
+    let obfuscated_listener = match TcpListener::bind(format!("0.0.0.0:{obfuscated_port}")).await {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            log(format!(
+                "Couldn't listen for obfuscated connections on port {obfuscated_port}: {e}"
+            ));
+            None
+        }
+    };
+    let my_obfuscated_port = obfuscated_listener
+        .as_ref()
+        .and_then(|listener| listener.local_addr().ok())
+        .map(|address| address.port().into());
     let my_username = Arc::new(RwLock::new(None));
     let server_my_username = Arc::clone(&my_username);
     let config_username = config.read().await.user.name.clone();
@@ -254,24 +380,28 @@ async fn handle_client(
     let quit = Arc::new(RwLock::new(false));
     // We have to clone the quit flag so it can be read in different tokio tasks
     let quit_write = Arc::clone(&quit);
+    let quit_client = Arc::clone(&quit);
 
     let logged_in = Arc::new(RwLock::new(false));
     // The listener needs to know if we're logged in so it can ignore connections we may receive from previous sessions.
     // This can happen if you logout and login in quick succession.
     let logged_in_listener = Arc::clone(&logged_in);
+    let logged_in_client = Arc::clone(&logged_in);
+    let logged_in_ping = Arc::clone(&logged_in);
 
     let indirect_peers_list_writer = Worker::<_ReceiveConnectToPeer>::new_fifo();
-    let direct_peers_list_writer = Worker::<(TcpStream, String, u32, ConnectionTypes)>::new_fifo();
+    let direct_peers_list_writer = Worker::<(PeerStream, String, u32, ConnectionTypes)>::new_fifo();
     let prompted_peers_list_writer = Worker::<(String, u32, ConnectionTypes)>::new_fifo();
     let indirect_peers_list_reader = indirect_peers_list_writer.stealer();
     let direct_peers_list_reader = direct_peers_list_writer.stealer();
     let prompted_peers_list_reader = prompted_peers_list_writer.stealer();
     let peer_write_queue = write_queue.clone();
     let writer_write_queue = write_queue.clone();
+    let ping_write_queue = write_queue.clone();
 
     let file_info_map = Arc::new(Mutex::new(HashMap::<u32, VecDeque<(String, u64)>>::new()));
 
-    let user_info_map = Arc::new(Mutex::new(HashMap::<String, (Ipv4Addr, u32)>::new()));
+    let user_info_map = Arc::new(Mutex::new(HashMap::<String, PeerAddress>::new()));
     let peer_user_info_map = Arc::clone(&user_info_map);
     let writer_user_info_map = Arc::clone(&user_info_map);
 
@@ -284,10 +414,22 @@ async fn handle_client(
             Arc<RwLock<DownloadStatus>>,
             Arc<RwLock<Percentage>>,
             Option<bool>,
+            Option<Vec<FileAttribute>>,
         )>,
     >::new()));
     let peer_download_filename_map = Arc::clone(&download_filename_map);
 
+    let upload_map: UploadMap = Arc::new(Mutex::new(HashMap::new()));
+    let peer_upload_map = Arc::clone(&upload_map);
+    // started before logging in, so it hears about privileged users
+    let upload_task = start_upload_task(
+        Arc::clone(&config),
+        upload_map,
+        read_queue.resubscribe(),
+        write_queue.clone(),
+    )
+    .await;
+
     // Spawn separate tasks for reading and writing
     let server_read_task = start_server_read_task(
         quit,
@@ -301,6 +443,7 @@ async fn handle_client(
     )
     .await;
 
+    let peer_config = Arc::clone(&config);
     let server_write_task = start_server_write_task(
         quit_write,
         config,
@@ -308,16 +451,26 @@ async fn handle_client(
         writer,
         my_username.clone(),
         my_port,
+        my_obfuscated_port,
         token_message_map,
         writer_user_info_map,
         writer_write_queue,
         prompted_peers_list_writer,
         download_filename_map,
+        session,
+        auto_login,
     )
     .await;
 
-    let listener_task =
-        start_listener_task(listener, logged_in_listener, direct_peers_list_writer).await;
+    let listener_task = start_listener_task(
+        listener,
+        obfuscated_listener,
+        logged_in_listener,
+        direct_peers_list_writer,
+    )
+    .await;
+
+    let ping_task = start_server_ping_task(logged_in_ping, ping_write_queue).await;
 
     // TODO: Handle old peers better
     // Peers who try to send data from a previous search (now deleted/invalid) still get sent to the queue.
@@ -335,30 +488,42 @@ async fn handle_client(
         peer_token_message_map,
         file_info_map,
         peer_download_filename_map,
+        peer_upload_map,
         shares_message,
+        peer_config,
     )
     .await;
 
     let read_result = server_read_task.await;
-    match read_result {
-        Ok(exit) => match exit {
-            SLSKExitCode::LoginFail => {
-                peer_task.abort();
-                server_write_task.abort();
-                listener_task.abort();
-                return SLSKExitCode::LoginFail;
-            }
-            _ => (),
-        },
-        Err(_) => (),
+    let exit = match read_result {
+        // reading fails after we quit because the writer shuts the connection
+        Ok(SLSKExitCode::IoError(error)) if !*quit_client.read().await => {
+            Some(SLSKExitCode::ConnectionLost {
+                error,
+                logged_in: *logged_in_client.read().await,
+            })
+        }
+        Ok(exit @ (SLSKExitCode::LoginFail | SLSKExitCode::OtherLogin)) => Some(exit),
+        Ok(_) => None,
+        Err(e) => Some(SLSKExitCode::JoinError(e)),
+    };
+    if let Some(exit) = exit {
+        server_write_task.abort();
+        peer_task.abort();
+        upload_task.abort();
+        listener_task.abort();
+        ping_task.abort();
+        return exit;
     }
 
     let write_result = server_write_task.await;
+    // the next session needs the port
+    peer_task.abort();
+    upload_task.abort();
+    listener_task.abort();
+    ping_task.abort();
     match write_result {
-        Ok(exit) => {
-            println!("handle_client finished!");
-            exit
-        }
+        Ok(exit) => exit,
         Err(e) => SLSKExitCode::JoinError(e),
     }
 }
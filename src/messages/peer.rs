@@ -29,6 +29,7 @@ impl FileAttribute {
         bitrate: Option<u32>,
         duration: Option<u32>,
         vbr: Option<bool>,
+        encoder: Option<u32>,
         sample_rate: Option<u32>,
         bit_depth: Option<u32>,
     ) -> Vec<Self> {
@@ -36,6 +37,7 @@ impl FileAttribute {
             bitrate.map(|bitrate| FileAttribute::Bitrate(bitrate)),
             duration.map(|duration| FileAttribute::Duration(duration)),
             vbr.map(|vbr| FileAttribute::VBR(vbr)),
+            encoder.map(FileAttribute::Encoder),
             sample_rate.map(|sample_rate| FileAttribute::SampleRate(sample_rate)),
             bit_depth.map(|bit_depth| FileAttribute::BitDepth(bit_depth)),
         ]
@@ -256,11 +258,43 @@ impl_message_trait!(
     FolderContentsRequest > (MessageType::Peer(36))
 );
 
-define_message_to_send_and_receive!(FolderContentsResponse {
-    token: u32,
-    folder: String,
-    folders: Vec<Directory>,
-});
+#[derive(Debug, Clone)]
+pub struct FolderContentsResponse {
+    pub token: u32,
+    pub folder: String,
+    pub folders: Vec<Directory>,
+}
+
+impl PackToBytes for FolderContentsResponse {
+    fn pack_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.token.pack_to_bytes();
+        bytes.extend(self.folder.pack_to_bytes());
+        bytes.extend(self.folders.pack_to_bytes());
+
+        let mut writer = ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        let _ = writer.write_all(&bytes);
+        writer.finish().unwrap_or_default()
+    }
+}
+
+impl UnpackFromBytes for FolderContentsResponse {
+    fn unpack_from_bytes(buf: &mut Vec<u8>) -> Option<Self> {
+        let mut bytes = Vec::new();
+        ZlibDecoder::new(buf.as_slice())
+            .read_to_end(&mut bytes)
+            .ok()?;
+        let token = <u32>::unpack_from_bytes(&mut bytes)?;
+        let folder = <String>::unpack_from_bytes(&mut bytes)?;
+        let folders = <Vec<Directory>>::unpack_from_bytes(&mut bytes)?;
+
+        Some(Self {
+            token,
+            folder,
+            folders,
+        })
+    }
+}
+
 impl_message_trait!(
     FolderContentsResponse < FolderContentsResponse,
     FolderContentsResponse > (MessageType::Peer(37))
@@ -77,7 +77,7 @@ impl_message_trait!(Login < _SendLogin, _ReceiveLogin > (MessageType::Server(1))
 
 define_message_to_send!(SetWaitPort {
     port: u32,
-    _unknown: u32,
+    obfuscation_type: u32,
     obfuscated_port: u32,
 });
 impl_message_trait!(
@@ -92,7 +92,7 @@ define_message_to_receive!(_ReceiveGetPeerAddress {
     username: String,
     ip: Ipv4Addr,
     port: u32,
-    _unknown: u32,
+    obfuscation_type: u32,
     obfuscated_port: u16,
 });
 
@@ -314,7 +314,7 @@ define_message_to_receive!(_ReceiveConnectToPeer {
     port: u32,
     firewall_token: u32,
     privileged: bool,
-    _unknown: u32,
+    obfuscation_type: u32,
     obfuscated_port: u32,
 });
 
@@ -420,14 +420,58 @@ impl_message_trait!(
     IsntReceived > (MessageType::Server(42))
 );
 
-// TODO:
-// Server Codes 51, 52, 56, 57 all deprecated and sent only.
-// Can be ignored for now, "maybe" added in the future.
-// The following are deprecated but can still be received,
-// should be implemented and handled.
-// 54: Recommendations
-// 56: GlobalRecommendations
-// 57: UserInterests
+// The interest messages (51-57, 110-112, 117, 118) are deprecated,
+// but the server still answers them and other clients still use them.
+#[rustfmt::skip]
+define_message_to_send!(AddThingILike { item: String ,});
+impl_message_trait!(
+    AddThingILike < AddThingILike,
+    IsntReceived > (MessageType::Server(51))
+);
+
+#[rustfmt::skip]
+define_message_to_send!(RemoveThingILike { item: String ,});
+impl_message_trait!(
+    RemoveThingILike < RemoveThingILike,
+    IsntReceived > (MessageType::Server(52))
+);
+
+define_message_to_receive!(Recommendation {
+    item: String,
+    score: i32, // Negative for unrecommendations
+});
+
+define_message_to_send!(_SendRecommendations {});
+define_message_to_receive!(_ReceiveRecommendations {
+    recommendations: Vec<Recommendation>,
+    unrecommendations: Vec<Recommendation>,
+});
+pub struct Recommendations;
+impl_message_trait!(
+    Recommendations < _SendRecommendations,
+    _ReceiveRecommendations > (MessageType::Server(54))
+);
+
+define_message_to_send!(_SendGlobalRecommendations {});
+pub struct GlobalRecommendations;
+impl_message_trait!(
+    GlobalRecommendations < _SendGlobalRecommendations,
+    _ReceiveRecommendations > (MessageType::Server(56))
+);
+
+#[rustfmt::skip]
+define_message_to_send!(_SendUserInterests { username: String ,});
+define_message_to_receive!(_ReceiveUserInterests {
+    username: String,
+    likes: Vec<String>,
+    hates: Vec<String>,
+});
+pub struct UserInterests;
+impl_message_trait!(
+    UserInterests < _SendUserInterests,
+    _ReceiveUserInterests > (MessageType::Server(57))
+);
+
 define_message_to_send!(_SendRoomList {});
 // This message is a mess, I hate it!
 //  ^^ Having Vec<(String, u32)> for owned/non-owned rooms makes more intuitive sense
@@ -498,6 +542,15 @@ impl_message_trait!(
     ParentSpeedRatio > (MessageType::Server(84))
 );
 
+#[rustfmt::skip]
+define_message_to_receive!(AddPrivilegedUser {
+    username: String,
+});
+impl_message_trait!(
+    AddPrivilegedUser < IsntSent,
+    AddPrivilegedUser > (MessageType::Server(91))
+);
+
 define_message_to_send!(_SendCheckPrivileges {});
 define_message_to_receive!(_ReceiveCheckPrivileges {
     time_left_seconds: u32,
@@ -537,7 +590,7 @@ impl_message_trait!(
 
 define_message_to_send!(WishlistSearch {
     token: u32,
-    search_query: u32,
+    search_query: String,
 });
 impl_message_trait!(
     WishlistSearch < WishlistSearch,
@@ -545,17 +598,51 @@ impl_message_trait!(
 );
 
 define_message_to_receive!(WishListInterval {
-    interval: u32, // Seconds?
+    interval: u32, // Seconds
 });
 impl_message_trait!(
     WishListInterval < IsntSent,
     WishListInterval > (MessageType::Server(104))
 );
 
-// TODO: The following are deprecated but can be received.
-// 110: SimilarUsers
-// 111: ItemRecommendations
-// 112: ItemSimilarUsers
+define_message_to_receive!(SimilarUser {
+    username: String,
+    rating: u32,
+});
+
+define_message_to_send!(_SendSimilarUsers {});
+define_message_to_receive!(_ReceiveSimilarUsers {
+    users: Vec<SimilarUser>,
+});
+pub struct SimilarUsers;
+impl_message_trait!(
+    SimilarUsers < _SendSimilarUsers,
+    _ReceiveSimilarUsers > (MessageType::Server(110))
+);
+
+#[rustfmt::skip]
+define_message_to_send!(_SendItemRecommendations { item: String ,});
+define_message_to_receive!(_ReceiveItemRecommendations {
+    item: String,
+    recommendations: Vec<Recommendation>,
+});
+pub struct ItemRecommendations;
+impl_message_trait!(
+    ItemRecommendations < _SendItemRecommendations,
+    _ReceiveItemRecommendations > (MessageType::Server(111))
+);
+
+#[rustfmt::skip]
+define_message_to_send!(_SendItemSimilarUsers { item: String ,});
+define_message_to_receive!(_ReceiveItemSimilarUsers {
+    item: String,
+    usernames: Vec<String>,
+});
+pub struct ItemSimilarUsers;
+impl_message_trait!(
+    ItemSimilarUsers < _SendItemSimilarUsers,
+    _ReceiveItemSimilarUsers > (MessageType::Server(112))
+);
 
 define_message_to_receive!(Ticker {
     username: String,
@@ -598,7 +685,19 @@ impl_message_trait!(
     IsntReceived > (MessageType::Server(116))
 );
 
-// Server Codes 117, 118 are deprecated
+#[rustfmt::skip]
+define_message_to_send!(AddThingIHate { item: String ,});
+impl_message_trait!(
+    AddThingIHate < AddThingIHate,
+    IsntReceived > (MessageType::Server(117))
+);
+
+#[rustfmt::skip]
+define_message_to_send!(RemoveThingIHate { item: String ,});
+impl_message_trait!(
+    RemoveThingIHate < RemoveThingIHate,
+    IsntReceived > (MessageType::Server(118))
+);
 
 define_message_to_send!(RoomSearch {
     room: String,
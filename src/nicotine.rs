@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::read_to_string,
+    io,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+use crate::{config::Config, sql::DiskIndex};
+
+/// The name Nicotine+ gives the download folder when it's shared
+const DOWNLOADS_ALIAS: &str = "Downloaded";
+
+/// The parts of a Nicotine+ config we know how to use
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NicotineImport {
+    pub(crate) server: Option<(String, u16)>,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) download_directory: Option<PathBuf>,
+    /// Alias, path and whether the folder is buddy-only
+    pub(crate) shares: Vec<(String, PathBuf, bool)>,
+    pub(crate) buddies: Vec<String>,
+    pub(crate) banned: Vec<String>,
+}
+
+/// A value as Nicotine+ writes it, which is the Python `repr` of anything that isn't a string.
+/// Tuples are read as lists.
+#[derive(Debug, Clone)]
+enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+    None,
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a single Python literal, returning `None` if there's anything left over
+fn parse_literal(text: &str) -> Option<Value> {
+    let mut chars = text.trim().chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    chars.peek().is_none().then_some(value)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Option<Value> {
+    skip_whitespace(chars);
+    match *chars.peek()? {
+        quote @ ('\'' | '"') => {
+            chars.next();
+            parse_string(chars, quote).map(Value::Str)
+        }
+        open @ ('[' | '(') => {
+            chars.next();
+            let close = if open == '[' { ']' } else { ')' };
+            let mut list = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                if chars.next_if_eq(&close).is_some() {
+                    break Some(Value::List(list));
+                }
+                list.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => (),
+                    c if c == close => break Some(Value::List(list)),
+                    _ => break None,
+                }
+            }
+        }
+        c if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::from(chars.next()?);
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+            number.parse().ok().map(Value::Int)
+        }
+        _ => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "True" => Some(Value::Bool(true)),
+                "False" => Some(Value::Bool(false)),
+                "None" => Some(Value::None),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Reads up to the closing `quote`, the opening one has already been consumed
+fn parse_string(chars: &mut Peekable<Chars>, quote: char) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            c if c == quote => break Some(string),
+            '\\' => match chars.next()? {
+                'n' => string.push('\n'),
+                't' => string.push('\t'),
+                'r' => string.push('\r'),
+                'x' => string.push(parse_codepoint(chars, 2)?),
+                'u' => string.push(parse_codepoint(chars, 4)?),
+                'U' => string.push(parse_codepoint(chars, 8)?),
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+fn parse_codepoint(chars: &mut Peekable<Chars>, digits: usize) -> Option<char> {
+    let hex: String = (0..digits).map_while(|_| chars.next()).collect();
+    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+}
+
+/// An INI file, by section then key. Values are kept as they were written.
+struct Ini(HashMap<String, HashMap<String, String>>);
+
+impl Ini {
+    fn parse(text: &str) -> Self {
+        let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut section = String::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_lowercase();
+            } else if let Some((key, value)) = line.split_once('=') {
+                sections
+                    .entry(section.clone())
+                    .or_default()
+                    .insert(key.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+        Self(sections)
+    }
+
+    fn raw(&self, section: &str, key: &str) -> Option<&str> {
+        self.0.get(section)?.get(key).map(String::as_str)
+    }
+
+    /// Nicotine+ writes strings as they are, without quotes
+    fn string(&self, section: &str, key: &str) -> Option<String> {
+        self.raw(section, key)
+            .filter(|value| !value.is_empty() && *value != "None")
+            .map(String::from)
+    }
+
+    fn literal(&self, section: &str, key: &str) -> Option<Value> {
+        parse_literal(self.raw(section, key)?)
+    }
+
+    fn list(&self, section: &str, key: &str) -> Vec<Value> {
+        match self.literal(section, key) {
+            Some(Value::List(list)) => list,
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Where Nicotine+ keeps its config by default
+pub(crate) fn default_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(|appdata| {
+            Path::new(&appdata)
+                .join("nicotine")
+                .join("config")
+                .join("config")
+        });
+    }
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|config| config.join("nicotine").join("config"))
+}
+
+/// Shared folders are `(alias, path)` tuples, very old configs only have the path
+fn shared_folder(value: &Value) -> Option<(String, PathBuf)> {
+    match value {
+        Value::Str(path) => {
+            let path = PathBuf::from(path);
+            let alias = path.file_name()?.to_string_lossy().to_string();
+            Some((alias, path))
+        }
+        Value::List(list) => Some((
+            list.first()?.as_str()?.to_string(),
+            PathBuf::from(list.get(1)?.as_str()?),
+        )),
+        _ => None,
+    }
+}
+
+fn port(value: &Value) -> Option<u16> {
+    value.as_int()?.try_into().ok()
+}
+
+impl NicotineImport {
+    pub(crate) fn read(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&read_to_string(path)?))
+    }
+
+    fn parse(text: &str) -> Self {
+        let ini = Ini::parse(text);
+
+        let server = ini.literal("server", "server").and_then(|server| {
+            let server = server.as_list()?;
+            Some((server.first()?.as_str()?.to_string(), port(server.get(1)?)?))
+        });
+        let port = ini
+            .literal("server", "portrange")
+            .and_then(|range| port(range.as_list()?.first()?));
+        let download_directory = ini.string("transfers", "downloaddir").map(PathBuf::from);
+
+        let mut shares: Vec<(String, PathBuf, bool)> = Vec::new();
+        for (key, buddy_only) in [
+            ("shared", false),
+            ("buddyshared", true),
+            ("trustedshared", true),
+        ] {
+            for (alias, path) in ini.list("transfers", key).iter().filter_map(shared_folder) {
+                shares.push((alias, path, buddy_only));
+            }
+        }
+        if let (Some(Value::Bool(true)), Some(download_directory)) = (
+            ini.literal("transfers", "sharedownloaddir"),
+            &download_directory,
+        ) {
+            if !shares.iter().any(|(_, path, _)| path == download_directory) {
+                shares.push((
+                    String::from(DOWNLOADS_ALIAS),
+                    download_directory.clone(),
+                    false,
+                ));
+            }
+        }
+
+        // each buddy is a list starting with their name, followed by a note and other settings
+        let buddies = ini
+            .list("server", "userlist")
+            .iter()
+            .filter_map(|user| match user {
+                Value::List(user) => user.first()?.as_str().map(String::from),
+                user => user.as_str().map(String::from),
+            })
+            .collect();
+        let banned = ini
+            .list("server", "banlist")
+            .iter()
+            .filter_map(|user| user.as_str().map(String::from))
+            .collect();
+
+        Self {
+            server,
+            username: ini.string("server", "login"),
+            password: ini.string("server", "passw"),
+            port,
+            download_directory,
+            shares,
+            buddies,
+            banned,
+        }
+    }
+
+    /// Drops shares of folders that are already shared, and renames any whose alias is already taken
+    pub(crate) fn new_shares(&self, index: &DiskIndex) -> Vec<(String, PathBuf, bool)> {
+        let mut taken: Vec<String> = index
+            .root_folders()
+            .iter()
+            .map(|(_, alias, _)| alias.clone())
+            .collect();
+        let mut shares = Vec::new();
+        for (alias, path, buddy_only) in &self.shares {
+            if index
+                .root_folders()
+                .iter()
+                .any(|(shared, _, _)| shared == path)
+            {
+                continue;
+            }
+            let alias = alias.replace('\\', "/");
+            let mut unique_alias = alias.clone();
+            let mut count = 1;
+            while taken.contains(&unique_alias) {
+                count += 1;
+                unique_alias = format!("{alias} ({count})");
+            }
+            taken.push(unique_alias.clone());
+            shares.push((unique_alias, path.clone(), *buddy_only));
+        }
+        shares
+    }
+
+    /// Describes what `apply` would change, one line per change
+    pub(crate) fn describe_changes(&self, config: &Config) -> Vec<String> {
+        let mut changes = Vec::new();
+        if let Some((address, port)) = &self.server {
+            if *address != config.server.address || *port != config.server.port {
+                changes.push(format!(
+                    "Server: {} -> {address}:{port}",
+                    config.server.to_string()
+                ));
+            }
+        }
+        if let Some(username) = &self.username {
+            if *username != config.user.name {
+                changes.push(format!("Username: {:?} -> {username:?}", config.user.name));
+            }
+        }
+        if self
+            .password
+            .as_ref()
+            .is_some_and(|password| *password != config.user.password)
+        {
+            changes.push(String::from("Password will be replaced"));
+        }
+        if let Some(port) = self.port {
+            if port != config.user.port {
+                changes.push(format!("Listening port: {} -> {port}", config.user.port));
+            }
+        }
+        if let Some(directory) = &self.download_directory {
+            if *directory != config.downloads.directory {
+                changes.push(format!(
+                    "Download folder: {:?} -> {directory:?}",
+                    config.downloads.directory
+                ));
+            }
+        }
+        for (alias, path, buddy_only) in self.new_shares(&config.index) {
+            changes.push(format!(
+                "Share {} as {alias:?}{}",
+                path.to_string_lossy(),
+                if buddy_only { " (buddy-only)" } else { "" }
+            ));
+        }
+        let new_buddies = new_names(&self.buddies, &config.users.buddies);
+        if new_buddies > 0 {
+            changes.push(format!("Add {new_buddies} buddies"));
+        }
+        let new_bans = new_names(&self.banned, &config.users.banned);
+        if new_bans > 0 {
+            changes.push(format!("Ban {new_bans} users"));
+        }
+        changes
+    }
+
+    /// Applies everything except the shares, which need indexing.
+    /// Buddies and bans are added to the existing ones.
+    pub(crate) fn apply(&self, config: &mut Config) {
+        if let Some((address, port)) = &self.server {
+            config.server.address = address.clone();
+            config.server.port = *port;
+        }
+        if let Some(username) = &self.username {
+            config.user.name = username.clone();
+        }
+        if let Some(password) = &self.password {
+            config.user.password = password.clone();
+        }
+        if let Some(port) = self.port {
+            config.user.port = port;
+        }
+        if let Some(directory) = &self.download_directory {
+            config.downloads.directory = directory.clone();
+        }
+        for (names, existing) in [
+            (&self.buddies, &mut config.users.buddies),
+            (&self.banned, &mut config.users.banned),
+        ] {
+            for name in names {
+                if !existing.contains(name) {
+                    existing.push(name.clone());
+                }
+            }
+        }
+    }
+}
+
+fn new_names(names: &[String], existing: &[String]) -> usize {
+    names.iter().filter(|name| !existing.contains(name)).count()
+}
@@ -0,0 +1,247 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use rand::random;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+
+/// The only kind of obfuscation there is, sent with our obfuscated port and received with other users'
+pub(crate) const OBFUSCATION_ROTATED: u32 = 1;
+
+/// XORs bytes with a key that's rotated before every 4 bytes
+#[derive(Clone, Copy)]
+struct Cipher {
+    key: u32,
+    /// How far into the current 4 bytes we are
+    position: usize,
+}
+
+impl Cipher {
+    fn new(key: u32) -> Self {
+        Self { key, position: 4 }
+    }
+
+    fn apply(&mut self, byte: u8) -> u8 {
+        if self.position == 4 {
+            self.key = self.key.rotate_right(31);
+            self.position = 0;
+        }
+        let byte = byte ^ self.key.to_le_bytes()[self.position];
+        self.position += 1;
+        byte
+    }
+}
+
+/// Where we are in the messages going one way on an obfuscated connection.
+/// Each message is preceded by a new random key, which isn't counted in its length.
+enum Framing {
+    /// Between messages, with as much of the next key as we have
+    Key(Vec<u8>),
+    /// In a message's length, with as much of it as we have
+    Length(Cipher, Vec<u8>),
+    /// In a message's body, with how many bytes of it are left
+    Body(Cipher, u32),
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing::Key(Vec::new())
+    }
+}
+
+impl Framing {
+    /// Moves past a byte of the message we're in, before it was obfuscated or after it was deobfuscated
+    fn advance(&mut self, byte: u8) {
+        match self {
+            Framing::Key(_) => (),
+            Framing::Length(cipher, length) => {
+                length.push(byte);
+                if length.len() == 4 {
+                    let cipher = *cipher;
+                    *self = match u32::from_le_bytes([length[0], length[1], length[2], length[3]]) {
+                        0 => Framing::default(),
+                        length => Framing::Body(cipher, length),
+                    };
+                }
+            }
+            Framing::Body(_, remaining) => {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    *self = Framing::default();
+                }
+            }
+        }
+    }
+
+    /// Deobfuscates a received byte, unless it's part of a key
+    fn receive(&mut self, byte: u8) -> Option<u8> {
+        let byte = match self {
+            Framing::Key(key) => {
+                key.push(byte);
+                if key.len() == 4 {
+                    let key = u32::from_le_bytes([key[0], key[1], key[2], key[3]]);
+                    *self = Framing::Length(Cipher::new(key), Vec::new());
+                }
+                return None;
+            }
+            Framing::Length(cipher, _) | Framing::Body(cipher, _) => cipher.apply(byte),
+        };
+        self.advance(byte);
+        Some(byte)
+    }
+
+    /// Obfuscates a byte we're sending onto `sent`, after a new key if it starts a message
+    fn send(&mut self, byte: u8, sent: &mut Vec<u8>) {
+        if let Framing::Key(_) = self {
+            let key = random::<u32>();
+            sent.extend(key.to_le_bytes());
+            *self = Framing::Length(Cipher::new(key), Vec::new());
+        }
+        if let Framing::Length(cipher, _) | Framing::Body(cipher, _) = self {
+            sent.push(cipher.apply(byte));
+        }
+        self.advance(byte);
+    }
+}
+
+#[derive(Default)]
+struct Obfuscation {
+    receiving: Framing,
+    sending: Framing,
+    /// Obfuscated bytes that haven't been written to the connection yet
+    unsent: Vec<u8>,
+    /// How many bytes `unsent` was obfuscated from
+    accepted: usize,
+}
+
+/// A connection to a peer, which is obfuscated if it was made to or from an obfuscated port.
+/// Messages are read from and written to it as they would be to a plain connection.
+pub(crate) struct PeerStream {
+    stream: TcpStream,
+    obfuscation: Option<Obfuscation>,
+}
+
+impl PeerStream {
+    pub(crate) fn plain(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            obfuscation: None,
+        }
+    }
+
+    pub(crate) fn obfuscated(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            obfuscation: Some(Obfuscation::default()),
+        }
+    }
+
+    /// Stops obfuscating, as file and distributed connections only obfuscate their PeerInit.
+    /// Messages are read exactly, so nothing after the PeerInit has been read yet.
+    pub(crate) fn into_plain(self) -> Self {
+        Self::plain(self.stream)
+    }
+}
+
+/// Writes everything in `unsent` to `stream`
+fn poll_write_unsent(
+    stream: &mut TcpStream,
+    unsent: &mut Vec<u8>,
+    cx: &mut Context<'_>,
+) -> Poll<io::Result<()>> {
+    while !unsent.is_empty() {
+        let written = ready!(Pin::new(&mut *stream).poll_write(cx, unsent))?;
+        if written == 0 {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        unsent.drain(..written);
+    }
+    Poll::Ready(Ok(()))
+}
+
+impl AsyncRead for PeerStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let Some(obfuscation) = &mut this.obfuscation else {
+            return Pin::new(&mut this.stream).poll_read(cx, buf);
+        };
+        // keys aren't passed on, so keep reading until there's something that is or the connection ends
+        loop {
+            let mut received = vec![0; buf.remaining()];
+            let mut received_buf = ReadBuf::new(&mut received);
+            ready!(Pin::new(&mut this.stream).poll_read(cx, &mut received_buf))?;
+            if received_buf.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let deobfuscated: Vec<u8> = received_buf
+                .filled()
+                .iter()
+                .filter_map(|byte| obfuscation.receiving.receive(*byte))
+                .collect();
+            if !deobfuscated.is_empty() {
+                buf.put_slice(&deobfuscated);
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl AsyncWrite for PeerStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(obfuscation) = &mut this.obfuscation else {
+            return Pin::new(&mut this.stream).poll_write(cx, buf);
+        };
+        // the keys make what's written longer than `buf`, so it's only reported as written once all of it is.
+        // until then, we're called again with the same `buf` (as write_all does), which mustn't be obfuscated twice
+        if obfuscation.unsent.is_empty() {
+            for byte in buf {
+                obfuscation.sending.send(*byte, &mut obfuscation.unsent);
+            }
+            obfuscation.accepted = buf.len();
+        }
+        ready!(poll_write_unsent(
+            &mut this.stream,
+            &mut obfuscation.unsent,
+            cx
+        ))?;
+        Poll::Ready(Ok(obfuscation.accepted))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(obfuscation) = &mut this.obfuscation {
+            ready!(poll_write_unsent(
+                &mut this.stream,
+                &mut obfuscation.unsent,
+                cx
+            ))?;
+        }
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(obfuscation) = &mut this.obfuscation {
+            ready!(poll_write_unsent(
+                &mut this.stream,
+                &mut obfuscation.unsent,
+                cx
+            ))?;
+        }
+        Pin::new(&mut this.stream).poll_shutdown(cx)
+    }
+}
@@ -33,7 +33,7 @@ impl AiffParser {
         Ok((chunk_id, chunk_size))
     }
 
-    fn read_ieee754_extended(file: &mut File) -> Result<f64> {
+    pub(crate) fn read_ieee754_extended(file: &mut File) -> Result<f64> {
         let mut buf = [0u8; 10];
         file.read_exact(&mut buf)?;
 
@@ -287,7 +287,7 @@ impl AudioMetadata for AiffParser {
 
     fn is_vbr(&self) -> bool {
         match self.bitrate {
-            Bitrate::Variable(_) => true,
+            Bitrate::Variable(_) | Bitrate::Lossless(_) => true,
             Bitrate::Constant(_) => false,
         }
     }
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use super::{skip_id3v2, AudioMetadata, Bitrate};
+
+/// Monkey's Audio (.ape)
+#[derive(Debug)]
+pub(crate) struct ApeParser {
+    sample_rate: u32,
+    bits_per_sample: u16,
+    total_samples: u64,
+    audio_size: u64,
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn le_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn le_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+impl ApeParser {
+    /// Version 3.98 and later start with a descriptor, followed by the header
+    fn parse_new_header(file: &mut File, start: u64) -> Result<(u32, u16, u64, u64)> {
+        // "MAC " (4), version (2), padding (2), then the descriptor's fields
+        let mut descriptor = [0u8; 52];
+        file.read_exact(&mut descriptor)?;
+        let descriptor_size = le_u32(&descriptor, 8) as u64;
+        let header_size = le_u32(&descriptor, 12);
+        let frame_data_size =
+            le_u32(&descriptor, 24) as u64 | (le_u32(&descriptor, 28) as u64) << 32;
+        if header_size < 24 {
+            return Err(invalid("APE header is too short"));
+        }
+
+        file.seek(SeekFrom::Start(start + descriptor_size))?;
+        let mut header = [0u8; 24];
+        file.read_exact(&mut header)?;
+        let blocks_per_frame = le_u32(&header, 4) as u64;
+        let final_frame_blocks = le_u32(&header, 8) as u64;
+        let total_frames = le_u32(&header, 12) as u64;
+        let bits_per_sample = le_u16(&header, 16);
+        let sample_rate = le_u32(&header, 20);
+
+        let total_samples = match total_frames {
+            0 => 0,
+            frames => (frames - 1) * blocks_per_frame + final_frame_blocks,
+        };
+        Ok((sample_rate, bits_per_sample, total_samples, frame_data_size))
+    }
+
+    /// Versions before 3.98 only have a header
+    fn parse_old_header(file: &mut File, version: u16) -> Result<(u32, u16, u64)> {
+        // "MAC " (4), version (2), then the header's fields
+        let mut header = [0u8; 32];
+        file.read_exact(&mut header)?;
+        let compression_level = le_u16(&header, 6);
+        let format_flags = le_u16(&header, 8);
+        let sample_rate = le_u32(&header, 12);
+        let total_frames = le_u32(&header, 24) as u64;
+        let final_frame_blocks = le_u32(&header, 28) as u64;
+
+        let bits_per_sample = if format_flags & 0x1 != 0 {
+            8
+        } else if format_flags & 0x8 != 0 {
+            24
+        } else {
+            16
+        };
+        let blocks_per_frame = if version >= 3950 {
+            73728 * 4
+        } else if version >= 3900 || (version >= 3800 && compression_level == 4000) {
+            73728
+        } else {
+            9216
+        };
+
+        let total_samples = match total_frames {
+            0 => 0,
+            frames => (frames - 1) * blocks_per_frame + final_frame_blocks,
+        };
+        Ok((sample_rate, bits_per_sample, total_samples))
+    }
+}
+
+impl AudioMetadata for ApeParser {
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        skip_id3v2(&mut file)?;
+        let start = file.stream_position()?;
+
+        let mut magic = [0u8; 6];
+        file.read_exact(&mut magic)?;
+        if &magic[0..4] != b"MAC " {
+            return Err(invalid("Not a Monkey's Audio file"));
+        }
+        let version = le_u16(&magic, 4);
+        file.seek(SeekFrom::Start(start))?;
+
+        let (sample_rate, bits_per_sample, total_samples, audio_size) = if version >= 3980 {
+            Self::parse_new_header(&mut file, start)?
+        } else {
+            let (sample_rate, bits_per_sample, total_samples) =
+                Self::parse_old_header(&mut file, version)?;
+            // there's no record of how much of the file is audio, so assume all of it
+            (
+                sample_rate,
+                bits_per_sample,
+                total_samples,
+                file_size - start,
+            )
+        };
+
+        if sample_rate == 0 {
+            return Err(invalid("Invalid sample rate"));
+        }
+
+        Ok(Self {
+            sample_rate,
+            bits_per_sample,
+            total_samples,
+            audio_size,
+        })
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        let duration = self.duration();
+        if duration > 0.0 {
+            Bitrate::Lossless(((self.audio_size as f64 * 8.0) / (duration * 1000.0)) as u16)
+        } else {
+            Bitrate::Lossless(0)
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.total_samples as f64 / self.sample_rate as f64
+    }
+
+    fn is_vbr(&self) -> bool {
+        true // compressed frames vary in size with the content
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> Option<u16> {
+        Some(self.bits_per_sample)
+    }
+}
@@ -0,0 +1,178 @@
+use std::fs::File;
+use std::io::{Read, Result};
+use std::path::Path;
+
+use crate::utils::latin1_to_string;
+
+/// Sheets are tiny, anything bigger than this isn't one
+const MAX_CUE_SIZE: u64 = 1024 * 1024;
+/// INDEX times are mm:ss:ff, with 75 frames a second
+const FRAMES_PER_SECOND: f64 = 75.0;
+
+/// A CUE sheet, describing the tracks in one or more audio files (usually a single image of a whole album)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CueSheet {
+    pub(crate) performer: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) files: Vec<CueFile>,
+}
+
+/// An audio file referenced by a `FILE` line, along with the tracks inside it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CueFile {
+    pub(crate) name: String,
+    pub(crate) tracks: Vec<CueTrack>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CueTrack {
+    pub(crate) number: u32,
+    pub(crate) title: Option<String>,
+    pub(crate) performer: Option<String>,
+    /// Seconds from the start of the file (`INDEX 01`)
+    pub(crate) start: f64,
+}
+
+impl CueFile {
+    /// Each track's duration in seconds, worked out from where the next one starts.
+    /// The last track runs to the end of the file, so it needs `file_duration`.
+    pub(crate) fn track_durations(&self, file_duration: Option<f64>) -> Vec<Option<f64>> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| {
+                let end = match self.tracks.get(i + 1) {
+                    Some(next) => Some(next.start),
+                    None => file_duration,
+                };
+                end.map(|end| end - track.start)
+                    .filter(|duration| *duration > 0.0)
+            })
+            .collect()
+    }
+}
+
+impl CueTrack {
+    /// The text worth searching by, the title and performer
+    pub(crate) fn values(&self) -> impl Iterator<Item = &str> {
+        [&self.title, &self.performer]
+            .into_iter()
+            .filter_map(|value| value.as_deref())
+    }
+}
+
+pub(crate) fn read_cue(path: &Path) -> Result<CueSheet> {
+    let mut data = Vec::new();
+    File::open(path)?
+        .take(MAX_CUE_SIZE)
+        .read_to_end(&mut data)?;
+    Ok(parse_cue(&decode_text(&data)))
+}
+
+/// Sheets come in all sorts of encodings, older rippers write them in the system code page
+fn decode_text(data: &[u8]) -> String {
+    let utf16 = |data: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        String::from_utf16_lossy(
+            &data
+                .chunks_exact(2)
+                .map(|pair| from_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        )
+    };
+    match data {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).to_string(),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => match std::str::from_utf8(data) {
+            Ok(text) => text.to_string(),
+            Err(_) => latin1_to_string(data),
+        },
+    }
+}
+
+/// Splits the arguments of a command, keeping quoted strings together
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut rest = arguments.trim();
+    while !rest.is_empty() {
+        let (argument, remaining) = match rest.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((argument, remaining)) => (argument, remaining),
+                // an unterminated quote runs to the end of the line
+                None => (quoted, ""),
+            },
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        split.push(argument.to_string());
+        rest = remaining.trim_start();
+    }
+    split
+}
+
+/// Parses an `mm:ss:ff` time into seconds
+fn parse_time(time: &str) -> Option<f64> {
+    let mut parts = time.split(':').map(|part| part.parse::<u32>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some((minutes * 60 + seconds) as f64 + frames as f64 / FRAMES_PER_SECOND)
+}
+
+fn parse_cue(text: &str) -> CueSheet {
+    let mut sheet = CueSheet::default();
+
+    for line in text.lines() {
+        let line = line.trim();
+        let (command, arguments) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arguments = split_arguments(arguments);
+        let first = arguments.first().cloned().filter(|first| !first.is_empty());
+        let file = sheet.files.last_mut();
+        let track = file.and_then(|file| file.tracks.last_mut());
+
+        match (command.to_ascii_uppercase().as_str(), track) {
+            ("FILE", _) => {
+                if let Some(name) = first {
+                    sheet.files.push(CueFile {
+                        name,
+                        tracks: Vec::new(),
+                    });
+                }
+            }
+            ("TRACK", _) => {
+                // tracks before a FILE line are invalid
+                if let (Some(file), Some(number)) = (
+                    sheet.files.last_mut(),
+                    first.and_then(|number| number.parse().ok()),
+                ) {
+                    file.tracks.push(CueTrack {
+                        number,
+                        ..Default::default()
+                    });
+                }
+            }
+            ("TITLE", Some(track)) => track.title = first,
+            ("PERFORMER", Some(track)) => track.performer = first,
+            ("TITLE", None) => sheet.title = first,
+            ("PERFORMER", None) => sheet.performer = first,
+            ("INDEX", Some(track)) => {
+                if let (Some("01"), Some(start)) = (
+                    arguments.first().map(String::as_str),
+                    arguments.get(1).and_then(|time| parse_time(time)),
+                ) {
+                    track.start = start;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // tracks without their own performer are by the album's performer
+    for track in sheet
+        .files
+        .iter_mut()
+        .flat_map(|file| file.tracks.iter_mut())
+    {
+        if track.performer.is_none() {
+            track.performer = sheet.performer.clone();
+        }
+    }
+    sheet
+}
@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use super::{AudioMetadata, Bitrate};
+
+/// How many chunks to look through before giving up
+const MAX_CHUNKS: usize = 64;
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Uncompressed DSD has a fixed bitrate of one bit per sample per channel
+fn dsd_bitrate(sample_rate: u32, channels: u32) -> u16 {
+    (sample_rate as u64 * channels as u64 / 1000).min(u16::MAX as u64) as u16
+}
+
+/// DSD Stream File (.dsf)
+#[derive(Debug)]
+pub(crate) struct DsfParser {
+    sample_rate: u32,
+    channels: u32,
+    sample_count: u64,
+}
+
+impl AudioMetadata for DsfParser {
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+
+        // "DSD " (4), chunk size (8), total file size (8), metadata pointer (8)
+        let mut header = [0u8; 28];
+        file.read_exact(&mut header)?;
+        if &header[0..4] != b"DSD " {
+            return Err(invalid("Not a DSF file"));
+        }
+        let header_size = u64::from_le_bytes(header[4..12].try_into().unwrap());
+        file.seek(SeekFrom::Start(header_size))?;
+
+        // "fmt " (4), chunk size (8), format version (4), format id (4), channel type (4),
+        // channel count (4), sample rate (4), bits per sample (4), sample count (8)...
+        let mut fmt = [0u8; 48];
+        file.read_exact(&mut fmt)?;
+        if &fmt[0..4] != b"fmt " {
+            return Err(invalid("Missing fmt chunk"));
+        }
+        let channels = u32::from_le_bytes(fmt[24..28].try_into().unwrap());
+        let sample_rate = u32::from_le_bytes(fmt[28..32].try_into().unwrap());
+        let sample_count = u64::from_le_bytes(fmt[36..44].try_into().unwrap());
+
+        if sample_rate == 0 {
+            return Err(invalid("Invalid sample rate"));
+        }
+
+        Ok(Self {
+            sample_rate,
+            channels,
+            sample_count,
+        })
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        Bitrate::Constant(dsd_bitrate(self.sample_rate, self.channels))
+    }
+
+    fn duration(&self) -> f64 {
+        self.sample_count as f64 / self.sample_rate as f64
+    }
+
+    fn is_vbr(&self) -> bool {
+        false
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> Option<u16> {
+        // the bits per sample field only says which order the bits are packed in
+        Some(1)
+    }
+}
+
+/// DSDIFF (.dff), either uncompressed or DST compressed
+#[derive(Debug)]
+pub(crate) struct DffParser {
+    sample_rate: u32,
+    channels: u32,
+    duration: f64,
+    /// DST compressed audio has to be averaged over the size of the sound data
+    dst_size: Option<u64>,
+}
+
+impl DffParser {
+    /// Returns the chunk's id and size, the size doesn't include the header
+    fn read_chunk_header(file: &mut File) -> Result<([u8; 4], u64)> {
+        let mut header = [0u8; 12];
+        file.read_exact(&mut header)?;
+        Ok((
+            [header[0], header[1], header[2], header[3]],
+            u64::from_be_bytes(header[4..12].try_into().unwrap()),
+        ))
+    }
+
+    /// Where a chunk of `size` starting at the current position ends, including its pad byte
+    fn chunk_end(file: &mut File, size: u64) -> Result<u64> {
+        file.stream_position()?
+            .checked_add(size)
+            .and_then(|end| end.checked_add(size & 1))
+            .ok_or_else(|| invalid("Invalid chunk size"))
+    }
+
+    /// Returns (sample rate, channels) from the sound property chunk
+    fn parse_prop(file: &mut File, size: u64) -> Result<(u32, u32)> {
+        let end = Self::chunk_end(file, size)?;
+        let mut prop_type = [0u8; 4];
+        file.read_exact(&mut prop_type)?;
+        if &prop_type != b"SND " {
+            return Err(invalid("Missing sound properties"));
+        }
+
+        let (mut sample_rate, mut channels) = (0, 0);
+        while file.stream_position()? + 12 <= end {
+            let (id, size) = Self::read_chunk_header(file)?;
+            let next = Self::chunk_end(file, size)?;
+            match &id {
+                b"FS  " => {
+                    let mut bytes = [0u8; 4];
+                    file.read_exact(&mut bytes)?;
+                    sample_rate = u32::from_be_bytes(bytes);
+                }
+                b"CHNL" => {
+                    let mut bytes = [0u8; 2];
+                    file.read_exact(&mut bytes)?;
+                    channels = u16::from_be_bytes(bytes) as u32;
+                }
+                _ => (),
+            }
+            file.seek(SeekFrom::Start(next))?;
+        }
+        Ok((sample_rate, channels))
+    }
+
+    /// Returns the duration from the DST frame information
+    fn parse_dst(file: &mut File, size: u64) -> Result<f64> {
+        let end = Self::chunk_end(file, size)?;
+        while file.stream_position()? + 12 <= end {
+            let (id, size) = Self::read_chunk_header(file)?;
+            let next = Self::chunk_end(file, size)?;
+            if &id == b"FRTE" {
+                // frame count (4), frame rate (2)
+                let mut bytes = [0u8; 6];
+                file.read_exact(&mut bytes)?;
+                let frames = u32::from_be_bytes(bytes[0..4].try_into().unwrap());
+                let frame_rate = u16::from_be_bytes([bytes[4], bytes[5]]);
+                if frame_rate == 0 {
+                    return Err(invalid("Invalid DST frame rate"));
+                }
+                return Ok(frames as f64 / frame_rate as f64);
+            }
+            file.seek(SeekFrom::Start(next))?;
+        }
+        Err(invalid("Missing DST frame information"))
+    }
+}
+
+impl AudioMetadata for DffParser {
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+
+        let (id, _) = Self::read_chunk_header(&mut file)?;
+        let mut form_type = [0u8; 4];
+        file.read_exact(&mut form_type)?;
+        if &id != b"FRM8" || &form_type != b"DSD " {
+            return Err(invalid("Not a DSDIFF file"));
+        }
+
+        let mut properties = None;
+        for _ in 0..MAX_CHUNKS {
+            if file.stream_position()? + 12 > file_size {
+                break;
+            }
+            let (id, size) = Self::read_chunk_header(&mut file)?;
+            let next = Self::chunk_end(&mut file, size)?;
+            match &id {
+                b"PROP" => properties = Some(Self::parse_prop(&mut file, size)?),
+                b"DSD " | b"DST " => {
+                    let (sample_rate, channels) =
+                        properties.ok_or_else(|| invalid("Sound data before properties"))?;
+                    if sample_rate == 0 || channels == 0 {
+                        return Err(invalid("Invalid sound properties"));
+                    }
+                    return Ok(if &id == b"DSD " {
+                        Self {
+                            sample_rate,
+                            channels,
+                            // 8 samples per byte, interleaved by channel
+                            duration: size as f64 * 8.0 / (sample_rate as f64 * channels as f64),
+                            dst_size: None,
+                        }
+                    } else {
+                        Self {
+                            sample_rate,
+                            channels,
+                            duration: Self::parse_dst(&mut file, size)?,
+                            dst_size: Some(size),
+                        }
+                    });
+                }
+                _ => (),
+            }
+            file.seek(SeekFrom::Start(next))?;
+        }
+        Err(invalid("Missing sound data"))
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        match self.dst_size {
+            Some(size) if self.duration > 0.0 => {
+                Bitrate::Lossless(((size as f64 * 8.0) / (self.duration * 1000.0)) as u16)
+            }
+            Some(_) => Bitrate::Lossless(0),
+            None => Bitrate::Constant(dsd_bitrate(self.sample_rate, self.channels)),
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    fn is_vbr(&self) -> bool {
+        self.dst_size.is_some()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> Option<u16> {
+        Some(1)
+    }
+}
@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::{Read, Result, Seek, SeekFrom};
 use std::path::Path;
 
+use super::tags::vorbis_comment_vendor;
 use super::{AudioMetadata, Bitrate};
 
 pub(crate) struct FlacParser {
@@ -13,6 +14,7 @@ pub(crate) struct FlacParser {
     max_frame_size: u32,
     audio_start_pos: u64,
     file_size: u64,
+    encoder: Option<String>,
 }
 
 impl AudioMetadata for FlacParser {
@@ -28,6 +30,7 @@ impl AudioMetadata for FlacParser {
 
         let mut audio_start_pos = 4; // Position after signature
         let mut streaminfo = None;
+        let mut encoder = None;
 
         // Process metadata blocks
         loop {
@@ -76,6 +79,13 @@ impl AudioMetadata for FlacParser {
                 ));
             }
 
+            if block_type == 4 {
+                // VORBIS_COMMENT, the vendor string names the encoder
+                let mut data = vec![0; length as usize];
+                file.read_exact(&mut data)?;
+                encoder = vorbis_comment_vendor(&data);
+            }
+
             if is_last {
                 break;
             }
@@ -95,13 +105,14 @@ impl AudioMetadata for FlacParser {
             max_frame_size,
             audio_start_pos,
             file_size,
+            encoder,
         })
     }
 
     fn bitrate(&self) -> Bitrate {
         let duration = self.duration();
         let audio_size = self.file_size - self.audio_start_pos;
-        Bitrate::Variable(((audio_size as f64 * 8.0) / (duration * 1000.0)) as u16)
+        Bitrate::Lossless(((audio_size as f64 * 8.0) / (duration * 1000.0)) as u16)
     }
 
     fn duration(&self) -> f64 {
@@ -119,4 +130,8 @@ impl AudioMetadata for FlacParser {
     fn bit_depth(&self) -> Option<u16> {
         Some(self.bits_per_sample as u16)
     }
+
+    fn encoder(&self) -> Option<String> {
+        self.encoder.clone()
+    }
 }
@@ -1,19 +1,47 @@
-use std::{io::Result, ops::Deref, path::Path};
+use std::{
+    fs::File,
+    io::{Read, Result, Seek, SeekFrom},
+    ops::Deref,
+    path::Path,
+};
 
 pub(crate) use self::{
-    aiff::AiffParser, flac::FlacParser, mp3::MpParser, ogg::OggParser, wav::WavParser,
+    aiff::AiffParser,
+    ape::ApeParser,
+    cue::read_cue,
+    dsd::{DffParser, DsfParser},
+    flac::FlacParser,
+    mp3::MpParser,
+    mp4::Mp4Parser,
+    musepack::MusepackParser,
+    ogg::OggParser,
+    tags::read_tags,
+    verify::verify_download,
+    wav::WavParser,
+    wavpack::WavPackParser,
 };
 
 pub(crate) mod aiff;
+pub(crate) mod ape;
+pub(crate) mod cue;
+pub(crate) mod dsd;
 pub(crate) mod flac;
 pub(crate) mod mp3;
+pub(crate) mod mp4;
+pub(crate) mod musepack;
 pub(crate) mod ogg;
+pub(crate) mod tags;
+pub(crate) mod verify;
 pub(crate) mod wav;
+pub(crate) mod wavpack;
 
+/// All variants hold kbps.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum Bitrate {
     Constant(u16),
     Variable(u16),
+    /// Losslessly compressed audio, the bitrate is an average that depends on the content
+    Lossless(u16),
 }
 
 impl Deref for Bitrate {
@@ -23,6 +51,7 @@ impl Deref for Bitrate {
         match self {
             Bitrate::Constant(u) => u,
             Bitrate::Variable(u) => u,
+            Bitrate::Lossless(u) => u,
         }
     }
 }
@@ -36,10 +65,79 @@ pub(crate) trait AudioMetadata: Send {
     fn is_vbr(&self) -> bool;
     fn sample_rate(&self) -> u32;
     fn bit_depth(&self) -> Option<u16>;
+    /// What the file says it was encoded with, if anything
+    fn encoder(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Encoders we know, by the start of the string they leave in files, and what to call them.
+/// The ids are sent in the encoder file attribute, so existing ones mustn't change.
+const ENCODERS: [(&str, u32, &str); 10] = [
+    ("LAME", 1, "LAME"),
+    ("reference libFLAC", 2, "libFLAC"),
+    ("Xiph.Org libVorbis", 3, "libVorbis"),
+    ("libopus", 4, "libopus"),
+    ("Lavf", 5, "FFmpeg"),
+    ("Lavc", 5, "FFmpeg"),
+    ("AO; aoTuV", 6, "aoTuV"),
+    ("Encoded with Speex", 7, "Speex"),
+    ("GOGO", 8, "GOGO"),
+    ("Flake", 9, "Flake"),
+];
+
+/// The id of the encoder that wrote `encoder`, for the encoder file attribute
+pub(crate) fn encoder_id(encoder: &str) -> Option<u32> {
+    ENCODERS
+        .iter()
+        .find(|(prefix, _, _)| encoder.starts_with(prefix))
+        .map(|(_, id, _)| *id)
+}
+
+/// The name of the encoder with the given encoder file attribute id
+pub(crate) fn encoder_name(id: u32) -> Option<&'static str> {
+    ENCODERS
+        .iter()
+        .find(|(_, encoder_id, _)| *encoder_id == id)
+        .map(|(_, _, name)| *name)
+}
+
+/// Skips an ID3v2 tag if the file starts with one, otherwise leaves the file where it was
+pub(crate) fn skip_id3v2(file: &mut File) -> Result<()> {
+    let start = file.stream_position()?;
+    let mut header = [0u8; 10];
+    if file.read_exact(&mut header).is_ok() && &header[0..3] == b"ID3" {
+        // ID3v2 uses synchsafe integers (7 bits per byte)
+        let size = ((header[6] & 0x7F) as u64) << 21
+            | ((header[7] & 0x7F) as u64) << 14
+            | ((header[8] & 0x7F) as u64) << 7
+            | (header[9] & 0x7F) as u64;
+        // a footer is present if flag bit 4 is set
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        file.seek(SeekFrom::Current((size + footer) as i64))?;
+    } else {
+        file.seek(SeekFrom::Start(start))?;
+    }
+    Ok(())
+}
+
+/// The extensions `parse` has a parser for
+const PARSED_EXTENSIONS: &[&str] = &[
+    "mp3", "mp2", "mp1", "flac", "wav", "opus", "ogg", "oga", "aiff", "aifc", "aif", "m4a", "m4b",
+    "mp4", "ape", "wv", "mpc", "mp+", "mpp", "dsf", "dff",
+];
+
+/// Whether files with this extension can be parsed, so should have metadata
+pub(crate) fn can_parse(extension: &str) -> bool {
+    PARSED_EXTENSIONS
+        .iter()
+        .any(|parsed| parsed.eq_ignore_ascii_case(extension))
 }
 
 pub(crate) fn parse(path: &Path) -> Option<Result<Box<dyn AudioMetadata>>> {
-    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase())?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())?;
     Some(match extension.as_str() {
         "mp3" | "mp2" | "mp1" => {
             MpParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>)
@@ -54,6 +152,18 @@ pub(crate) fn parse(path: &Path) -> Option<Result<Box<dyn AudioMetadata>>> {
         "aiff" | "aifc" | "aif" => {
             AiffParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>)
         }
+        "m4a" | "m4b" | "mp4" => {
+            Mp4Parser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>)
+        }
+        "ape" => ApeParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>),
+        "wv" => {
+            WavPackParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>)
+        }
+        "mpc" | "mp+" | "mpp" => {
+            MusepackParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>)
+        }
+        "dsf" => DsfParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>),
+        "dff" => DffParser::new(path).map(|metadata| Box::new(metadata) as Box<dyn AudioMetadata>),
         _ => return None,
     })
 }
@@ -2,13 +2,14 @@ use std::fs::File;
 use std::io::{Read, Result, Seek, SeekFrom};
 use std::path::Path;
 
-use super::{AudioMetadata, Bitrate};
+use super::{skip_id3v2, AudioMetadata, Bitrate};
 
 pub(crate) struct MpParser {
     bitrate: Bitrate,
     duration: f64,
     vbr: bool,
     sample_rate: u32,
+    encoder: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -36,7 +37,7 @@ impl MpegLayer {
 
 #[derive(Clone, Copy, Debug)]
 struct FrameHeader {
-    _version: MpegVersion,
+    version: MpegVersion,
     layer: MpegLayer,
     bitrate: u16, // Store as kbps, 0 means free bitrate
     sample_rate: u32,
@@ -57,6 +58,10 @@ impl FrameHeader {
                 // Layer I: (12 * bitrate / sample_rate + padding) * 4
                 (12 * bitrate_bps / self.sample_rate + self.padding_bit as u32) * 4
             }
+            MpegLayer::Layer3 if !matches!(self.version, MpegVersion::V1) => {
+                // Layer III in MPEG 2 and 2.5 has half as many samples per frame
+                72 * bitrate_bps / self.sample_rate + self.padding_bit as u32
+            }
             _ => {
                 // Layer II & III: 144 * bitrate / sample_rate + padding
                 144 * bitrate_bps / self.sample_rate + self.padding_bit as u32
@@ -71,6 +76,48 @@ impl FrameHeader {
 struct VbrHeader {
     frames: Option<u32>,
     bytes: Option<u32>,
+    /// From the LAME tag that follows the Xing/Info header
+    encoder: Option<String>,
+}
+
+/// Describes the encoder version and settings in a LAME tag, e.g. "LAME3.100 -V0"
+fn describe_lame_tag(tag: &[u8; 36], quality: Option<u32>) -> Option<String> {
+    let version = String::from_utf8_lossy(&tag[0..9]);
+    let version = version.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_graphic()) {
+        return None;
+    }
+    // other encoders (e.g. FFmpeg) write the same tag, but don't fill in the settings
+    if !version.starts_with("LAME") {
+        return Some(version.to_string());
+    }
+
+    let vbr_method = tag[9] & 0x0F;
+    let bitrate = tag[20];
+    let preset = u16::from_be_bytes([tag[26], tag[27]]) & 0x07FF;
+    let settings = match preset {
+        410..=500 if preset.is_multiple_of(10) => Some(format!("-V{}", (500 - preset) / 10)),
+        1001 => Some(String::from("--preset standard")),
+        1002 => Some(String::from("--preset extreme")),
+        1003 => Some(String::from("--preset insane")),
+        1004 => Some(String::from("--preset fast standard")),
+        1005 => Some(String::from("--preset fast extreme")),
+        1006 => Some(String::from("--preset medium")),
+        1007 => Some(String::from("--preset fast medium")),
+        _ => match vbr_method {
+            // the bitrate is capped at 255, so only trust it below that
+            1 | 8 if bitrate > 0 && bitrate < 255 => Some(format!("-b {bitrate}")),
+            2 | 9 if bitrate > 0 && bitrate < 255 => Some(format!("--abr {bitrate}")),
+            // the Xing quality is 100 - 10 * V - q
+            3..=6 => quality.map(|quality| format!("-V{}", (100 - quality.min(100)) / 10)),
+            _ => None,
+        },
+    };
+
+    Some(match settings {
+        Some(settings) => format!("{version} {settings}"),
+        None => version.to_string(),
+    })
 }
 
 impl VbrHeader {
@@ -118,7 +165,13 @@ impl VbrHeader {
                     None
                 };
 
-                return Ok(Some(VbrHeader { frames, bytes }));
+                let encoder = Self::parse_lame_tag(file, flags)?;
+
+                return Ok(Some(VbrHeader {
+                    frames,
+                    bytes,
+                    encoder,
+                }));
             }
 
             // Reset position for next attempt
@@ -127,6 +180,33 @@ impl VbrHeader {
 
         Ok(None)
     }
+
+    /// Reads the LAME tag following the rest of the Xing/Info header, leaving the file where it was
+    fn parse_lame_tag(file: &mut File, flags: u32) -> Result<Option<String>> {
+        let position = file.stream_position()?;
+
+        // Skip the TOC if present
+        if flags & 0x04 != 0 {
+            file.seek(SeekFrom::Current(100))?;
+        }
+        // Read quality if present
+        let quality = if flags & 0x08 != 0 {
+            let mut buf = [0u8; 4];
+            file.read_exact(&mut buf)
+                .ok()
+                .map(|_| u32::from_be_bytes(buf))
+        } else {
+            None
+        };
+        let mut tag = [0u8; 36];
+        let encoder = match file.read_exact(&mut tag) {
+            Ok(()) => describe_lame_tag(&tag, quality),
+            Err(_) => None,
+        };
+
+        file.seek(SeekFrom::Start(position))?;
+        Ok(encoder)
+    }
 }
 
 impl MpParser {
@@ -216,6 +296,63 @@ impl MpParser {
         }
     }
 
+    /// Walks every frame in the file, failing if the frame sync is lost part way through
+    /// or the last frame is cut short. Tags after the last frame are fine.
+    pub(crate) fn check_frames<P: AsRef<Path>>(path: P) -> Result<()> {
+        let mut file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        skip_id3v2(&mut file)?;
+        if !Self::resync_to_frame(&mut file)? {
+            return Err(std::io::Error::other("No MPEG frames found"));
+        }
+
+        let mut frames = 0u64;
+        loop {
+            let position = file.stream_position()?;
+            let mut buffer = [0u8; 4];
+            if file.read_exact(&mut buffer).is_err() {
+                return if position == file_size {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::other(format!(
+                        "Frame {frames} is cut short, the file looks truncated"
+                    )))
+                };
+            }
+
+            match Self::read_frame_header(buffer) {
+                Ok(header) => match header.frame_length() {
+                    // free bitrate frames can't be walked without searching for every sync
+                    None => return Ok(()),
+                    Some(length) => {
+                        let next = position + length as u64;
+                        if next > file_size {
+                            return Err(std::io::Error::other(format!(
+                                "Frame {frames} is cut short, the file looks truncated"
+                            )));
+                        }
+                        file.seek(SeekFrom::Start(next))?;
+                        frames += 1;
+                    }
+                },
+                // ID3v1, APEv2, Lyrics3 and appended ID3v2 tags
+                Err(_)
+                    if buffer.starts_with(b"TAG")
+                        || buffer.starts_with(b"ID3")
+                        || &buffer == b"APET"
+                        || &buffer == b"LYRI" =>
+                {
+                    return Ok(())
+                }
+                Err(_) => {
+                    return Err(std::io::Error::other(format!(
+                        "Lost frame sync after {frames} frames (byte {position})"
+                    )))
+                }
+            }
+        }
+    }
+
     fn read_frame_header(buffer: [u8; 4]) -> Result<FrameHeader> {
         // Check frame sync (11 bits of 1)
         let frame_sync = (buffer[0] as u16) << 3 | (buffer[1] as u16) >> 5;
@@ -277,7 +414,7 @@ impl MpParser {
         let padding_bit = (buffer[2] >> 1) & 1 == 1; // Fixed: should be == 1
 
         Ok(FrameHeader {
-            _version: version,
+            version,
             layer,
             bitrate,
             sample_rate,
@@ -319,6 +456,8 @@ impl AudioMetadata for MpParser {
             None
         };
 
+        let encoder = vbr_header.as_ref().and_then(|vbr| vbr.encoder.clone());
+
         let (duration, bitrate, is_vbr) = if let Some(vbr) = vbr_header {
             // VBR file with Xing/Info header
             match (vbr.frames, vbr.bytes) {
@@ -377,6 +516,7 @@ impl AudioMetadata for MpParser {
             duration: duration.max(0.1), // Ensure positive duration
             vbr: is_vbr,
             sample_rate,
+            encoder,
         })
     }
 
@@ -399,4 +539,8 @@ impl AudioMetadata for MpParser {
     fn bit_depth(&self) -> Option<u16> {
         None // MP3 doesn't have a fixed bit depth concept
     }
+
+    fn encoder(&self) -> Option<String> {
+        self.encoder.clone()
+    }
 }
@@ -0,0 +1,356 @@
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use super::{AudioMetadata, Bitrate};
+
+/// Refuse to load a `moov` atom bigger than this
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+
+pub(crate) struct Mp4Parser {
+    bitrate: Bitrate,
+    duration: f64,
+    sample_rate: u32,
+    bit_depth: Option<u16>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Codec {
+    Aac,
+    Alac,
+}
+
+/// What's needed from the audio track's sample description
+#[derive(Debug)]
+struct SampleEntry {
+    codec: Codec,
+    sample_rate: u32,
+    bit_depth: Option<u16>,
+    /// (average, maximum) in bits per second, 0 if unknown
+    bitrates: (u32, u32),
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn be_u16(data: &[u8], pos: usize) -> Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid("Atom is too short"))
+}
+
+fn be_u32(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid("Atom is too short"))
+}
+
+fn be_u64(data: &[u8], pos: usize) -> Result<u64> {
+    Ok((be_u32(data, pos)? as u64) << 32 | be_u32(data, pos + 4)? as u64)
+}
+
+/// Splits the contents of an atom into its child atoms, as (type, contents) pairs
+fn child_atoms(data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let mut atoms = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let size = be_u32(data, pos)? as u64;
+        let atom_type = [data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]];
+        let (header_size, size) = match size {
+            0 => (8, (data.len() - pos) as u64),
+            1 => (16, be_u64(data, pos + 8)?),
+            _ => (8, size),
+        };
+        let end = match (pos as u64)
+            .checked_add(size)
+            .filter(|end| size >= header_size && *end <= data.len() as u64)
+        {
+            Some(end) => end as usize,
+            None => return Err(invalid("Invalid atom size")),
+        };
+        atoms.push((atom_type, &data[pos + header_size as usize..end]));
+        pos = end;
+    }
+    Ok(atoms)
+}
+
+fn find_atom<'a>(data: &'a [u8], atom_type: &[u8; 4]) -> Result<Option<&'a [u8]>> {
+    Ok(child_atoms(data)?
+        .into_iter()
+        .find(|(t, _)| t == atom_type)
+        .map(|(_, contents)| contents))
+}
+
+fn require_atom<'a>(data: &'a [u8], atom_type: &[u8; 4]) -> Result<&'a [u8]> {
+    find_atom(data, atom_type)?.ok_or_else(|| {
+        invalid(&format!(
+            "Missing {} atom",
+            String::from_utf8_lossy(atom_type)
+        ))
+    })
+}
+
+impl Mp4Parser {
+    /// Returns the contents of `moov` and the size of `mdat`
+    fn read_top_level(file: &mut File) -> Result<(Vec<u8>, u64)> {
+        let file_size = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(0))?;
+
+        let mut moov = None;
+        let mut mdat_size = 0;
+        let mut pos = 0;
+        let mut first = true;
+
+        while pos + 8 <= file_size {
+            file.seek(SeekFrom::Start(pos))?;
+            let mut header = [0u8; 8];
+            file.read_exact(&mut header)?;
+            let atom_type = [header[4], header[5], header[6], header[7]];
+            let (header_size, size) =
+                match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+                    0 => (8, file_size - pos),
+                    1 => {
+                        let mut largesize = [0u8; 8];
+                        file.read_exact(&mut largesize)?;
+                        (16, u64::from_be_bytes(largesize))
+                    }
+                    size => (8, size as u64),
+                };
+            let end = match pos
+                .checked_add(size)
+                .filter(|end| size >= header_size && *end <= file_size)
+            {
+                Some(end) => end,
+                None => return Err(invalid("Invalid atom size")),
+            };
+            if first && &atom_type != b"ftyp" {
+                return Err(invalid("Not an MP4 file"));
+            }
+            first = false;
+
+            match &atom_type {
+                b"moov" => {
+                    if size - header_size > MAX_MOOV_SIZE {
+                        return Err(invalid("moov atom is too large"));
+                    }
+                    let mut data = vec![0u8; (size - header_size) as usize];
+                    file.read_exact(&mut data)?;
+                    moov = Some(data);
+                }
+                b"mdat" => mdat_size += size - header_size,
+                b"moof" => return Err(invalid("Fragmented MP4 files aren't supported")),
+                _ => (),
+            }
+            pos = end;
+        }
+
+        Ok((moov.ok_or_else(|| invalid("Missing moov atom"))?, mdat_size))
+    }
+
+    /// Finds the first sound track, returning its `mdia` atom
+    fn find_audio_track(moov: &[u8]) -> Result<&[u8]> {
+        for (atom_type, trak) in child_atoms(moov)? {
+            if &atom_type != b"trak" {
+                continue;
+            }
+            let mdia = require_atom(trak, b"mdia")?;
+            let hdlr = require_atom(mdia, b"hdlr")?;
+            // version/flags (4), pre_defined (4), handler_type (4)
+            if hdlr.get(8..12) == Some(b"soun") {
+                return Ok(mdia);
+            }
+        }
+        Err(invalid("No audio track found"))
+    }
+
+    /// Returns (timescale, duration) from `mdhd`
+    fn parse_mdhd(mdhd: &[u8]) -> Result<(u32, u64)> {
+        match mdhd.first() {
+            // version, flags, creation and modification times are 32 bit
+            Some(0) => Ok((be_u32(mdhd, 12)?, be_u32(mdhd, 16)? as u64)),
+            // ...or 64 bit
+            Some(1) => Ok((be_u32(mdhd, 20)?, be_u64(mdhd, 24)?)),
+            _ => Err(invalid("Unsupported mdhd version")),
+        }
+    }
+
+    fn parse_stsd(stsd: &[u8]) -> Result<SampleEntry> {
+        // version/flags (4), entry count (4)
+        if be_u32(stsd, 4)? != 1 {
+            return Err(invalid("Expected exactly one sample description"));
+        }
+        let entries = child_atoms(stsd.get(8..).unwrap_or_default())?;
+        let (entry_type, entry) = entries
+            .first()
+            .ok_or_else(|| invalid("Missing sample description"))?;
+
+        let codec = match entry_type {
+            b"mp4a" => Codec::Aac,
+            b"alac" => Codec::Alac,
+            _ => {
+                return Err(invalid(&format!(
+                    "Unsupported codec {}",
+                    String::from_utf8_lossy(entry_type)
+                )))
+            }
+        };
+
+        // reserved (6), data reference index (2), then the (QuickTime) sound description
+        let version = be_u16(entry, 8)?;
+        let mut sample_rate = be_u32(entry, 24)? >> 16;
+        let children_start = match version {
+            0 => 28,
+            1 => 28 + 16,
+            _ => return Err(invalid("Unsupported sound description version")),
+        };
+        let children = entry.get(children_start..).unwrap_or_default();
+
+        let (bit_depth, bitrates) = match codec {
+            Codec::Aac => {
+                let esds = require_atom(children, b"esds")?;
+                (None, Self::parse_esds(esds)?)
+            }
+            Codec::Alac => {
+                // version/flags (4), then the ALACSpecificConfig
+                let alac = require_atom(children, b"alac")?;
+                let bit_depth = *alac
+                    .get(9)
+                    .ok_or_else(|| invalid("alac atom is too short"))?;
+                let average = be_u32(alac, 20)?;
+                // the sound description's sample rate only has room for 16 bits
+                sample_rate = be_u32(alac, 24)?;
+                (Some(bit_depth as u16), (average, 0))
+            }
+        };
+
+        if sample_rate == 0 {
+            return Err(invalid("Invalid sample rate"));
+        }
+
+        Ok(SampleEntry {
+            codec,
+            sample_rate,
+            bit_depth,
+            bitrates,
+        })
+    }
+
+    /// Returns (average, maximum) bitrates from the DecoderConfigDescriptor
+    fn parse_esds(esds: &[u8]) -> Result<(u32, u32)> {
+        // descriptors have a tag, then a length stored in up to 4 bytes of 7 bits each
+        fn descriptor(data: &[u8], pos: usize) -> Result<(u8, usize, usize)> {
+            let tag = *data
+                .get(pos)
+                .ok_or_else(|| invalid("esds atom is too short"))?;
+            let mut length = 0;
+            let mut pos = pos + 1;
+            for _ in 0..4 {
+                let byte = *data
+                    .get(pos)
+                    .ok_or_else(|| invalid("esds atom is too short"))?;
+                pos += 1;
+                length = (length << 7) | (byte & 0x7F) as usize;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            Ok((tag, pos, length))
+        }
+
+        // version/flags (4)
+        let (tag, mut pos, _) = descriptor(esds, 4)?;
+        if tag != 0x03 {
+            return Err(invalid("Missing ES descriptor"));
+        }
+        // ES_ID (2), then flags saying which optional fields follow
+        let flags = *esds
+            .get(pos + 2)
+            .ok_or_else(|| invalid("esds atom is too short"))?;
+        pos += 3;
+        if flags & 0x80 != 0 {
+            pos += 2;
+        }
+        if flags & 0x40 != 0 {
+            pos += 1 + *esds
+                .get(pos)
+                .ok_or_else(|| invalid("esds atom is too short"))? as usize;
+        }
+        if flags & 0x20 != 0 {
+            pos += 2;
+        }
+
+        let (tag, pos, _) = descriptor(esds, pos)?;
+        if tag != 0x04 {
+            return Err(invalid("Missing decoder config descriptor"));
+        }
+        // object type (1), stream type (1), buffer size (3), max bitrate (4), average bitrate (4)
+        Ok((be_u32(esds, pos + 9)?, be_u32(esds, pos + 5)?))
+    }
+}
+
+impl AudioMetadata for Mp4Parser {
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let (moov, mdat_size) = Self::read_top_level(&mut file)?;
+
+        if find_atom(&moov, b"mvex")?.is_some() {
+            return Err(invalid("Fragmented MP4 files aren't supported"));
+        }
+
+        let mdia = Self::find_audio_track(&moov)?;
+        let (timescale, duration) = Self::parse_mdhd(require_atom(mdia, b"mdhd")?)?;
+        if timescale == 0 || duration == 0 {
+            return Err(invalid("Invalid track duration"));
+        }
+        let duration = duration as f64 / timescale as f64;
+
+        let minf = require_atom(mdia, b"minf")?;
+        let stbl = require_atom(minf, b"stbl")?;
+        let entry = Self::parse_stsd(require_atom(stbl, b"stsd")?)?;
+
+        let (average, maximum) = entry.bitrates;
+        let bitrate = if average > 0 {
+            let kbps = (average / 1000) as u16;
+            match entry.codec {
+                Codec::Alac => Bitrate::Lossless(kbps),
+                Codec::Aac if maximum == 0 || maximum == average => Bitrate::Constant(kbps),
+                Codec::Aac => Bitrate::Variable(kbps),
+            }
+        } else {
+            // work it out from the size of the audio data
+            let kbps = ((mdat_size as f64 * 8.0) / (duration * 1000.0)) as u16;
+            match entry.codec {
+                Codec::Alac => Bitrate::Lossless(kbps),
+                Codec::Aac => Bitrate::Variable(kbps),
+            }
+        };
+
+        Ok(Self {
+            bitrate,
+            duration,
+            sample_rate: entry.sample_rate,
+            bit_depth: entry.bit_depth,
+        })
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        self.bitrate
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    fn is_vbr(&self) -> bool {
+        matches!(self.bitrate, Bitrate::Variable(_) | Bitrate::Lossless(_))
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> Option<u16> {
+        self.bit_depth
+    }
+}
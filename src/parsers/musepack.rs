@@ -0,0 +1,145 @@
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use super::{skip_id3v2, AudioMetadata, Bitrate};
+
+const SAMPLE_RATES: [u32; 4] = [44100, 48000, 37800, 32000];
+
+/// Every Musepack frame decodes to this many samples
+const SAMPLES_PER_FRAME: u64 = 1152;
+
+/// How many SV8 packets to look through for the stream header
+const MAX_PACKETS: usize = 64;
+
+/// Musepack (.mpc), stream versions 7 and 8
+#[derive(Debug)]
+pub(crate) struct MusepackParser {
+    sample_rate: u32,
+    total_samples: u64,
+    audio_size: u64,
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// SV8 sizes are stored 7 bits per byte, the high bit meaning another byte follows
+fn read_varint(file: &mut File) -> Result<(u64, u64)> {
+    let mut value = 0;
+    for length in 1..=9 {
+        let mut byte = [0u8; 1];
+        file.read_exact(&mut byte)?;
+        value = (value << 7) | (byte[0] & 0x7F) as u64;
+        if byte[0] & 0x80 == 0 {
+            return Ok((value, length));
+        }
+    }
+    Err(invalid("Invalid Musepack size"))
+}
+
+impl MusepackParser {
+    /// Returns (sample rate, total samples)
+    fn parse_sv7(file: &mut File) -> Result<(u32, u64)> {
+        // "MP+" (3), version (1), frame count (4), flags (4)
+        let mut header = [0u8; 12];
+        file.read_exact(&mut header)?;
+        if header[3] & 0x0F != 7 {
+            return Err(invalid("Unsupported Musepack stream version"));
+        }
+        let frames = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        let flags = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        let sample_rate = SAMPLE_RATES[((flags >> 16) & 0x3) as usize];
+        Ok((sample_rate, frames * SAMPLES_PER_FRAME))
+    }
+
+    /// Returns (sample rate, total samples) from the stream header packet
+    fn parse_sv8(file: &mut File) -> Result<(u32, u64)> {
+        // "MPCK" has already been read
+        for _ in 0..MAX_PACKETS {
+            let mut key = [0u8; 2];
+            file.read_exact(&mut key)?;
+            // the size includes the key and the size itself
+            let (size, size_length) = read_varint(file)?;
+            let contents_size = size
+                .checked_sub(2 + size_length)
+                .ok_or_else(|| invalid("Invalid Musepack packet size"))?;
+
+            match &key {
+                b"SH" => {
+                    // CRC (4), stream version (1)
+                    let mut start = [0u8; 5];
+                    file.read_exact(&mut start)?;
+                    if start[4] != 8 {
+                        return Err(invalid("Unsupported Musepack stream version"));
+                    }
+                    let (total_samples, _) = read_varint(file)?;
+                    let (silence, _) = read_varint(file)?;
+                    // sample rate index (3 bits), max used bands (5 bits)
+                    let mut rate = [0u8; 1];
+                    file.read_exact(&mut rate)?;
+                    let sample_rate = *SAMPLE_RATES
+                        .get((rate[0] >> 5) as usize)
+                        .ok_or_else(|| invalid("Invalid sample rate"))?;
+                    return Ok((sample_rate, total_samples.saturating_sub(silence)));
+                }
+                b"AP" | b"SE" => break,
+                _ => {
+                    file.seek(SeekFrom::Current(contents_size as i64))?;
+                }
+            }
+        }
+        Err(invalid("Missing Musepack stream header"))
+    }
+}
+
+impl AudioMetadata for MusepackParser {
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        skip_id3v2(&mut file)?;
+        let start = file.stream_position()?;
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        let (sample_rate, total_samples) = if &magic == b"MPCK" {
+            Self::parse_sv8(&mut file)?
+        } else if &magic[0..3] == b"MP+" {
+            file.seek(SeekFrom::Start(start))?;
+            Self::parse_sv7(&mut file)?
+        } else {
+            return Err(invalid("Not a Musepack file"));
+        };
+
+        Ok(Self {
+            sample_rate,
+            total_samples,
+            audio_size: file_size - start,
+        })
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        let duration = self.duration();
+        if duration > 0.0 {
+            Bitrate::Variable(((self.audio_size as f64 * 8.0) / (duration * 1000.0)) as u16)
+        } else {
+            Bitrate::Variable(0)
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.total_samples as f64 / self.sample_rate as f64
+    }
+
+    fn is_vbr(&self) -> bool {
+        true // Musepack is always VBR
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> Option<u16> {
+        None
+    }
+}
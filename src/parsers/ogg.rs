@@ -1,3 +1,4 @@
+use super::tags::{read_ogg_comment, vorbis_comment_vendor};
 use super::{AudioMetadata, Bitrate};
 use std::fs::File;
 use std::io::{Read, Result, Seek, SeekFrom};
@@ -8,10 +9,12 @@ pub struct OggParser {
     duration: f64,
     sample_rate: u32,
     bit_depth: Option<u16>,
+    encoder: Option<String>,
 }
 
 impl AudioMetadata for OggParser {
     fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
         let mut file = File::open(path)?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)?;
@@ -37,11 +40,18 @@ impl AudioMetadata for OggParser {
             Bitrate::Constant(avg_bitrate)
         };
 
+        // the comment header's vendor string says what encoded the stream
+        let encoder = read_ogg_comment(path)
+            .ok()
+            .flatten()
+            .and_then(|comment| vorbis_comment_vendor(&comment));
+
         Ok(OggParser {
             bitrate,
             duration,
             sample_rate,
             bit_depth,
+            encoder,
         })
     }
 
@@ -64,6 +74,10 @@ impl AudioMetadata for OggParser {
     fn bit_depth(&self) -> Option<u16> {
         self.bit_depth
     }
+
+    fn encoder(&self) -> Option<String> {
+        self.encoder.clone()
+    }
 }
 
 impl OggParser {
@@ -0,0 +1,540 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::utils::latin1_to_string;
+
+/// Cap on how much of a tag gets read into memory (embedded artwork can be huge)
+const MAX_TAG_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The tags that are worth searching by
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Tags {
+    pub(crate) artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) track: Option<String>,
+    pub(crate) year: Option<String>,
+}
+
+impl Tags {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.values().next().is_none()
+    }
+
+    /// All the tag values that are set
+    pub(crate) fn values(&self) -> impl Iterator<Item = &str> {
+        [
+            &self.artist,
+            &self.album,
+            &self.title,
+            &self.track,
+            &self.year,
+        ]
+        .into_iter()
+        .filter_map(|value| value.as_deref())
+    }
+
+    /// Fills in anything missing from `self` using `other`
+    fn or(mut self, other: Tags) -> Tags {
+        self.artist = self.artist.or(other.artist);
+        self.album = self.album.or(other.album);
+        self.title = self.title.or(other.title);
+        self.track = self.track.or(other.track);
+        self.year = self.year.or(other.year);
+        self
+    }
+
+    /// Sets a field using a (case insensitive) Vorbis comment/APEv2 style key.
+    /// The first value for a key wins.
+    fn set_from_key(&mut self, key: &str, value: &str) {
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if value.is_empty() {
+            return;
+        }
+        let field = match key.to_ascii_uppercase().as_str() {
+            "ARTIST" => &mut self.artist,
+            "ALBUM" => &mut self.album,
+            "TITLE" => &mut self.title,
+            "TRACK" | "TRACKNUMBER" => &mut self.track,
+            "YEAR" | "DATE" => &mut self.year,
+            _ => return,
+        };
+        if field.is_none() {
+            *field = Some(value.to_string());
+        }
+    }
+}
+
+pub(crate) fn read_tags(path: &Path) -> Option<Result<Tags>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())?;
+    Some(match extension.as_str() {
+        "mp3" | "mp2" | "mp1" => read_mp3_tags(path),
+        "flac" => read_flac_tags(path),
+        "opus" | "ogg" | "oga" => read_ogg_tags(path),
+        "wav" => read_riff_id3(path),
+        "aiff" | "aifc" | "aif" => read_aiff_id3(path),
+        _ => return None,
+    })
+}
+
+/// MP3s can have ID3v2 at the start, APEv2 and/or ID3v1 at the end.
+/// ID3v2 takes priority, then APEv2, then ID3v1.
+fn read_mp3_tags(path: &Path) -> Result<Tags> {
+    let mut file = File::open(path)?;
+    let id3v2 = read_id3v2(&mut file)?.unwrap_or_default();
+    let apev2 = read_apev2(&mut file)?.unwrap_or_default();
+    let id3v1 = read_id3v1(&mut file)?.unwrap_or_default();
+    Ok(id3v2.or(apev2).or(id3v1))
+}
+
+fn read_flac_tags(path: &Path) -> Result<Tags> {
+    let mut file = File::open(path)?;
+
+    let mut signature = [0; 4];
+    file.read_exact(&mut signature)?;
+    if &signature != b"fLaC" {
+        return Err(std::io::Error::other("Not a FLAC file"));
+    }
+
+    loop {
+        let mut block_header = [0; 4];
+        file.read_exact(&mut block_header)?;
+
+        let is_last = (block_header[0] & 0x80) != 0;
+        let block_type = block_header[0] & 0x7F;
+        let length = u32::from_be_bytes([0, block_header[1], block_header[2], block_header[3]]);
+
+        if block_type == 4 {
+            // VORBIS_COMMENT
+            let mut data = vec![0; length as usize];
+            file.read_exact(&mut data)?;
+            return parse_vorbis_comment(&data);
+        }
+        if is_last {
+            return Ok(Tags::default());
+        }
+        file.seek(SeekFrom::Current(length as i64))?;
+    }
+}
+
+fn read_ogg_tags(path: &Path) -> Result<Tags> {
+    match read_ogg_comment(path)? {
+        Some(comment) => parse_vorbis_comment(&comment),
+        None => Ok(Tags::default()),
+    }
+}
+
+/// Returns the Vorbis comment from an Ogg file's comment header.
+/// The comment header is always the second packet of the first logical stream.
+pub(super) fn read_ogg_comment(path: &Path) -> Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let mut serial = None;
+    let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut read = 0;
+
+    while packets.len() < 3 && read < MAX_TAG_SIZE {
+        let mut header = [0u8; 27];
+        file.read_exact(&mut header)?;
+        if &header[0..4] != b"OggS" {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Invalid Ogg page",
+            ));
+        }
+        let page_serial = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+        let mut segment_table = vec![0u8; header[26] as usize];
+        file.read_exact(&mut segment_table)?;
+        let page_size: u64 = segment_table.iter().map(|&s| s as u64).sum();
+        read += 27 + segment_table.len() as u64 + page_size;
+
+        if *serial.get_or_insert(page_serial) != page_serial {
+            file.seek(SeekFrom::Current(page_size as i64))?;
+            continue;
+        }
+
+        for segment in segment_table {
+            let packet = packets.last_mut().unwrap();
+            let start = packet.len();
+            packet.resize(start + segment as usize, 0);
+            file.read_exact(&mut packet[start..])?;
+            // a segment shorter than 255 bytes ends the packet
+            if segment < 255 {
+                packets.push(Vec::new());
+            }
+        }
+    }
+
+    let comment = packets
+        .get(1)
+        .filter(|_| packets.len() >= 3)
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "No comment header found")
+        })?;
+    let data = if comment.starts_with(b"\x03vorbis") {
+        &comment[7..]
+    } else if comment.starts_with(b"OpusTags") {
+        &comment[8..]
+    } else if comment.first().is_some_and(|&b| b & 0x7F == 4) && comment.len() >= 4 {
+        // FLAC in Ogg, a VORBIS_COMMENT metadata block
+        &comment[4..]
+    } else if packets[0].starts_with(b"Speex   ") {
+        &comment[..]
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(data.to_vec()))
+}
+
+/// WAV files can store an ID3v2 tag in an "id3 " chunk
+fn read_riff_id3(path: &Path) -> Result<Tags> {
+    let mut file = File::open(path)?;
+    let mut riff_header = [0u8; 12];
+    file.read_exact(&mut riff_header)?;
+    if &riff_header[0..4] != b"RIFF" || &riff_header[8..12] != b"WAVE" {
+        return Err(std::io::Error::other("Not a valid WAV file"));
+    }
+    read_chunked_id3(&mut file, u32::from_le_bytes)
+}
+
+/// AIFF files can store an ID3v2 tag in an "ID3 " chunk
+fn read_aiff_id3(path: &Path) -> Result<Tags> {
+    let mut file = File::open(path)?;
+    let mut form_header = [0u8; 12];
+    file.read_exact(&mut form_header)?;
+    if &form_header[0..4] != b"FORM" || !matches!(&form_header[8..12], b"AIFF" | b"AIFC") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Not a valid AIFF/AIFC file",
+        ));
+    }
+    read_chunked_id3(&mut file, u32::from_be_bytes)
+}
+
+fn read_chunked_id3(file: &mut File, chunk_size: fn([u8; 4]) -> u32) -> Result<Tags> {
+    let mut chunk_header = [0u8; 8];
+    while file.read_exact(&mut chunk_header).is_ok() {
+        let size = chunk_size([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]);
+        if chunk_header[0..4].eq_ignore_ascii_case(b"id3 ") {
+            return Ok(read_id3v2(file)?.unwrap_or_default());
+        }
+        // chunks are padded to even byte boundaries
+        file.seek(SeekFrom::Current(size as i64 + (size % 2) as i64))?;
+    }
+    Ok(Tags::default())
+}
+
+/// The vendor string at the start of a Vorbis comment, which names the encoder
+pub(super) fn vorbis_comment_vendor(data: &[u8]) -> Option<String> {
+    let length = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?) as usize;
+    let vendor = String::from_utf8_lossy(data.get(4..4 + length)?);
+    let vendor = vendor.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!vendor.is_empty()).then(|| vendor.to_string())
+}
+
+fn parse_vorbis_comment(data: &[u8]) -> Result<Tags> {
+    fn read_u32(data: &mut Cursor<&[u8]>) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        data.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+    let mut data = Cursor::new(data);
+
+    let vendor_length = read_u32(&mut data)?;
+    data.seek(SeekFrom::Current(vendor_length as i64))?;
+
+    let mut tags = Tags::default();
+    let count = read_u32(&mut data)?;
+    for _ in 0..count {
+        let length = read_u32(&mut data)? as u64;
+        if length > data.get_ref().len() as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Invalid comment length",
+            ));
+        }
+        let mut comment = vec![0u8; length as usize];
+        data.read_exact(&mut comment)?;
+        if let Some((key, value)) = String::from_utf8_lossy(&comment).split_once('=') {
+            tags.set_from_key(key, value);
+        }
+    }
+    Ok(tags)
+}
+
+/// Reads an ID3v2 tag from the reader's current position.
+/// Returns `None` (leaving the reader where it was) if there isn't one.
+fn read_id3v2<R: Read + Seek>(reader: &mut R) -> Result<Option<Tags>> {
+    let start = reader.stream_position()?;
+    let mut header = [0u8; 10];
+    if reader.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" {
+        reader.seek(SeekFrom::Start(start))?;
+        return Ok(None);
+    }
+    let version = header[3];
+    let flags = header[5];
+    let size = synchsafe([header[6], header[7], header[8], header[9]]) as u64;
+    if !(2..=4).contains(&version) || size > MAX_TAG_SIZE {
+        return Ok(None);
+    }
+    let end = reader.stream_position()? + size;
+
+    // the whole tag has been unsynchronised (only possible per-frame in v2.4), so undo it and start again
+    if flags & 0x80 != 0 && version < 4 {
+        let mut data = vec![0u8; size as usize];
+        reader.read_exact(&mut data)?;
+        let mut data = remove_unsynchronisation(&data);
+        let mut header = header.to_vec();
+        header[5] &= !0x80;
+        let length = synchsafe_bytes(data.len() as u32);
+        header[6..10].copy_from_slice(&length);
+        header.append(&mut data);
+        return read_id3v2(&mut Cursor::new(header));
+    }
+
+    if flags & 0x40 != 0 && version >= 3 {
+        // skip the extended header
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)?;
+        let extended_size = match version {
+            3 => u32::from_be_bytes(buf) as i64,
+            _ => synchsafe(buf) as i64 - 4,
+        };
+        reader.seek(SeekFrom::Current(extended_size))?;
+    }
+
+    let (id_length, header_length) = if version == 2 { (3, 6) } else { (4, 10) };
+    let mut tags = Tags::default();
+    let mut artist_fallback = None;
+
+    while reader.stream_position()? + header_length < end {
+        let mut frame_header = [0u8; 10];
+        reader.read_exact(&mut frame_header[..header_length as usize])?;
+        if frame_header[0] == 0 {
+            // padding
+            break;
+        }
+        let id = latin1_to_string(&frame_header[..id_length]);
+        let frame_size = match version {
+            2 => u32::from_be_bytes([0, frame_header[3], frame_header[4], frame_header[5]]),
+            3 => u32::from_be_bytes([
+                frame_header[4],
+                frame_header[5],
+                frame_header[6],
+                frame_header[7],
+            ]),
+            _ => synchsafe([
+                frame_header[4],
+                frame_header[5],
+                frame_header[6],
+                frame_header[7],
+            ]),
+        } as u64;
+        let frame_end = reader.stream_position()? + frame_size;
+        if frame_end > end {
+            break;
+        }
+
+        let field = match id.as_str() {
+            "TP1" | "TPE1" => Some(&mut tags.artist),
+            "TP2" | "TPE2" => Some(&mut artist_fallback),
+            "TAL" | "TALB" => Some(&mut tags.album),
+            "TT2" | "TIT2" => Some(&mut tags.title),
+            "TRK" | "TRCK" => Some(&mut tags.track),
+            "TYE" | "TYER" | "TDRC" => Some(&mut tags.year),
+            _ => None,
+        };
+
+        let format_flags = frame_header[9];
+        let (compressed, encrypted, grouped, unsynchronised, length_indicator) = match version {
+            2 => (false, false, false, false, false),
+            3 => (
+                format_flags & 0x80 != 0,
+                format_flags & 0x40 != 0,
+                format_flags & 0x20 != 0,
+                false,
+                false,
+            ),
+            _ => (
+                format_flags & 0x08 != 0,
+                format_flags & 0x04 != 0,
+                format_flags & 0x40 != 0,
+                format_flags & 0x02 != 0,
+                format_flags & 0x01 != 0,
+            ),
+        };
+
+        if let Some(field) = field.filter(|field| field.is_none() && !compressed && !encrypted) {
+            let mut data = vec![0u8; frame_size as usize];
+            reader.read_exact(&mut data)?;
+            // grouping adds a byte before the text, a data length indicator adds 4
+            let skip = grouped as usize + 4 * length_indicator as usize;
+            let data = data.get(skip..).unwrap_or_default();
+            let data = if unsynchronised {
+                remove_unsynchronisation(data)
+            } else {
+                data.to_vec()
+            };
+            *field = decode_text_frame(&data);
+        }
+        reader.seek(SeekFrom::Start(frame_end))?;
+    }
+
+    reader.seek(SeekFrom::Start(end))?;
+    tags.artist = tags.artist.or(artist_fallback);
+    Ok(Some(tags))
+}
+
+fn read_id3v1<R: Read + Seek>(reader: &mut R) -> Result<Option<Tags>> {
+    if reader.seek(SeekFrom::End(0))? < 128 {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::End(-128))?;
+    let mut tag = [0u8; 128];
+    reader.read_exact(&mut tag)?;
+    if &tag[0..3] != b"TAG" {
+        return Ok(None);
+    }
+
+    let text = |bytes: &[u8]| {
+        let text = latin1_to_string(bytes)
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+            .to_string();
+        (!text.is_empty()).then_some(text)
+    };
+    Ok(Some(Tags {
+        title: text(&tag[3..33]),
+        artist: text(&tag[33..63]),
+        album: text(&tag[63..93]),
+        year: text(&tag[93..97]),
+        // ID3v1.1 puts the track number at the end of the comment
+        track: (tag[125] == 0 && tag[126] != 0).then(|| tag[126].to_string()),
+    }))
+}
+
+/// APEv2 tags live at the end of the file, possibly followed by an ID3v1 tag
+fn read_apev2<R: Read + Seek>(reader: &mut R) -> Result<Option<Tags>> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    for footer_offset in [32, 128 + 32] {
+        if file_size < footer_offset {
+            continue;
+        }
+        let footer_start = file_size - footer_offset;
+        reader.seek(SeekFrom::Start(footer_start))?;
+        let mut footer = [0u8; 32];
+        reader.read_exact(&mut footer)?;
+        if &footer[0..8] != b"APETAGEX" {
+            continue;
+        }
+
+        // the tag size includes the footer, but not the header
+        let tag_size = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as u64;
+        let item_count = u32::from_le_bytes([footer[16], footer[17], footer[18], footer[19]]);
+        if tag_size < 32 || tag_size > footer_start + 32 || tag_size > MAX_TAG_SIZE {
+            return Ok(None);
+        }
+        reader.seek(SeekFrom::Start(footer_start + 32 - tag_size))?;
+        let mut items = vec![0u8; tag_size as usize - 32];
+        reader.read_exact(&mut items)?;
+
+        let mut tags = Tags::default();
+        let mut pos = 0;
+        for _ in 0..item_count {
+            let Some(header) = items.get(pos..pos + 8) else {
+                break;
+            };
+            let value_size = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+            let item_flags = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            pos += 8;
+            let Some(key_length) = items[pos..].iter().position(|&b| b == 0) else {
+                break;
+            };
+            let key = latin1_to_string(&items[pos..pos + key_length]);
+            pos += key_length + 1;
+            let Some(value) = items.get(pos..pos + value_size as usize) else {
+                break;
+            };
+            pos += value_size as usize;
+
+            // bits 1-2 are 0 for UTF-8 text items
+            if item_flags & 0b110 == 0 {
+                tags.set_from_key(&key, &String::from_utf8_lossy(value));
+            }
+        }
+        return Ok(Some(tags));
+    }
+    Ok(None)
+}
+
+fn decode_text_frame(data: &[u8]) -> Option<String> {
+    let (&encoding, text) = data.split_first()?;
+    let text = match encoding {
+        0 => latin1_to_string(text),
+        1 | 2 => {
+            let mut units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|pair| match encoding {
+                    2 => u16::from_be_bytes([pair[0], pair[1]]),
+                    _ => u16::from_le_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            // UTF-16 with a BOM, which may say the text is actually big endian
+            if encoding == 1 {
+                match units.first() {
+                    Some(0xFFFE) => units.iter_mut().for_each(|unit| *unit = unit.swap_bytes()),
+                    Some(0xFEFF) => (),
+                    _ => return None,
+                }
+                units.remove(0);
+            }
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).to_string(),
+        _ => return None,
+    };
+
+    // ID3v2.4 separates multiple values with nulls
+    let text = text
+        .split('\0')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(" / ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn synchsafe(bytes: [u8; 4]) -> u32 {
+    ((bytes[0] & 0x7F) as u32) << 21
+        | ((bytes[1] & 0x7F) as u32) << 14
+        | ((bytes[2] & 0x7F) as u32) << 7
+        | (bytes[3] & 0x7F) as u32
+}
+
+fn synchsafe_bytes(n: u32) -> [u8; 4] {
+    [
+        ((n >> 21) & 0x7F) as u8,
+        ((n >> 14) & 0x7F) as u8,
+        ((n >> 7) & 0x7F) as u8,
+        (n & 0x7F) as u8,
+    ]
+}
+
+/// Unsynchronisation inserts a 0x00 after every 0xFF
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut previous = 0;
+    for &byte in data {
+        if !(previous == 0xFF && byte == 0x00) {
+            output.push(byte);
+        }
+        previous = byte;
+    }
+    output
+}
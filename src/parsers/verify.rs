@@ -0,0 +1,113 @@
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use md5::{Digest, Md5};
+
+use super::{parse, MpParser};
+use crate::messages::FileAttribute;
+
+/// How far (seconds) the duration can be from what the uploader reported
+const DURATION_TOLERANCE: f64 = 2.0;
+/// How far (as a fraction) the bitrate can be from what the uploader reported.
+/// Clients work out VBR averages differently, so this is fairly loose.
+const BITRATE_TOLERANCE: f64 = 0.1;
+
+fn failed(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Checks a finished download is intact.
+/// The file is parsed and compared to the attributes from the search result it was downloaded from,
+/// then MP3 and FLAC files are read all the way through to find broken frames.
+/// Files the parsers don't support always pass.
+pub(crate) fn verify_download(path: &Path, expected: &[FileAttribute]) -> Result<()> {
+    let metadata = match parse(path) {
+        Some(metadata) => {
+            metadata.map_err(|e| failed(format!("The file couldn't be parsed: {e}")))?
+        }
+        None => return Ok(()),
+    };
+
+    for attribute in expected {
+        match *attribute {
+            FileAttribute::Duration(duration) if duration > 0 => {
+                let difference = (metadata.duration() - duration as f64).abs();
+                // uploaders round durations differently, and a second is lost here and there
+                if difference > DURATION_TOLERANCE.max(duration as f64 * 0.01) {
+                    return Err(failed(format!(
+                        "Duration is {:.0}s, the uploader reported {duration}s",
+                        metadata.duration()
+                    )));
+                }
+            }
+            FileAttribute::Bitrate(bitrate) if bitrate > 0 => {
+                let actual = *metadata.bitrate() as f64;
+                if (actual - bitrate as f64).abs() > (bitrate as f64 * BITRATE_TOLERANCE).max(2.0) {
+                    return Err(failed(format!(
+                        "Bitrate is {actual} kbps, the uploader reported {bitrate} kbps"
+                    )));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "mp3" | "mp2" | "mp1" => MpParser::check_frames(path),
+        "flac" => check_flac_stream(path),
+        _ => Ok(()),
+    }
+}
+
+/// Decodes the whole stream, which checks every frame's CRC,
+/// then makes sure the sample count and MD5 match the STREAMINFO block
+fn check_flac_stream(path: &Path) -> Result<()> {
+    let to_io = |e: claxon::Error| match e {
+        claxon::Error::IoError(e) => e,
+        e => failed(format!("Broken FLAC frame: {e}")),
+    };
+    let mut reader = claxon::FlacReader::open(path).map_err(to_io)?;
+    let info = reader.streaminfo();
+    // an all zero signature means the encoder didn't work one out
+    let check_md5 = info.md5sum != [0; 16];
+    let bytes_per_sample = info.bits_per_sample.div_ceil(8) as usize;
+
+    let mut hasher = Md5::new();
+    let mut samples = 0u64;
+    let mut blocks = reader.blocks();
+    let mut buffer = Vec::new();
+    while let Some(block) = blocks.read_next_or_eof(buffer).map_err(to_io)? {
+        if check_md5 {
+            // the signature is of the interleaved samples, little endian, using as few bytes as possible
+            let mut bytes = Vec::with_capacity(
+                block.duration() as usize * block.channels() as usize * bytes_per_sample,
+            );
+            for i in 0..block.duration() {
+                for channel in 0..block.channels() {
+                    bytes.extend_from_slice(
+                        &block.sample(channel, i).to_le_bytes()[..bytes_per_sample],
+                    );
+                }
+            }
+            hasher.update(&bytes);
+        }
+        samples += block.duration() as u64;
+        buffer = block.into_buffer();
+    }
+
+    if let Some(total) = info.samples.filter(|total| *total != samples) {
+        return Err(failed(format!(
+            "STREAMINFO says there are {total} samples, but the stream has {samples}"
+        )));
+    }
+    if check_md5 && hasher.finalize()[..] != info.md5sum {
+        return Err(failed(String::from(
+            "The decoded audio doesn't match the STREAMINFO MD5 signature",
+        )));
+    }
+    Ok(())
+}
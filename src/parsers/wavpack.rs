@@ -0,0 +1,171 @@
+use std::fs::File;
+use std::io::{Read, Result, Seek, SeekFrom};
+use std::path::Path;
+
+use super::{skip_id3v2, AudioMetadata, Bitrate};
+
+/// Sample rates indexed by bits 23-26 of a block's flags, 15 means it's stored in a metadata sub-block
+const SAMPLE_RATES: [u32; 15] = [
+    6000, 8000, 9600, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000, 64000, 88200, 96000,
+    192000,
+];
+
+/// How many blocks to look through for the first one containing audio
+const MAX_BLOCKS: usize = 64;
+
+/// Blocks can't be bigger than this, so anything claiming to be isn't WavPack
+const MAX_BLOCK_SIZE: usize = 1024 * 1024;
+
+const FLAG_HYBRID: u32 = 0x8;
+const FLAG_DSD: u32 = 0x8000_0000;
+const ID_SAMPLE_RATE: u8 = 0x27;
+
+/// WavPack (.wv)
+#[derive(Debug)]
+pub(crate) struct WavPackParser {
+    sample_rate: u32,
+    bit_depth: u16,
+    total_samples: u64,
+    file_size: u64,
+    /// Hybrid files keep the correction data in a separate .wvc file, making the .wv lossy
+    is_lossy: bool,
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn le_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+impl WavPackParser {
+    /// Looks through a block's metadata sub-blocks for a non-standard sample rate
+    fn custom_sample_rate(block: &[u8]) -> Option<u32> {
+        let mut pos = 0;
+        while pos + 2 <= block.len() {
+            let id = block[pos];
+            // sizes are in 16 bit words, large sub-blocks have a 3 byte size
+            let (header_size, size) = if id & 0x80 != 0 {
+                let bytes = block.get(pos + 1..pos + 4)?;
+                (
+                    4,
+                    (bytes[0] as usize | (bytes[1] as usize) << 8 | (bytes[2] as usize) << 16) * 2,
+                )
+            } else {
+                (2, block[pos + 1] as usize * 2)
+            };
+            let data = block.get(pos + header_size..pos + header_size + size)?;
+            if id & 0x3F == ID_SAMPLE_RATE && data.len() >= 3 {
+                return Some(data[0] as u32 | (data[1] as u32) << 8 | (data[2] as u32) << 16);
+            }
+            pos += header_size + size;
+        }
+        None
+    }
+}
+
+impl AudioMetadata for WavPackParser {
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        skip_id3v2(&mut file)?;
+
+        for _ in 0..MAX_BLOCKS {
+            let mut header = [0u8; 32];
+            file.read_exact(&mut header)?;
+            if &header[0..4] != b"wvpk" {
+                return Err(invalid("Not a WavPack file"));
+            }
+            // the size doesn't include the id and the size itself, so taking off the rest of the header
+            // leaves what's after it
+            let block_size = match (le_u32(&header, 4) as usize).checked_sub(24) {
+                Some(block_size) if block_size <= MAX_BLOCK_SIZE => block_size,
+                _ => return Err(invalid("Invalid WavPack block size")),
+            };
+            let block_samples = le_u32(&header, 20);
+            if block_samples == 0 {
+                // metadata only, e.g. the RIFF header, so keep looking
+                file.seek(SeekFrom::Current(block_size as i64))?;
+                continue;
+            }
+
+            // the total is 40 bits, the low 32 being all ones means it's unknown
+            let total_samples = le_u32(&header, 12);
+            if total_samples == u32::MAX {
+                return Err(invalid("Unknown number of samples"));
+            }
+            let total_samples = total_samples as u64 | (header[11] as u64) << 32;
+            let flags = le_u32(&header, 24);
+
+            let sample_rate = match ((flags >> 23) & 0xF) as usize {
+                index if index < SAMPLE_RATES.len() => SAMPLE_RATES[index],
+                _ => {
+                    let mut block = vec![0u8; block_size];
+                    file.read_exact(&mut block)?;
+                    Self::custom_sample_rate(&block)
+                        .ok_or_else(|| invalid("Missing sample rate"))?
+                }
+            };
+            let (sample_rate, total_samples, bit_depth) = if flags & FLAG_DSD != 0 {
+                // DSD audio is counted in bytes, each holding 8 one-bit samples
+                (sample_rate * 8, total_samples * 8, 1)
+            } else {
+                // bytes per sample, minus the bits shifted out
+                let bytes = (flags & 0x3) as u16 + 1;
+                let shift = ((flags >> 13) & 0x1F) as u16;
+                (
+                    sample_rate,
+                    total_samples,
+                    (bytes * 8).saturating_sub(shift),
+                )
+            };
+            if sample_rate == 0 {
+                return Err(invalid("Invalid sample rate"));
+            }
+
+            return Ok(Self {
+                sample_rate,
+                bit_depth,
+                total_samples,
+                file_size,
+                is_lossy: flags & FLAG_HYBRID != 0,
+            });
+        }
+        Err(invalid("No audio found"))
+    }
+
+    fn bitrate(&self) -> Bitrate {
+        let duration = self.duration();
+        let kbps = if duration > 0.0 {
+            ((self.file_size as f64 * 8.0) / (duration * 1000.0)) as u16
+        } else {
+            0
+        };
+        if self.is_lossy {
+            Bitrate::Variable(kbps)
+        } else {
+            Bitrate::Lossless(kbps)
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.total_samples as f64 / self.sample_rate as f64
+    }
+
+    fn is_vbr(&self) -> bool {
+        true
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn bit_depth(&self) -> Option<u16> {
+        if self.is_lossy {
+            None
+        } else {
+            Some(self.bit_depth)
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::{HashMap, VecDeque},
+    future::pending,
     net::Ipv4Addr,
     sync::Arc,
     time::Duration,
@@ -9,39 +10,57 @@ use crossbeam_deque::{Stealer, Worker};
 use smol::block_on;
 use tokio::{
     io::AsyncWriteExt,
-    net::{TcpListener, TcpStream},
+    net::TcpListener,
     sync::{broadcast::Sender, Mutex, RwLock},
     task::JoinHandle,
     time::sleep,
 };
 
 use crate::{
+    config::Config,
     constants::{ConnectionTypes, DownloadStatus, Percentage, MAX_RESULTS},
-    file_transfer::handle_file_transfer,
     events::SLSKEvents,
+    file_transfer::{handle_file_transfer, handle_upload},
     messages::{
-        FileSearchResponse, FolderContentsRequest, FolderContentsResponse, MessageTrait,
+        FileAttribute, FileSearchResponse, FolderContentsRequest, FolderContentsResponse, MessageTrait,
         MessageType, PeerInit, PierceFireWall, SharedFileListResponse, TransferRequest,
         TransferResponse, TransferResponseReason, UserInfoRequest, UserInfoResponse,
         _ReceiveConnectToPeer,
     },
+    obfuscation::PeerStream,
+    upload_handling::UploadMap,
     utils::{get_code_and_bytes_from_readable, log},
     PlaceInQueueRequest, PlaceInQueueResponse, QueueUpload, SLSKExitCode, SharedFileListRequest,
     TransferDirections, UploadDenied, UploadFailed, UploadQueueNotification,
     CONNECTION_TIME,
 };
 
+/// Where a peer listens: their IP, port, and obfuscated port if they have one
+pub(crate) type PeerAddress = (Ipv4Addr, u32, Option<u32>);
+
 /// Listens for connection attempts from peers and writes them to the queue
 pub(crate) async fn start_listener_task(
     listener: TcpListener,
+    obfuscated_listener: Option<TcpListener>,
     logged_in_listener: Arc<RwLock<bool>>,
-    direct_peers_list_writer: Worker<(TcpStream, String, u32, ConnectionTypes)>,
+    direct_peers_list_writer: Worker<(PeerStream, String, u32, ConnectionTypes)>,
 ) -> JoinHandle<SLSKExitCode> {
     tokio::spawn(async move {
         loop {
             sleep(Duration::from_nanos(1)).await;
-            match listener.accept().await {
-                Ok((mut peer_stream, _peer_addr)) => {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => {
+                    accepted.map(|(peer_stream, _)| PeerStream::plain(peer_stream))
+                }
+                accepted = async {
+                    match &obfuscated_listener {
+                        Some(obfuscated_listener) => obfuscated_listener.accept().await,
+                        None => pending().await,
+                    }
+                } => accepted.map(|(peer_stream, _)| PeerStream::obfuscated(peer_stream)),
+            };
+            match accepted {
+                Ok(mut peer_stream) => {
                     // If we receive unhandled connection requests from the previous session
                     if !*logged_in_listener.read().await {
                         let _ = peer_stream.shutdown();
@@ -63,6 +82,11 @@ pub(crate) async fn start_listener_task(
                             }
                             MessageType::PeerInit(1) => {
                                 if let Some(response) = PeerInit::from_stream(&mut bytes) {
+                                    // only peer messages stay obfuscated after the PeerInit
+                                    let peer_stream = match response.connection_type {
+                                        ConnectionTypes::PeerToPeer => peer_stream,
+                                        _ => peer_stream.into_plain(),
+                                    };
                                     let _ = direct_peers_list_writer.push((
                                         peer_stream,
                                         response.username,
@@ -86,8 +110,8 @@ pub(crate) async fn start_listener_task(
 pub(crate) async fn start_peer_task(
     prompted_peers_list_reader: Stealer<(String, u32, ConnectionTypes)>,
     indirect_peers_list_reader: Stealer<_ReceiveConnectToPeer>,
-    direct_peers_list_reader: Stealer<(TcpStream, String, u32, ConnectionTypes)>,
-    peer_user_info_map: Arc<Mutex<HashMap<String, (Ipv4Addr, u32)>>>,
+    direct_peers_list_reader: Stealer<(PeerStream, String, u32, ConnectionTypes)>,
+    peer_user_info_map: Arc<Mutex<HashMap<String, PeerAddress>>>,
     my_username: Arc<RwLock<Option<String>>>,
     peer_write_queue: Sender<SLSKEvents>,
     peer_token_message_map: Arc<Mutex<HashMap<u32, VecDeque<Vec<u8>>>>>,
@@ -100,17 +124,19 @@ pub(crate) async fn start_peer_task(
                     Arc<RwLock<DownloadStatus>>,
                     Arc<RwLock<Percentage>>,
                     Option<bool>,
+                    Option<Vec<FileAttribute>>,
                 )>,
             >,
         >,
     >,
+    upload_map: UploadMap,
     shares_message: Arc<RwLock<Option<Vec<u8>>>>,
+    config: Arc<RwLock<Config>>,
 ) -> JoinHandle<()> {
     tokio::spawn({
         async move {
             let results_map = Arc::new(Mutex::new(HashMap::<u32, u32>::new()));
-            let tcp_queue =
-                Worker::<(String, u32, tokio::net::TcpStream, ConnectionTypes)>::new_fifo();
+            let tcp_queue = Worker::<(String, u32, PeerStream, ConnectionTypes)>::new_fifo();
             let tcp_reader = tcp_queue.stealer();
             let tcp_queue = Arc::new(Mutex::new(tcp_queue));
 
@@ -124,21 +150,29 @@ pub(crate) async fn start_peer_task(
                                 loop {
                                     sleep(Duration::from_millis(10)).await;
 
-                                    if let Some((ip, port)) =
+                                    if let Some((ip, port, obfuscated_port)) =
                                         peer_user_info_map.lock().await.get(&username).cloned()
                                     {
                                         let my_username = my_username.clone();
                                         let tcp_queue = tcp_queue.clone();
+                                        // only peer message connections can be obfuscated
+                                        let obfuscated_port = obfuscated_port.filter(|_| {
+                                            connection_type == ConnectionTypes::PeerToPeer
+                                        });
                                         tokio::spawn(async move {
-                                            if let Ok(Ok(mut peer_stream)) = tokio::time::timeout(
+                                            if let Ok(Ok(peer_stream)) = tokio::time::timeout(
                                                 Duration::from_secs(CONNECTION_TIME),
                                                 tokio::net::TcpStream::connect(format!(
-                                                    "{}:{port}",
-                                                    ip.to_string()
+                                                    "{ip}:{}",
+                                                    obfuscated_port.unwrap_or(port)
                                                 )),
                                             )
                                             .await
                                             {
+                                                let mut peer_stream = match obfuscated_port {
+                                                    Some(_) => PeerStream::obfuscated(peer_stream),
+                                                    None => PeerStream::plain(peer_stream),
+                                                };
                                                 block_on(
                                                     PeerInit::async_write_to(
                                                         &mut peer_stream,
@@ -218,7 +252,7 @@ pub(crate) async fn start_peer_task(
                                                 Ok(_) => tcp_queue.lock().await.push((
                                                     indirect_connection.username,
                                                     indirect_connection.firewall_token,
-                                                    peer_stream,
+                                                    PeerStream::plain(peer_stream),
                                                     indirect_connection.connection_type,
                                                 )),
                                                 Err(_) => {
@@ -262,9 +296,11 @@ pub(crate) async fn start_peer_task(
                     let peer_token_message_map = Arc::clone(&peer_token_message_map);
                     let peer_download_filename_map = Arc::clone(&peer_download_filename_map);
                     let file_info_map = Arc::clone(&file_info_map);
+                    let upload_map = Arc::clone(&upload_map);
                     let results_map = Arc::clone(&results_map);
                     let tcp_reader = tcp_reader.clone();
                     let shares_message = Arc::clone(&shares_message);
+                    let config = Arc::clone(&config);
 
                     async move {
                         loop {
@@ -273,7 +309,6 @@ pub(crate) async fn start_peer_task(
                             let file_info_map = Arc::clone(&file_info_map);
                             let results_map = Arc::clone(&results_map);
 
-                            // TODO: This works with downloads, but uploads need to be implemented too
                             let (username, token, mut peer_stream, connection_type) = loop {
                                 match tcp_reader.steal() {
                                     crossbeam_deque::Steal::Empty => {
@@ -289,13 +324,34 @@ pub(crate) async fn start_peer_task(
                                 let peer_download_filename_map =
                                     Arc::clone(&peer_download_filename_map);
                                 let shares_message = Arc::clone(&shares_message);
+                                let config = Arc::clone(&config);
+                                let upload_map = Arc::clone(&upload_map);
                                 async move {
-                                    if connection_type == ConnectionTypes::FileTransfer {
+                                    // file connections we made for an upload have its token
+                                    let upload = match connection_type {
+                                        ConnectionTypes::FileTransfer => {
+                                            upload_map.lock().await.remove(&token)
+                                        }
+                                        _ => None,
+                                    };
+                                    if let Some(upload) = upload {
+                                        handle_upload(
+                                            peer_stream,
+                                            token,
+                                            upload,
+                                            peer_task_write_queue,
+                                        )
+                                        .await;
+                                    } else if connection_type == ConnectionTypes::FileTransfer {
+                                        let download_directory =
+                                            config.read().await.downloads.directory.clone();
                                         handle_file_transfer(
                                             peer_stream,
                                             file_info_map,
                                             peer_download_filename_map,
                                             username,
+                                            peer_task_write_queue,
+                                            download_directory,
                                         )
                                         .await;
                                     } else {
@@ -333,10 +389,18 @@ pub(crate) async fn start_peer_task(
                                                 Ok((code, mut bytes)) => {
                                                     match code {
                                                         MessageType::Peer(4) => {
+                                                            // banned users don't get to browse us
+                                                            let is_banned = config
+                                                                .read()
+                                                                .await
+                                                                .users
+                                                                .banned
+                                                                .contains(&username);
                                                             if SharedFileListRequest::from_stream(
                                                                 &mut bytes,
                                                             )
                                                             .is_some()
+                                                                && !is_banned
                                                             {
                                                                 if let Some(shares_message) =
                                                                     shares_message
@@ -353,15 +417,17 @@ pub(crate) async fn start_peer_task(
                                                             }
                                                         }
                                                         MessageType::Peer(5) => {
-                                                            if let Some(_response) =
+                                                            if let Some(file_list) =
                                                                 SharedFileListResponse::from_stream(
                                                                     &mut bytes,
                                                                 )
                                                             {
-                                                                // loop {
-                                                                // println!("{response:?}");
-                                                                // TODO: UI events for SharedFileListResponse
-                                                                // }
+                                                                let _ = peer_task_write_queue.send(
+                                                                    SLSKEvents::UserShares {
+                                                                        username: username.clone(),
+                                                                        file_list,
+                                                                    },
+                                                                );
                                                             }
                                                         }
                                                         MessageType::Peer(9) => {
@@ -411,25 +477,61 @@ pub(crate) async fn start_peer_task(
                                                             }
                                                         }
                                                         MessageType::Peer(16) => {
-                                                            if let Some(response) =
+                                                            if let Some(info) =
                                                                 UserInfoResponse::from_stream(
                                                                     &mut bytes,
                                                                 )
                                                             {
-                                                                // loop {
-                                                                println!("{response:?}");
-                                                                // }
+                                                                let _ = peer_task_write_queue.send(
+                                                                    SLSKEvents::UserInfo {
+                                                                        username: username.clone(),
+                                                                        info,
+                                                                    },
+                                                                );
                                                             }
                                                         }
                                                         MessageType::Peer(36) => {
-                                                            if let Some(response) =
+                                                            if let Some(request) =
                                                                 FolderContentsRequest::from_stream(
                                                                     &mut bytes,
                                                                 )
                                                             {
-                                                                // loop {
-                                                                println!("{response:?}");
-                                                                // }
+                                                                let (index, is_banned, is_buddy) = {
+                                                                    let config =
+                                                                        config.read().await;
+                                                                    (
+                                                                        config.index.clone(),
+                                                                        config
+                                                                            .users
+                                                                            .banned
+                                                                            .contains(&username),
+                                                                        config
+                                                                            .users
+                                                                            .buddies
+                                                                            .contains(&username),
+                                                                    )
+                                                                };
+                                                                // banned users don't get to see our folders either
+                                                                if !is_banned {
+                                                                    let folders = index
+                                                                        .folder_contents(
+                                                                            &request.folder,
+                                                                            is_buddy,
+                                                                        )
+                                                                        .await
+                                                                        .unwrap_or_default();
+                                                                    let _ = block_on(
+                                                                        FolderContentsResponse::async_write_to(
+                                                                            &mut peer_stream,
+                                                                            FolderContentsResponse {
+                                                                                token: request.token,
+                                                                                folder: request.folder,
+                                                                                folders,
+                                                                            },
+                                                                        )
+                                                                        .await,
+                                                                    );
+                                                                }
                                                             }
                                                         }
                                                         MessageType::Peer(37) => {
@@ -438,9 +540,13 @@ pub(crate) async fn start_peer_task(
                                                                     &mut bytes,
                                                                 )
                                                             {
-                                                                // loop {
-                                                                println!("{response:?}");
-                                                                // }
+                                                                let _ = peer_task_write_queue.send(
+                                                                    SLSKEvents::FolderContents {
+                                                                        username: username.clone(),
+                                                                        folder: response.folder,
+                                                                        folders: response.folders,
+                                                                    },
+                                                                );
                                                             }
                                                         }
                                                         MessageType::Peer(40) => {
@@ -484,18 +590,89 @@ pub(crate) async fn start_peer_task(
                                                                     &mut bytes,
                                                                 )
                                                             {
-                                                                // loop {
-                                                                println!("received {response:?}");
-                                                                // }
+                                                                // the answer to a file we offered to upload
+                                                                let _ = peer_task_write_queue.send(
+                                                                    SLSKEvents::UploadResponse {
+                                                                        token: response.token,
+                                                                        allowed: matches!(
+                                                                            response.reason,
+                                                                            TransferResponseReason::Allowed(_)
+                                                                        ),
+                                                                    },
+                                                                );
                                                             }
                                                         }
                                                         MessageType::Peer(43) => {
                                                             if let Some(response) =
                                                                 QueueUpload::from_stream(&mut bytes)
                                                             {
-                                                                // loop {
-                                                                println!("{response:?}");
-                                                                // }
+                                                                let (index, is_banned, is_buddy) = {
+                                                                    let config =
+                                                                        config.read().await;
+                                                                    (
+                                                                        config.index.clone(),
+                                                                        config
+                                                                            .users
+                                                                            .banned
+                                                                            .contains(&username),
+                                                                        config
+                                                                            .users
+                                                                            .buddies
+                                                                            .contains(&username),
+                                                                    )
+                                                                };
+                                                                // buddy-only files aren't shared with anyone else
+                                                                let shared_file = index
+                                                                    .shared_file(&response.filename)
+                                                                    .await
+                                                                    .ok()
+                                                                    .flatten()
+                                                                    .filter(|(_, _, buddy_only)| {
+                                                                        is_buddy || !buddy_only
+                                                                    });
+                                                                if is_banned {
+                                                                    let _ = block_on(
+                                                                        UploadDenied::async_write_to(
+                                                                            &mut peer_stream,
+                                                                            UploadDenied {
+                                                                                filename: response
+                                                                                    .filename,
+                                                                                reason: String::from(
+                                                                                    "Banned",
+                                                                                ),
+                                                                            },
+                                                                        )
+                                                                        .await,
+                                                                    );
+                                                                } else if let Some((
+                                                                    path,
+                                                                    filesize,
+                                                                    _,
+                                                                )) = shared_file
+                                                                {
+                                                                    let _ = peer_task_write_queue.send(
+                                                                        SLSKEvents::UploadQueued {
+                                                                            username: username.clone(),
+                                                                            filename: response.filename,
+                                                                            path,
+                                                                            filesize,
+                                                                        },
+                                                                    );
+                                                                } else {
+                                                                    let _ = block_on(
+                                                                        UploadDenied::async_write_to(
+                                                                            &mut peer_stream,
+                                                                            UploadDenied {
+                                                                                filename: response
+                                                                                    .filename,
+                                                                                reason: String::from(
+                                                                                    "File not shared.",
+                                                                                ),
+                                                                            },
+                                                                        )
+                                                                        .await,
+                                                                    );
+                                                                }
                                                             }
                                                         }
                                                         MessageType::Peer(44) => {
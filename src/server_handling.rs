@@ -1,5 +1,4 @@
-use std::collections::{HashMap, VecDeque};
-use std::net::Ipv4Addr;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
@@ -14,17 +13,69 @@ use tokio::time::sleep;
 use tokio::{sync::RwLock, task::JoinHandle};
 
 use crate::config::{Config, CONFIG_PATH};
-use crate::constants::{self, ConnectionTypes, DownloadStatus, Percentage};
+use crate::constants::{
+    self, ConnectionState, ConnectionTypes, DownloadStatus, Percentage, UserStatusCodes,
+};
 use crate::events::SLSKEvents;
 use crate::messages::{
-    CantConnectToPeer, ConnectToPeer, FileSearch, GetPeerAddress, JoinRoom, LeaveRoom, Login,
-    MessageTrait, RoomList, SayChatroom, SetWaitPort, SharedFileListRequest, SharedFoldersFiles,
-    UserStats, _ReceiveConnectToPeer, _SendFileSearch, _SendGetPeerAddress, _SendJoinRoom,
-    _SendLeaveRoom, _SendLogin, _SendRoomList, _SendSayChatroom,
+    AddPrivilegedUser, AdminMessage, AddThingIHate, AddThingILike, CantConnectToPeer,
+    CheckPrivileges, ConnectToPeer, FileAttribute, FileSearch, FolderContentsRequest,
+    GetPeerAddress, GetUserStats, GetUserStatus, GivePrivileges, GlobalRecommendations,
+    ItemRecommendations, ItemSimilarUsers, JoinRoom, LeaveRoom, Login, MessageAcked, MessageTrait,
+    MessageUser, PrivateRoomAddOperator, PrivateRoomAddUser, PrivateRoomAdded, PrivateRoomDismember,
+    PrivateRoomDisown, PrivateRoomOperatorAdded, PrivateRoomOwned, PrivateRoomRemoveOperator,
+    PrivateRoomRemoveUser, PrivateRoomRemoved, PrivateRoomToggle, PrivateRoomUsers, PrivilegedUsers,
+    Recommendation, Recommendations, RemoveThingIHate, RemoveThingILike, RoomList, RoomSearch,
+    RoomTickerAdd, RoomTickerRemove, RoomTickerSet, RoomTickerState, SayChatroom, SendUploadSpeed,
+    ServerPing, SetStatus, SetWaitPort, SharedFileListRequest, SharedFoldersFiles, SimilarUsers,
+    UserInfoRequest, UserInterests, UserJoinedRoom, UserLefRoom, UserSearch, UserStats, WatchUser,
+    WishListInterval, WishlistSearch, _ReceiveConnectToPeer, _SendCheckPrivileges, _SendFileSearch,
+    _SendGetPeerAddress, _SendGetUserStats, _SendGlobalRecommendations, _SendItemRecommendations,
+    _SendItemSimilarUsers, _SendJoinRoom, _SendLeaveRoom, _SendLogin, _SendMessageUser,
+    _SendRecommendations, _SendRoomList, _SendSayChatroom, _SendSimilarUsers, _SendUserInterests,
+    _SendWatchUser,
 };
+use crate::obfuscation::OBFUSCATION_ROTATED;
+use crate::peer_handling::PeerAddress;
 use crate::utils::get_code_and_bytes_from_readable;
 use crate::{messages::MessageType, SLSKExitCode};
 
+/// What we've asked the server for that it forgets when we disconnect,
+/// kept across connections so it can be asked for again when we log back in
+#[derive(Default)]
+pub(crate) struct SessionState {
+    /// Rooms we're in, and whether they're private
+    joined_rooms: HashMap<String, u32>,
+    watched_users: HashSet<String>,
+    away: bool,
+    /// Users who've been sent the auto-reply since we went away
+    auto_replied: HashSet<String>,
+}
+
+impl SessionState {
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// How often the server is pinged while we're logged in
+const PING_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Asks for the server to be pinged every so often, so it doesn't drop the connection when we're idle
+pub(crate) async fn start_server_ping_task(
+    logged_in: Arc<RwLock<bool>>,
+    write_queue: Sender<SLSKEvents>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            sleep(PING_INTERVAL).await;
+            if *logged_in.read().await {
+                let _ = write_queue.send(SLSKEvents::ServerPing);
+            }
+        }
+    })
+}
+
 /// Reads messages from the server and acts accordingly
 pub(crate) async fn start_server_read_task(
     quit: Arc<RwLock<bool>>,
@@ -34,7 +85,7 @@ pub(crate) async fn start_server_read_task(
     indirect_peers_list_writer: Worker<_ReceiveConnectToPeer>,
     server_username: Arc<RwLock<Option<String>>>,
     config_username: String,
-    user_info_map: Arc<Mutex<HashMap<String, (Ipv4Addr, u32)>>>,
+    user_info_map: Arc<Mutex<HashMap<String, PeerAddress>>>,
 ) -> JoinHandle<SLSKExitCode> {
     tokio::spawn(async move {
         loop {
@@ -64,22 +115,42 @@ pub(crate) async fn start_server_read_task(
                             return SLSKExitCode::LoginFail;
                         }
                         *logged_in.write().await = true;
+                        let _ = write_queue
+                            .send(SLSKEvents::ConnectionState(ConnectionState::LoggedIn));
                     }
                 }
                 MessageType::Server(3) => {
                     if let Some(response) = GetPeerAddress::from_stream(&mut bytes) {
-                        user_info_map
-                            .lock()
-                            .await
-                            .insert(response.username.clone(), (response.ip, response.port));
+                        let obfuscated_port = (response.obfuscation_type == OBFUSCATION_ROTATED
+                            && response.obfuscated_port != 0)
+                            .then_some(response.obfuscated_port.into());
+                        user_info_map.lock().await.insert(
+                            response.username.clone(),
+                            (response.ip, response.port, obfuscated_port),
+                        );
                     }
                     // println!("{:#?}", GetPeerAddress::from_stream(&mut bytes));
                 }
                 MessageType::Server(5) => {
-                    // println!("{:#?}", WatchUser::from_stream(&mut bytes));
+                    if let Some(response) = WatchUser::from_stream(&mut bytes) {
+                        // users that don't exist have no status
+                        if let Some(status) = response.status {
+                            let _ = write_queue.send(SLSKEvents::UserStatus {
+                                username: response.username,
+                                status,
+                                privileged: false,
+                            });
+                        }
+                    }
                 }
                 MessageType::Server(7) => {
-                    // println!("{:#?}", GetUserStatus::from_stream(&mut bytes));
+                    if let Some(response) = GetUserStatus::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::UserStatus {
+                            username: response.username,
+                            status: response.status,
+                            privileged: response.privileged,
+                        });
+                    }
                 }
                 MessageType::Server(13) => {
                     if let Some(response) = SayChatroom::from_stream(&mut bytes) {
@@ -97,8 +168,12 @@ pub(crate) async fn start_server_read_task(
                             stats: response
                                 .usernames
                                 .into_iter()
+                                .zip(response.statuses)
                                 .zip(response.stats)
-                                .collect::<Vec<(String, UserStats)>>(),
+                                .map(|((username, status), stats)| (username, status, stats))
+                                .collect(),
+                            owner: response.owner,
+                            operators: response.operators.unwrap_or_default(),
                         });
                     }
                 }
@@ -106,10 +181,27 @@ pub(crate) async fn start_server_read_task(
                     // println!("{:#?}", LeaveRoom::from_stream(&mut bytes));
                 }
                 MessageType::Server(16) => {
-                    // println!("{:#?}", UserJoinedRoom::from_stream(&mut bytes));
+                    if let Some(response) = UserJoinedRoom::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::UserJoinedRoom {
+                            room: response.room,
+                            username: response.username,
+                            status: response.status,
+                            stats: UserStats {
+                                avg_speeds: response.avg_speed,
+                                upload_num: response.upload_num,
+                                num_of_files: response.files,
+                                num_of_dirs: response.dirs,
+                            },
+                        });
+                    }
                 }
                 MessageType::Server(17) => {
-                    // println!("{:#?}", UserLefRoom::from_stream(&mut bytes));
+                    if let Some(response) = UserLefRoom::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::UserLeftRoom {
+                            room: response.room,
+                            username: response.username,
+                        });
+                    }
                 }
                 MessageType::Server(18) => {
                     // Ideally, this shouldn't happen if we receive the PeerInit message
@@ -128,18 +220,58 @@ pub(crate) async fn start_server_read_task(
                     }
                 }
                 MessageType::Server(22) => {
-                    // println!("{:#?}", MessageUser::from_stream(&mut bytes));
+                    if let Some(response) = MessageUser::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivateMessage {
+                            id: Some(response.id),
+                            username: response.username,
+                            message: response.message,
+                        });
+                    }
                 }
                 MessageType::Server(26) => {
-                    // println!("{:#?}", FileSearch::from_stream(&mut bytes));
+                    if let Some(response) = FileSearch::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::SearchRequest {
+                            username: response.username,
+                            token: response.token,
+                            query: response.search_query,
+                        });
+                    }
                 }
                 MessageType::Server(36) => {
-                    // println!("{:#?}", GetUserStats::from_stream(&mut bytes));
+                    if let Some(response) = GetUserStats::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::UserStats {
+                            username: response.username,
+                            stats: UserStats {
+                                avg_speeds: response.avg_speed,
+                                upload_num: response.upload_num,
+                                num_of_files: response.files,
+                                num_of_dirs: response.dirs,
+                            },
+                        });
+                    }
                 }
                 MessageType::Server(41) => {
-                    // println!("{:#?}", Relogged::from_stream(&mut bytes));
+                    let _ = write_queue.send(SLSKEvents::Relogged);
                     return SLSKExitCode::OtherLogin;
                 }
+                MessageType::Server(54) | MessageType::Server(56) => {
+                    if let Some(response) = Recommendations::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::Recommendations {
+                            global: code == MessageType::Server(56),
+                            recommendations: scored_items(response.recommendations),
+                            unrecommendations: scored_items(response.unrecommendations),
+                        });
+                    }
+                }
+                MessageType::Server(57) => {
+                    if let Some(response) = UserInterests::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::UserInterests {
+                            username: response.username,
+                            likes: response.likes,
+                            hates: response.hates,
+                        });
+                    }
+                }
                 MessageType::Server(64) => {
                     if let Some(room_list) = RoomList::from_stream(&mut bytes) {
                         let rooms_and_num_of_users = room_list
@@ -147,16 +279,39 @@ pub(crate) async fn start_server_read_task(
                             .into_iter()
                             .zip(room_list.num_of_users)
                             .collect();
+                        let private_rooms_and_num_of_users = room_list
+                            .owned_priv_rooms
+                            .iter()
+                            .cloned()
+                            .zip(room_list.owned_priv_num_of_users)
+                            .chain(
+                                room_list
+                                    .non_owned_priv_rooms
+                                    .into_iter()
+                                    .zip(room_list.non_owned_priv_num_of_users),
+                            )
+                            .collect();
                         let _ = write_queue.send(SLSKEvents::RoomList {
                             rooms_and_num_of_users,
+                            private_rooms_and_num_of_users,
+                            owned_rooms: room_list.owned_priv_rooms,
+                            operated_rooms: room_list.operated_priv_rooms,
                         });
                     }
                 }
                 MessageType::Server(66) => {
-                    // println!("{:#?}", AdminMessage::from_stream(&mut bytes));
+                    if let Some(response) = AdminMessage::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::AdminMessage {
+                            message: response.message,
+                        });
+                    }
                 }
                 MessageType::Server(69) => {
-                    // println!("{:#?}", PrivilegedUsers::from_stream(&mut bytes));
+                    if let Some(response) = PrivilegedUsers::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivilegedUsers {
+                            usernames: response.usernames,
+                        });
+                    }
                 }
                 MessageType::Server(83) => {
                     // println!("{:#?}", ParentMinSpeed::from_stream(&mut bytes));
@@ -164,8 +319,19 @@ pub(crate) async fn start_server_read_task(
                 MessageType::Server(84) => {
                     // println!("{:#?}", ParentSpeedRatio::from_stream(&mut bytes));
                 }
+                MessageType::Server(91) => {
+                    if let Some(response) = AddPrivilegedUser::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivilegedUserAdded {
+                            username: response.username,
+                        });
+                    }
+                }
                 MessageType::Server(92) => {
-                    // println!("{:#?}", CheckPrivileges::from_stream(&mut bytes));
+                    if let Some(response) = CheckPrivileges::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivilegesLeft {
+                            seconds: response.time_left_seconds,
+                        });
+                    }
                 }
                 MessageType::Server(93) => {
                     // println!("{:#?}", EmbeddedMessage::from_stream(&mut bytes));
@@ -174,55 +340,142 @@ pub(crate) async fn start_server_read_task(
                     // println!("{:#?}", PossibleParents::from_stream(&mut bytes));
                 }
                 MessageType::Server(104) => {
-                    // println!("{:#?}", WishListInterval::from_stream(&mut bytes));
+                    if let Some(response) = WishListInterval::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::WishlistInterval {
+                            seconds: response.interval,
+                        });
+                    }
+                }
+                MessageType::Server(110) => {
+                    if let Some(response) = SimilarUsers::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::SimilarUsers {
+                            users: response
+                                .users
+                                .into_iter()
+                                .map(|user| (user.username, user.rating))
+                                .collect(),
+                        });
+                    }
+                }
+                MessageType::Server(111) => {
+                    if let Some(response) = ItemRecommendations::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::ItemRecommendations {
+                            item: response.item,
+                            recommendations: scored_items(response.recommendations),
+                        });
+                    }
+                }
+                MessageType::Server(112) => {
+                    if let Some(response) = ItemSimilarUsers::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::ItemSimilarUsers {
+                            item: response.item,
+                            usernames: response.usernames,
+                        });
+                    }
                 }
                 MessageType::Server(113) => {
-                    // println!("{:#?}", RoomTickerState::from_stream(&mut bytes));
+                    if let Some(response) = RoomTickerState::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomTickers {
+                            room: response.room,
+                            tickers: response
+                                .tickers
+                                .into_iter()
+                                .map(|ticker| (ticker.username, ticker.ticker))
+                                .collect(),
+                        });
+                    }
                 }
                 MessageType::Server(114) => {
-                    // println!("{:#?}", RoomTickerAdd::from_stream(&mut bytes));
+                    if let Some(response) = RoomTickerAdd::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomTickerChanged {
+                            room: response.room,
+                            username: response.username,
+                            ticker: Some(response.ticker),
+                        });
+                    }
                 }
                 MessageType::Server(115) => {
-                    // println!("{:#?}", RoomTickerRemove::from_stream(&mut bytes));
+                    if let Some(response) = RoomTickerRemove::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomTickerChanged {
+                            room: response.room,
+                            username: response.username,
+                            ticker: None,
+                        });
+                    }
                 }
                 MessageType::Server(130) => {
                     // println!("{:#?}", ResetDistributed::from_stream(&mut bytes));
                 }
                 MessageType::Server(133) => {
-                    // println!("{:#?}", PrivateRoomUsers::from_stream(&mut bytes));
-                }
-                MessageType::Server(134) => {
-                    // println!("{:#?}", PrivateRoomAddUser::from_stream(&mut bytes));
+                    if let Some(response) = PrivateRoomUsers::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomMembers {
+                            room: response.room,
+                            members: response.users,
+                        });
+                    }
                 }
-                MessageType::Server(135) => {
-                    // println!("{:#?}", PrivateRoomRemoveUser::from_stream(&mut bytes));
+                MessageType::Server(134) | MessageType::Server(135) => {
+                    // both messages are a room and a username
+                    if let Some(response) = PrivateRoomAddUser::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomMemberChanged {
+                            room: response.room,
+                            username: response.username,
+                            added: code == MessageType::Server(134),
+                        });
+                    }
                 }
                 MessageType::Server(139) => {
-                    // println!("{:#?}", PrivateRoomAdded::from_stream(&mut bytes));
+                    if let Some(response) = PrivateRoomAdded::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivateRoomAccess {
+                            room: response.room,
+                            added: true,
+                        });
+                    }
                 }
                 MessageType::Server(140) => {
-                    // println!("{:#?}", PrivateRoomRemoved::from_stream(&mut bytes));
+                    if let Some(response) = PrivateRoomRemoved::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivateRoomAccess {
+                            room: response.room,
+                            added: false,
+                        });
+                    }
                 }
                 MessageType::Server(141) => {
-                    // println!("{:#?}", PrivateRoomToggle::from_stream(&mut bytes));
+                    if let Some(response) = PrivateRoomToggle::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::PrivateRoomInvitations {
+                            enabled: response.enable,
+                        });
+                    }
                 }
                 MessageType::Server(142) => {
                     // println!("{:#?}", ChangePassword::from_stream(&mut bytes));
                 }
-                MessageType::Server(143) => {
-                    // println!("{:#?}", PrivateRoomAddOperator::from_stream(&mut bytes));
-                }
-                MessageType::Server(144) => {
-                    // println!("{:#?}", PrivateRoomRemoveOperator::from_stream(&mut bytes));
-                }
-                MessageType::Server(145) => {
-                    // println!("{:#?}", PrivateRoomOperatorAdded::from_stream(&mut bytes));
+                MessageType::Server(143) | MessageType::Server(144) => {
+                    if let Some(response) = PrivateRoomAddOperator::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomOperatorChanged {
+                            room: response.room,
+                            username: response.username,
+                            added: code == MessageType::Server(143),
+                        });
+                    }
                 }
-                MessageType::Server(146) => {
-                    // println!("{:#?}", PrivateRoomOperatorRemoved::from_stream(&mut bytes));
+                MessageType::Server(145) | MessageType::Server(146) => {
+                    // we were made an operator, or stopped being one
+                    if let Some(response) = PrivateRoomOperatorAdded::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomOperatorChanged {
+                            room: response.room,
+                            username: server_username.read().await.clone().unwrap_or_default(),
+                            added: code == MessageType::Server(145),
+                        });
+                    }
                 }
                 MessageType::Server(148) => {
-                    // println!("{:#?}", PrivateRoomOwned::from_stream(&mut bytes));
+                    if let Some(response) = PrivateRoomOwned::from_stream(&mut bytes) {
+                        let _ = write_queue.send(SLSKEvents::RoomOperators {
+                            room: response.room,
+                            operators: response.operators,
+                        });
+                    }
                 }
                 MessageType::Server(1001) => {
                     println!("cant connect to peer {bytes:?} ");
@@ -237,6 +490,13 @@ pub(crate) async fn start_server_read_task(
     })
 }
 
+fn scored_items(recommendations: Vec<Recommendation>) -> Vec<(String, i32)> {
+    recommendations
+        .into_iter()
+        .map(|recommendation| (recommendation.item, recommendation.score))
+        .collect()
+}
+
 /// Receives events and writes to the server accordingly
 pub(crate) async fn start_server_write_task(
     quit_write: Arc<RwLock<bool>>,
@@ -245,8 +505,9 @@ pub(crate) async fn start_server_write_task(
     mut writer: OwnedWriteHalf,
     my_username: Arc<RwLock<Option<String>>>,
     my_port: u32,
+    my_obfuscated_port: Option<u32>,
     token_message_map: Arc<Mutex<HashMap<u32, VecDeque<Vec<u8>>>>>,
-    writer_user_info_map: Arc<Mutex<HashMap<String, (Ipv4Addr, u32)>>>,
+    writer_user_info_map: Arc<Mutex<HashMap<String, PeerAddress>>>,
     writer_write_queue: Sender<SLSKEvents>,
     prompted_peers_list_writer: Worker<(String, u32, ConnectionTypes)>,
     download_filename_map: Arc<
@@ -257,10 +518,13 @@ pub(crate) async fn start_server_write_task(
                     Arc<RwLock<DownloadStatus>>,
                     Arc<RwLock<Percentage>>,
                     Option<bool>,
+                    Option<Vec<FileAttribute>>,
                 )>,
             >,
         >,
     >,
+    session: Arc<RwLock<SessionState>>,
+    auto_login: bool,
 ) -> JoinHandle<SLSKExitCode> {
     tokio::spawn({
         let my_username = Arc::clone(&my_username);
@@ -269,7 +533,7 @@ pub(crate) async fn start_server_write_task(
                 let config = config.read().await;
                 let username = &config.user.name;
                 let password = &config.user.password;
-                if config.server.auto_connect & !username.is_empty() & !password.is_empty() {
+                if auto_login & !username.is_empty() & !password.is_empty() {
                     let login_info = _SendLogin::new(username.to_owned(), password.to_owned());
                     let _ = block_on(Login::async_write_to(&mut writer, login_info).await);
                 }
@@ -299,6 +563,8 @@ pub(crate) async fn start_server_write_task(
                                 SLSKExitCode::Restart
                             };
                         }
+                        SLSKEvents::Relogged => (),
+                        SLSKEvents::AdminMessage { .. } => (),
                         SLSKEvents::LoginResult { success, .. } => {
                             if success {
                                 let _ = block_on(
@@ -306,8 +572,11 @@ pub(crate) async fn start_server_write_task(
                                         &mut writer,
                                         SetWaitPort {
                                             port: my_port,
-                                            _unknown: 0,
-                                            obfuscated_port: my_port, // TODO: Implement SetWaitPort obfuscated_port
+                                            obfuscation_type: match my_obfuscated_port {
+                                                Some(_) => OBFUSCATION_ROTATED,
+                                                None => 0,
+                                            },
+                                            obfuscated_port: my_obfuscated_port.unwrap_or_default(),
                                         },
                                     )
                                     .await,
@@ -330,14 +599,115 @@ pub(crate) async fn start_server_write_task(
                                     )
                                     .await,
                                 );
+                                // what the server says about us, as other users see it
+                                if let Some(username) = my_username.read().await.clone() {
+                                    let _ = block_on(
+                                        GetUserStats::async_write_to(
+                                            &mut writer,
+                                            _SendGetUserStats { username },
+                                        )
+                                        .await,
+                                    );
+                                }
 
                                 let _ = block_on(
                                     RoomList::async_write_to(&mut writer, _SendRoomList {}).await,
                                 );
+                                let _ = block_on(
+                                    CheckPrivileges::async_write_to(
+                                        &mut writer,
+                                        _SendCheckPrivileges {},
+                                    )
+                                    .await,
+                                );
+
+                                // the server forgets our interests when we log out
+                                let (likes, hates) = {
+                                    let interests = &config.read().await.interests;
+                                    (interests.likes.clone(), interests.hates.clone())
+                                };
+                                for item in likes {
+                                    let _ = block_on(
+                                        AddThingILike::async_write_to(
+                                            &mut writer,
+                                            AddThingILike { item },
+                                        )
+                                        .await,
+                                    );
+                                }
+                                for item in hates {
+                                    let _ = block_on(
+                                        AddThingIHate::async_write_to(
+                                            &mut writer,
+                                            AddThingIHate { item },
+                                        )
+                                        .await,
+                                    );
+                                }
+
+                                let enable = config.read().await.rooms.private_room_invitations;
+                                let _ = block_on(
+                                    PrivateRoomToggle::async_write_to(
+                                        &mut writer,
+                                        PrivateRoomToggle { enable },
+                                    )
+                                    .await,
+                                );
+
+                                // rejoin the rooms and rewatch the users from before we were disconnected
+                                let (joined_rooms, watched_users, away) = {
+                                    let session = session.read().await;
+                                    (
+                                        session.joined_rooms.clone(),
+                                        session.watched_users.clone(),
+                                        session.away,
+                                    )
+                                };
+                                if away {
+                                    let _ = block_on(
+                                        SetStatus::async_write_to(
+                                            &mut writer,
+                                            SetStatus {
+                                                status: UserStatusCodes::Away,
+                                            },
+                                        )
+                                        .await,
+                                    );
+                                }
+                                for (room, private) in joined_rooms {
+                                    let _ = block_on(
+                                        JoinRoom::async_write_to(
+                                            &mut writer,
+                                            _SendJoinRoom { room, private },
+                                        )
+                                        .await,
+                                    );
+                                }
+                                let buddies = config.read().await.users.buddies.clone();
+                                for username in watched_users.into_iter().chain(buddies) {
+                                    let _ = block_on(
+                                        WatchUser::async_write_to(
+                                            &mut writer,
+                                            _SendWatchUser { username },
+                                        )
+                                        .await,
+                                    );
+                                }
                             }
                         }
+                        SLSKEvents::ConnectionState(_) => (),
+                        SLSKEvents::ServerPing => {
+                            let _ = block_on(
+                                ServerPing::async_write_to(&mut writer, ServerPing {}).await,
+                            );
+                        }
                         SLSKEvents::RoomList { .. } => (),
                         SLSKEvents::JoinRoom { room, private } => {
+                            session
+                                .write()
+                                .await
+                                .joined_rooms
+                                .insert(room.clone(), private);
                             let _ = block_on(
                                 JoinRoom::async_write_to(
                                     &mut writer,
@@ -347,12 +717,40 @@ pub(crate) async fn start_server_write_task(
                             );
                         }
                         SLSKEvents::LeaveRoom { room } => {
+                            session.write().await.joined_rooms.remove(&room);
                             let _ = block_on(
                                 LeaveRoom::async_write_to(&mut writer, _SendLeaveRoom { room })
                                     .await,
                             );
                         }
                         SLSKEvents::UpdateRoom { .. } => (),
+                        SLSKEvents::UserJoinedRoom { .. } => (),
+                        SLSKEvents::UserLeftRoom { .. } => (),
+                        SLSKEvents::WatchUser { username } => {
+                            session.write().await.watched_users.insert(username.clone());
+                            let _ = block_on(
+                                WatchUser::async_write_to(&mut writer, _SendWatchUser { username })
+                                    .await,
+                            );
+                        }
+                        SLSKEvents::SetStatus { status } => {
+                            {
+                                let mut session = session.write().await;
+                                session.away = status == UserStatusCodes::Away;
+                                if !session.away {
+                                    session.auto_replied.clear();
+                                }
+                            }
+                            let _ = block_on(
+                                SetStatus::async_write_to(&mut writer, SetStatus { status }).await,
+                            );
+                        }
+                        SLSKEvents::UserStatus { .. } => (),
+                        SLSKEvents::SetShowJoinsAndLeaves { enabled } => {
+                            let mut config = config.write().await;
+                            config.rooms.show_joins_and_leaves = enabled;
+                            config.write_to_file(Path::new(CONFIG_PATH), true);
+                        }
                         SLSKEvents::ChatroomMessage {
                             room,
                             username,
@@ -382,6 +780,92 @@ pub(crate) async fn start_server_write_task(
                                 .await,
                             );
                         }
+                        SLSKEvents::CheckPrivileges => {
+                            let _ = block_on(
+                                CheckPrivileges::async_write_to(
+                                    &mut writer,
+                                    _SendCheckPrivileges {},
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::GivePrivileges { username, days } => {
+                            let _ = block_on(
+                                GivePrivileges::async_write_to(
+                                    &mut writer,
+                                    GivePrivileges { username, days },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::PrivilegesLeft { .. } => (),
+                        SLSKEvents::UserStats { .. } => (),
+                        SLSKEvents::PrivilegedUsers { .. } => (),
+                        SLSKEvents::PrivilegedUserAdded { .. } => (),
+                        SLSKEvents::UserSearch {
+                            username,
+                            query,
+                            token,
+                        } => {
+                            let _ = block_on(
+                                UserSearch::async_write_to(
+                                    &mut writer,
+                                    UserSearch {
+                                        username,
+                                        token,
+                                        search_query: query,
+                                    },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::RoomSearch { room, query, token } => {
+                            let _ = block_on(
+                                RoomSearch::async_write_to(
+                                    &mut writer,
+                                    RoomSearch {
+                                        room,
+                                        token,
+                                        search_query: query,
+                                    },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::BuddySearch { query, token } => {
+                            let buddies = config.read().await.users.buddies.clone();
+                            for username in buddies {
+                                let _ = block_on(
+                                    UserSearch::async_write_to(
+                                        &mut writer,
+                                        UserSearch {
+                                            username,
+                                            token,
+                                            search_query: query.clone(),
+                                        },
+                                    )
+                                    .await,
+                                );
+                            }
+                        }
+                        SLSKEvents::WishlistInterval { .. } => (),
+                        SLSKEvents::WishlistSearch { query, token } => {
+                            let _ = block_on(
+                                WishlistSearch::async_write_to(
+                                    &mut writer,
+                                    WishlistSearch {
+                                        token,
+                                        search_query: query,
+                                    },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::SetWishlist { queries } => {
+                            let mut config = config.write().await;
+                            config.wishlist.queries = queries;
+                            config.write_to_file(Path::new(CONFIG_PATH), true);
+                        }
                         SLSKEvents::QueueMessage {
                             token,
                             message_bytes,
@@ -420,21 +904,346 @@ pub(crate) async fn start_server_write_task(
                             filename,
                             status,
                             percentage,
+                            attributes,
                         } => {
+                            let verify = config.read().await.downloads.verify;
                             download_filename_map
                                 .lock()
                                 .await
                                 .entry(filename)
                                 .or_default()
-                                .push_back((status, percentage, None));
+                                .push_back((status, percentage, None, verify.then_some(attributes)));
                         }
                         SLSKEvents::UpdateDownloads { files, from_all } => {
+                            let verify = config.read().await.downloads.verify;
                             let mut download_filename_map = download_filename_map.lock().await;
-                            for (filename, status, percentage) in files {
-                                download_filename_map
-                                    .entry(filename)
-                                    .or_default()
-                                    .push_back((status, percentage, Some(from_all)));
+                            for (filename, status, percentage, attributes) in files {
+                                download_filename_map.entry(filename).or_default().push_back((
+                                    status,
+                                    percentage,
+                                    Some(from_all),
+                                    verify.then_some(attributes),
+                                ));
+                            }
+                        }
+                        SLSKEvents::VerifyFailed { .. } => (),
+                        SLSKEvents::UploadQueued { .. } => (),
+                        SLSKEvents::NewUpload { .. } => (),
+                        SLSKEvents::UploadResponse { .. } => (),
+                        SLSKEvents::UploadFinished { speed, .. } => {
+                            // the server averages it into the speed everyone sees in our stats
+                            if let Some(speed) = speed {
+                                let _ = block_on(
+                                    SendUploadSpeed::async_write_to(
+                                        &mut writer,
+                                        SendUploadSpeed { speed },
+                                    )
+                                    .await,
+                                );
+                                if let Some(username) = my_username.read().await.clone() {
+                                    let _ = block_on(
+                                        GetUserStats::async_write_to(
+                                            &mut writer,
+                                            _SendGetUserStats { username },
+                                        )
+                                        .await,
+                                    );
+                                }
+                            }
+                        }
+                        SLSKEvents::AddShare { .. } => (),
+                        SLSKEvents::RemoveShare { .. } => (),
+                        SLSKEvents::RenameShare { .. } => (),
+                        SLSKEvents::SetShareBuddyOnly { .. } => (),
+                        SLSKEvents::RescanShare { .. } => (),
+                        SLSKEvents::PreviewShares => (),
+                        SLSKEvents::SharesPreview { .. } => (),
+                        SLSKEvents::PreviewSearch { .. } => (),
+                        SLSKEvents::SearchRequest { .. } => (),
+                        SLSKEvents::SearchPreview { .. } => (),
+                        SLSKEvents::FindLocalCopies { .. } => (),
+                        SLSKEvents::LocalCopies { .. } => (),
+                        SLSKEvents::CheckLocalCopy { .. } => (),
+                        SLSKEvents::LocalCopyFound { .. } => (),
+                        SLSKEvents::PreviewImport { .. } => (),
+                        SLSKEvents::ImportPreview { .. } => (),
+                        SLSKEvents::ApplyImport { .. } => (),
+                        SLSKEvents::UserShares { .. } => (),
+                        SLSKEvents::AddInterest { item, hate } => {
+                            {
+                                let mut config = config.write().await;
+                                let interests = if hate {
+                                    &mut config.interests.hates
+                                } else {
+                                    &mut config.interests.likes
+                                };
+                                if !interests.contains(&item) {
+                                    interests.push(item.clone());
+                                }
+                                config.write_to_file(Path::new(CONFIG_PATH), true);
+                            }
+                            let _ = block_on(if hate {
+                                AddThingIHate::async_write_to(&mut writer, AddThingIHate { item })
+                                    .await
+                            } else {
+                                AddThingILike::async_write_to(&mut writer, AddThingILike { item })
+                                    .await
+                            });
+                        }
+                        SLSKEvents::RemoveInterest { item, hate } => {
+                            {
+                                let mut config = config.write().await;
+                                if hate {
+                                    config.interests.hates.retain(|hated| *hated != item);
+                                } else {
+                                    config.interests.likes.retain(|liked| *liked != item);
+                                }
+                                config.write_to_file(Path::new(CONFIG_PATH), true);
+                            }
+                            let _ = block_on(if hate {
+                                RemoveThingIHate::async_write_to(
+                                    &mut writer,
+                                    RemoveThingIHate { item },
+                                )
+                                .await
+                            } else {
+                                RemoveThingILike::async_write_to(
+                                    &mut writer,
+                                    RemoveThingILike { item },
+                                )
+                                .await
+                            });
+                        }
+                        SLSKEvents::GetRecommendations { global } => {
+                            let _ = block_on(if global {
+                                GlobalRecommendations::async_write_to(
+                                    &mut writer,
+                                    _SendGlobalRecommendations {},
+                                )
+                                .await
+                            } else {
+                                Recommendations::async_write_to(&mut writer, _SendRecommendations {})
+                                    .await
+                            });
+                        }
+                        SLSKEvents::Recommendations { .. } => (),
+                        SLSKEvents::GetSimilarUsers => {
+                            let _ = block_on(
+                                SimilarUsers::async_write_to(&mut writer, _SendSimilarUsers {})
+                                    .await,
+                            );
+                        }
+                        SLSKEvents::SimilarUsers { .. } => (),
+                        SLSKEvents::GetItemRecommendations { item } => {
+                            let _ = block_on(
+                                ItemRecommendations::async_write_to(
+                                    &mut writer,
+                                    _SendItemRecommendations { item: item.clone() },
+                                )
+                                .await,
+                            );
+                            let _ = block_on(
+                                ItemSimilarUsers::async_write_to(
+                                    &mut writer,
+                                    _SendItemSimilarUsers { item },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::ItemRecommendations { .. } => (),
+                        SLSKEvents::ItemSimilarUsers { .. } => (),
+                        SLSKEvents::GetUserInfo { username } => {
+                            let _ = block_on(
+                                UserInterests::async_write_to(
+                                    &mut writer,
+                                    _SendUserInterests {
+                                        username: username.clone(),
+                                    },
+                                )
+                                .await,
+                            );
+                            let token = rand::random();
+                            writer_write_queue
+                                .send(SLSKEvents::QueueMessage {
+                                    token,
+                                    message_bytes: UserInfoRequest::to_bytes(UserInfoRequest {}),
+                                })
+                                .unwrap();
+                            writer_write_queue
+                                .send(SLSKEvents::Connect {
+                                    username,
+                                    token,
+                                    connection_type: ConnectionTypes::PeerToPeer,
+                                })
+                                .unwrap();
+                        }
+                        SLSKEvents::UserInfo { .. } => (),
+                        SLSKEvents::GetFolderContents { username, folder } => {
+                            let token = rand::random();
+                            writer_write_queue
+                                .send(SLSKEvents::QueueMessage {
+                                    token,
+                                    message_bytes: FolderContentsRequest::to_bytes(
+                                        FolderContentsRequest { token, folder },
+                                    ),
+                                })
+                                .unwrap();
+                            writer_write_queue
+                                .send(SLSKEvents::Connect {
+                                    username,
+                                    token,
+                                    connection_type: ConnectionTypes::PeerToPeer,
+                                })
+                                .unwrap();
+                        }
+                        SLSKEvents::FolderContents { .. } => (),
+                        SLSKEvents::UserInterests { .. } => (),
+                        SLSKEvents::OpenPrivateChat { .. } => (),
+                        SLSKEvents::PrivateMessage {
+                            id,
+                            username,
+                            message,
+                        } => match id {
+                            Some(message_id) => {
+                                let _ = block_on(
+                                    MessageAcked::async_write_to(
+                                        &mut writer,
+                                        MessageAcked { message_id },
+                                    )
+                                    .await,
+                                );
+
+                                let auto_reply = config.read().await.away.auto_reply.clone();
+                                let mut session = session.write().await;
+                                if session.away
+                                    && !auto_reply.is_empty()
+                                    && session.auto_replied.insert(username.clone())
+                                {
+                                    // sent as if we'd typed it, so it's shown in the chat too
+                                    let _ = writer_write_queue.send(SLSKEvents::PrivateMessage {
+                                        id: None,
+                                        username,
+                                        message: auto_reply,
+                                    });
+                                }
+                            }
+                            None => {
+                                let _ = block_on(
+                                    MessageUser::async_write_to(
+                                        &mut writer,
+                                        _SendMessageUser { username, message },
+                                    )
+                                    .await,
+                                );
+                            }
+                        },
+                        SLSKEvents::AddRoomMember { room, username } => {
+                            let _ = block_on(
+                                PrivateRoomAddUser::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomAddUser { room, username },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::RemoveRoomMember { room, username } => {
+                            let _ = block_on(
+                                PrivateRoomRemoveUser::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomRemoveUser { room, username },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::AddRoomOperator { room, username } => {
+                            let _ = block_on(
+                                PrivateRoomAddOperator::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomAddOperator { room, username },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::RemoveRoomOperator { room, username } => {
+                            let _ = block_on(
+                                PrivateRoomRemoveOperator::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomRemoveOperator { room, username },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::DisownRoom { room } => {
+                            let _ = block_on(
+                                PrivateRoomDisown::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomDisown { room },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::DismemberRoom { room } => {
+                            let _ = block_on(
+                                PrivateRoomDismember::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomDismember { room },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::SetPrivateRoomInvitations { enabled } => {
+                            {
+                                let mut config = config.write().await;
+                                config.rooms.private_room_invitations = enabled;
+                                config.write_to_file(Path::new(CONFIG_PATH), true);
+                            }
+                            let _ = block_on(
+                                PrivateRoomToggle::async_write_to(
+                                    &mut writer,
+                                    PrivateRoomToggle { enable: enabled },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::RoomMembers { .. } => (),
+                        SLSKEvents::RoomOperators { .. } => (),
+                        SLSKEvents::RoomMemberChanged { .. } => (),
+                        SLSKEvents::RoomOperatorChanged { .. } => (),
+                        SLSKEvents::PrivateRoomAccess { .. } => (),
+                        SLSKEvents::PrivateRoomInvitations { .. } => (),
+                        SLSKEvents::RoomTickers { .. } => (),
+                        SLSKEvents::RoomTickerChanged { .. } => (),
+                        SLSKEvents::SetRoomTicker { room, ticker } => {
+                            let _ = block_on(
+                                RoomTickerSet::async_write_to(
+                                    &mut writer,
+                                    RoomTickerSet { room, ticker },
+                                )
+                                .await,
+                            );
+                        }
+                        SLSKEvents::SharesUpdated { shares, .. } => {
+                            // the server only cares about our shares once we've logged in
+                            if my_username.read().await.is_some() {
+                                let _ = block_on(
+                                    SharedFoldersFiles::async_write_to(
+                                        &mut writer,
+                                        SharedFoldersFiles {
+                                            dirs: shares.iter().map(|share| share.folders).sum(),
+                                            files: shares.iter().map(|share| share.files).sum(),
+                                        },
+                                    )
+                                    .await,
+                                );
+                                if let Some(username) = my_username.read().await.clone() {
+                                    let _ = block_on(
+                                        GetUserStats::async_write_to(
+                                            &mut writer,
+                                            _SendGetUserStats { username },
+                                        )
+                                        .await,
+                                    );
+                                }
                             }
                         }
                         SLSKEvents::BrowseUser { username } => {
@@ -0,0 +1,271 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+use md5::{Digest, Md5};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::{
+    sync::{Notify, RwLock},
+    task::JoinHandle,
+};
+
+use crate::analysis::{analyse, Verdict};
+
+use crate::config::{Config, CONFIG_PATH};
+use crate::events::SLSKEvents;
+use crate::messages::{MessageTrait, SharedFileListResponse};
+use crate::nicotine::NicotineImport;
+use crate::sql::{DiskIndex, ShareStats};
+use crate::utils::md5_digest;
+use crate::CHUNK_SIZE;
+
+/// Indexes the shares and applies any changes the user makes to them.
+/// Changes are saved to the config, and the cached shares message is rebuilt after each one.
+pub(crate) async fn start_shares_task(
+    config: Arc<RwLock<Config>>,
+    mut read_queue: Receiver<SLSKEvents>,
+    write_queue: Sender<SLSKEvents>,
+    shares_message: Arc<RwLock<Option<Vec<u8>>>>,
+) -> JoinHandle<()> {
+    let analysis_needed = Arc::new(Notify::new());
+    start_analysis_task(config.clone(), write_queue.clone(), analysis_needed.clone());
+    let hashing_needed = Arc::new(Notify::new());
+    start_hashing_task(config.clone(), hashing_needed.clone());
+
+    tokio::spawn(async move {
+        // update the file index in the background
+        // this stops the client freezing for ages while the files are being indexed for the first time
+        let mut index = config.read().await.index.clone();
+        let error = index.reindex_all().await.err().map(|e| e.to_string());
+        refresh_shares(&index, &shares_message, &write_queue, error).await;
+        analysis_needed.notify_one();
+        hashing_needed.notify_one();
+
+        loop {
+            let event = match read_queue.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+
+            // the index is cloned so the config isn't locked while folders are being indexed
+            let mut index = config.read().await.index.clone();
+            // previews only read the index, so nothing needs saving afterwards
+            match event {
+                SLSKEvents::PreviewShares => {
+                    if let Ok(file_list) = index.file_list().await {
+                        let mut stats = ShareStats::new(&file_list);
+                        stats.duplicates = index.duplicates().await.unwrap_or_default();
+                        stats.suspicious = index.suspicious_files().await.unwrap_or_default();
+                        stats.image_tracks = index.image_tracks().await.unwrap_or_default();
+                        let _ = write_queue.send(SLSKEvents::SharesPreview { file_list, stats });
+                    }
+                    continue;
+                }
+                SLSKEvents::FindLocalCopies { token, files } => {
+                    let mut local_copies = Vec::new();
+                    for (filename, size) in files {
+                        let local_paths = index.local_copies(size, None).await.unwrap_or_default();
+                        if !local_paths.is_empty() {
+                            local_copies.push((filename, local_paths));
+                        }
+                    }
+                    let _ = write_queue.send(SLSKEvents::LocalCopies {
+                        token,
+                        files: local_copies,
+                    });
+                    continue;
+                }
+                SLSKEvents::CheckLocalCopy {
+                    username,
+                    filename,
+                    size,
+                    head_hash,
+                } => {
+                    let local_paths = index
+                        .local_copies(size, Some(&head_hash))
+                        .await
+                        .unwrap_or_default();
+                    if !local_paths.is_empty() {
+                        let _ = write_queue.send(SLSKEvents::LocalCopyFound {
+                            username,
+                            filename,
+                            local_paths,
+                        });
+                    }
+                    continue;
+                }
+                SLSKEvents::PreviewImport { path } => {
+                    let preview = match NicotineImport::read(&path) {
+                        Ok(import) => {
+                            let changes = import.describe_changes(&*config.read().await);
+                            Ok((import, changes))
+                        }
+                        Err(e) => Err(e.to_string()),
+                    };
+                    let _ = write_queue.send(SLSKEvents::ImportPreview { path, preview });
+                    continue;
+                }
+                SLSKEvents::PreviewSearch { query } => {
+                    let (files, private_files) = index.search(&query).await.unwrap_or_default();
+                    let _ = write_queue.send(SLSKEvents::SearchPreview {
+                        query,
+                        files: files.into_iter().map(|(file, _)| file).collect(),
+                        private_files: private_files.into_iter().map(|(file, _)| file).collect(),
+                    });
+                    continue;
+                }
+                _ => (),
+            }
+            let result = match event {
+                SLSKEvents::AddShare {
+                    path,
+                    alias,
+                    buddy_only,
+                } => index.index_folder(path, &alias, buddy_only).await,
+                SLSKEvents::RemoveShare { alias } => index.remove_folder(&alias).await,
+                SLSKEvents::RenameShare { alias, new_alias } => {
+                    index.rename_folder(&alias, &new_alias).await
+                }
+                SLSKEvents::SetShareBuddyOnly { alias, buddy_only } => {
+                    index.set_buddy_only(&alias, buddy_only).await
+                }
+                SLSKEvents::RescanShare { alias } => index.rescan_folder(&alias).await,
+                SLSKEvents::ApplyImport { import } => {
+                    import.apply(&mut *config.write().await);
+                    let mut errors = Vec::new();
+                    for (alias, path, buddy_only) in import.new_shares(&index) {
+                        if let Err(e) = index.index_folder(&path, &alias, buddy_only).await {
+                            errors.push(format!("{}: {e}", path.to_string_lossy()));
+                        }
+                    }
+                    if errors.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(errors.join(", ").into())
+                    }
+                }
+                _ => continue,
+            };
+            let error = result.err().map(|e| e.to_string());
+
+            {
+                let mut config = config.write().await;
+                config.index = index.clone();
+                config.write_to_file(Path::new(CONFIG_PATH), true);
+            }
+            refresh_shares(&index, &shares_message, &write_queue, error).await;
+            analysis_needed.notify_one();
+            hashing_needed.notify_one();
+        }
+    })
+}
+
+/// Analyses newly indexed lossless files one at a time whenever the shares change,
+/// so the Settings window can show how many look transcoded
+fn start_analysis_task(
+    config: Arc<RwLock<Config>>,
+    write_queue: Sender<SLSKEvents>,
+    analysis_needed: Arc<Notify>,
+) {
+    tokio::spawn(async move {
+        loop {
+            analysis_needed.notified().await;
+            let index = config.read().await.index.clone();
+            let mut found_suspicious = false;
+
+            for (file_id, path) in index.pending_analysis().await.unwrap_or_default() {
+                let verdict = tokio::task::spawn_blocking(move || analyse(&path))
+                    .await
+                    .ok()
+                    .and_then(|verdict| verdict.ok())
+                    // files that can't be decoded aren't retried until they change
+                    .unwrap_or(Verdict::Inconclusive);
+                found_suspicious |= matches!(verdict, Verdict::Suspicious { .. });
+                // the file may have been removed from the index in the meantime
+                let _ = index.store_analysis(file_id, verdict).await;
+            }
+
+            if found_suspicious {
+                let _ = write_queue.send(SLSKEvents::SharesUpdated {
+                    shares: index.shared_roots().await.unwrap_or_default(),
+                    error: None,
+                });
+            }
+        }
+    });
+}
+
+/// Hashes shared files one at a time whenever the shares change, if it's enabled.
+/// Hashes are kept until a file's size or modified time changes, so only new and changed files are read.
+fn start_hashing_task(config: Arc<RwLock<Config>>, hashing_needed: Arc<Notify>) {
+    tokio::spawn(async move {
+        loop {
+            hashing_needed.notified().await;
+            if !config.read().await.shares.hash_files {
+                continue;
+            }
+            let index = config.read().await.index.clone();
+
+            for (file_id, path, size, modified_time) in
+                index.pending_hashes().await.unwrap_or_default()
+            {
+                let hashes = tokio::task::spawn_blocking(move || hash_file(&path)).await;
+                if let Ok(Ok((head_hash, hash))) = hashes {
+                    // the file may have been removed from the index in the meantime
+                    let _ = index
+                        .store_hash(file_id, size, modified_time, &head_hash, &hash)
+                        .await;
+                }
+            }
+        }
+    });
+}
+
+/// MD5s of a file's first chunk and of the whole file.
+/// The first chunk is the same size as the first one a download receives, so they can be compared.
+/// This reads the whole file, so shouldn't be run on an async task.
+fn hash_file(path: &Path) -> std::io::Result<(String, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut head_hash = None;
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    loop {
+        chunk.clear();
+        if (&mut file)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)?
+            == 0
+        {
+            break;
+        }
+        hasher.update(&chunk);
+        head_hash.get_or_insert_with(|| md5_digest(&chunk));
+    }
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok((head_hash.unwrap_or_else(|| md5_digest(&[])), hash))
+}
+
+/// Rebuilds the message sent to users browsing our shares, then lets everything else know the shares changed
+async fn refresh_shares(
+    index: &DiskIndex,
+    shares_message: &RwLock<Option<Vec<u8>>>,
+    write_queue: &Sender<SLSKEvents>,
+    error: Option<String>,
+) {
+    *shares_message.write().await = Some(match index.file_list().await {
+        Ok(file_list) => SharedFileListResponse::to_bytes(file_list),
+        Err(_) => Vec::new(),
+    });
+    let _ = write_queue.send(SLSKEvents::SharesUpdated {
+        shares: index.shared_roots().await.unwrap_or_default(),
+        error,
+    });
+}
@@ -2,15 +2,23 @@ use serde::{Deserialize, Serialize};
 use smol::block_on;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
 };
 
 use crate::{
+    analysis::{can_analyse, Verdict},
     messages::{Directory, File, FileAttribute, SharedFileListResponse},
+    parsers::{can_parse, encoder_id, read_cue},
     utils::file_is_hidden,
 };
 
+fn is_cue_sheet(filename: &str) -> bool {
+    Path::new(filename)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("cue"))
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(from = "DiskIndexDeser")]
 pub(crate) struct DiskIndex {
@@ -24,6 +32,91 @@ pub(crate) struct DiskIndex {
     alias_to_path: HashMap<String, PathBuf>, // alias -> real_path
 }
 
+/// A shared root folder along with how much of it is indexed
+#[derive(Debug, Clone)]
+pub(crate) struct SharedRoot {
+    pub(crate) path: PathBuf,
+    pub(crate) alias: String,
+    pub(crate) is_buddy_only: bool,
+    pub(crate) folders: u32,
+    pub(crate) files: u32,
+    /// Lossless files that look like they were transcoded from a lossy source
+    pub(crate) suspicious: u32,
+}
+
+/// A track inside an album image, as the CUE sheet next to it describes it
+#[derive(Debug, Clone)]
+pub(crate) struct ImageTrack {
+    pub(crate) number: u32,
+    pub(crate) title: Option<String>,
+    pub(crate) performer: Option<String>,
+    /// In seconds, the last track's is unknown if the image couldn't be parsed
+    pub(crate) duration: Option<f64>,
+}
+
+/// Totals for everything we share, worked out from the same file list users browsing us receive
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ShareStats {
+    pub(crate) folders: u32,
+    pub(crate) files: u32,
+    pub(crate) size: u64,
+    /// Extension, number of files and their total size, most common first
+    pub(crate) extensions: Vec<(String, u32, u64)>,
+    /// Files the parsers support, which should all have metadata
+    pub(crate) audio_files: u32,
+    pub(crate) with_metadata: u32,
+    /// Paths of the supported files that couldn't be parsed
+    pub(crate) unreadable: Vec<String>,
+    /// Groups of files with the same contents, filled in from the index since the file list doesn't have hashes
+    pub(crate) duplicates: Vec<Vec<String>>,
+    /// Paths of the files that look transcoded and what the spectrum said about them, also from the index
+    pub(crate) suspicious: HashMap<String, Verdict>,
+    /// The tracks in each album image with a CUE sheet, by the image's path, also from the index
+    pub(crate) image_tracks: HashMap<String, Vec<ImageTrack>>,
+}
+
+impl ShareStats {
+    pub(crate) fn new(file_list: &SharedFileListResponse) -> Self {
+        let mut stats = ShareStats::default();
+        let mut extensions: HashMap<String, (u32, u64)> = HashMap::new();
+
+        for directory in file_list
+            .directories
+            .iter()
+            .chain(&file_list.priv_directories)
+        {
+            stats.folders += 1;
+            for file in &directory.files {
+                stats.files += 1;
+                stats.size += file.file_size;
+                let extension = extensions.entry(file.extension.to_lowercase()).or_default();
+                extension.0 += 1;
+                extension.1 += file.file_size;
+
+                if can_parse(&file.extension) {
+                    stats.audio_files += 1;
+                    if file.attributes.is_empty() {
+                        stats
+                            .unreadable
+                            .push(format!("{}\\{}", directory.path, file.filename));
+                    } else {
+                        stats.with_metadata += 1;
+                    }
+                }
+            }
+        }
+
+        stats.extensions = extensions
+            .into_iter()
+            .map(|(extension, (files, size))| (extension, files, size))
+            .collect();
+        stats
+            .extensions
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        stats
+    }
+}
+
 #[derive(Deserialize)]
 struct DiskIndexDeser {
     save_dir: PathBuf,
@@ -99,7 +192,8 @@ impl DiskIndex {
             CREATE TABLE IF NOT EXISTS folders (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 alias TEXT UNIQUE NOT NULL,
-                is_buddy_only BOOLEAN NOT NULL DEFAULT 0
+                is_buddy_only BOOLEAN NOT NULL DEFAULT 0,
+                indexed_at INTEGER NOT NULL DEFAULT 0
             )
             "#,
         )
@@ -128,6 +222,8 @@ impl DiskIndex {
                 folder_id INTEGER NOT NULL,
                 filename TEXT NOT NULL,
                 modified_time INTEGER NOT NULL,
+                size INTEGER NOT NULL DEFAULT 0,
+                indexed_at INTEGER NOT NULL DEFAULT 0,
                 UNIQUE(folder_id, filename),
                 FOREIGN KEY (folder_id) REFERENCES folders(id) ON DELETE CASCADE
             )
@@ -146,7 +242,24 @@ impl DiskIndex {
                 vbr BOOLEAN,           -- is/is not VBR
                 sample_rate INTEGER,    -- Hz
                 bit_depth INTEGER,      -- bits
-                filesize INTEGER,
+                encoder TEXT,
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // File tags table - populated when files are indexed, so the tags can be searched
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS file_tags (
+                file_id INTEGER PRIMARY KEY,
+                artist TEXT,
+                album TEXT,
+                title TEXT,
+                track TEXT,
+                year TEXT,
                 FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
             )
             "#,
@@ -154,6 +267,56 @@ impl DiskIndex {
         .execute(&mut *tx)
         .await?;
 
+        // File analysis table - populated in the background for lossless files, see `analysis`
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS file_analysis (
+                file_id INTEGER PRIMARY KEY,
+                verdict INTEGER NOT NULL,
+                cutoff INTEGER,         -- Hz
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // File hashes table - populated in the background, the size and modified time say which version was hashed
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS file_hashes (
+                file_id INTEGER PRIMARY KEY,
+                size INTEGER NOT NULL,
+                modified_time INTEGER NOT NULL,
+                head_hash TEXT NOT NULL, -- MD5 of the first chunk, enough to recognise a download as it starts
+                hash TEXT NOT NULL,      -- MD5 of the whole file
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // CUE tracks table - the tracks inside audio files that have a CUE sheet next to them
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS cue_tracks (
+                file_id INTEGER NOT NULL,       -- the audio file
+                cue_file_id INTEGER NOT NULL,   -- the sheet describing it
+                track INTEGER NOT NULL,
+                title TEXT,
+                performer TEXT,
+                start REAL NOT NULL,    -- seconds
+                duration REAL,          -- seconds
+                PRIMARY KEY (file_id, track),
+                FOREIGN KEY (file_id) REFERENCES files(id) ON DELETE CASCADE,
+                FOREIGN KEY (cue_file_id) REFERENCES files(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+
         // Terms table for fast lookups
         sqlx::query(
             r#"
@@ -181,6 +344,24 @@ impl DiskIndex {
         .execute(&mut *tx)
         .await?;
 
+        // Databases created before `indexed_at` existed need it adding
+        Self::ensure_column(
+            &mut tx,
+            "folders",
+            "indexed_at",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+        Self::ensure_column(&mut tx, "files", "indexed_at", "INTEGER NOT NULL DEFAULT 0").await?;
+        // sizes start at 0, they're filled in the next time the root is indexed
+        Self::ensure_column(&mut tx, "files", "size", "INTEGER NOT NULL DEFAULT 0").await?;
+        // metadata cached before the encoder was read is thrown away, it gets parsed again on search
+        if Self::ensure_column(&mut tx, "file_metadata", "encoder", "TEXT").await? {
+            sqlx::query("DELETE FROM file_metadata")
+                .execute(&mut *tx)
+                .await?;
+        }
+
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_folders_alias ON folders(alias)")
             .execute(&mut *tx)
             .await?;
@@ -197,6 +378,14 @@ impl DiskIndex {
             .execute(&mut *tx)
             .await?;
 
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_files_size ON files(size)")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_file_hashes_hash ON file_hashes(hash)")
+            .execute(&mut *tx)
+            .await?;
+
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_terms_term ON terms(term)")
             .execute(&mut *tx)
             .await?;
@@ -213,6 +402,32 @@ impl DiskIndex {
         Ok(())
     }
 
+    /// Add a column to an existing table if it doesn't have it yet.
+    /// Returns whether the column was added.
+    async fn ensure_column(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let exists = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM pragma_table_info('{table}') WHERE name = ?"
+        ))
+        .bind(column)
+        .fetch_one(&mut **tx)
+        .await?
+            > 0;
+
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+            ))
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(!exists)
+    }
+
     /// Load existing folder mappings from database
     async fn load_folder_mappings(
         pool: &SqlitePool,
@@ -259,9 +474,9 @@ impl DiskIndex {
         let mut dir_path = None;
         let mut last_is_buddy_only = false;
 
-        let results = sqlx::query_as::<_, (Option<i64>, Option<i64>, Option<String>, String, bool, Option<u32>, Option<f64>, Option<bool>, Option<u32>, Option<u32>, Option<u64>)>(
+        let results = sqlx::query_as::<_, (Option<i64>, Option<i64>, Option<String>, Option<u64>, String, bool, Option<i64>, Option<u32>, Option<f64>, Option<bool>, Option<u32>, Option<u32>, Option<String>)>(
             r#"
-            SELECT f.id, folder_id, filename, alias, is_buddy_only, bitrate, duration, vbr, sample_rate, bit_depth, filesize
+            SELECT f.id, folder_id, filename, size, alias, is_buddy_only, fm.file_id, bitrate, duration, vbr, sample_rate, bit_depth, encoder
             FROM folders
             LEFT JOIN files f ON f.folder_id = folders.id
             LEFT JOIN file_metadata fm ON f.id = fm.file_id
@@ -275,14 +490,16 @@ impl DiskIndex {
             file_id,
             folder_id,
             filename,
+            file_size,
             alias,
             is_buddy_only,
+            metadata_id,
             bitrate,
             duration,
             vbr,
             sample_rate,
             bit_depth,
-            file_size,
+            encoder,
         ) in results
         {
             if file_id.is_none() {
@@ -295,23 +512,22 @@ impl DiskIndex {
             let file_id = file_id.unwrap();
             let folder_id = folder_id.unwrap();
             let filename = filename.unwrap();
-            let (file_size, attributes) = match file_size {
-                Some(file_size) => (
-                    file_size,
-                    FileAttribute::from_parts(
-                        bitrate,
-                        duration.map(|d| d.round() as u32),
-                        vbr,
-                        sample_rate,
-                        bit_depth,
-                    ),
+            let file_size = file_size.unwrap_or_default();
+            let attributes = match metadata_id {
+                Some(_) => FileAttribute::from_parts(
+                    bitrate,
+                    duration.map(|d| d.round() as u32),
+                    vbr,
+                    encoder.as_deref().and_then(encoder_id),
+                    sample_rate,
+                    bit_depth,
                 ),
-                None => match self.get_file_metadata(file_id).await {
-                    Ok(Some((attributes, file_size))) => {
-                        (file_size.unwrap_or_default(), attributes)
-                    }
-                    _ => (0, Vec::new()),
-                },
+                None => self
+                    .get_file_metadata(file_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default(),
             };
             if dir_path.is_none() {
                 dir_path = Some(alias.clone());
@@ -320,19 +536,19 @@ impl DiskIndex {
                 previous_id = Some(folder_id);
             } else if previous_id.unwrap() != folder_id {
                 previous_id = Some(folder_id);
-                if !dir_files.is_empty().clone() {
+                if !dir_files.is_empty() {
                     let dir = Directory {
                         path: dir_path.unwrap(),
                         files: dir_files,
                     };
-                    if is_buddy_only {
+                    if last_is_buddy_only {
                         priv_directories.push(dir);
                     } else {
                         directories.push(dir);
                     };
                     dir_files = Vec::new();
-                    dir_path = Some(alias);
                 }
+                dir_path = Some(alias);
             };
             let extension = filename
                 .rsplit_once('.')
@@ -348,15 +564,17 @@ impl DiskIndex {
             last_is_buddy_only = is_buddy_only;
         }
 
-        let last_dir = Directory {
-            path: dir_path.unwrap(),
-            files: dir_files,
-        };
+        if let Some(path) = dir_path {
+            let last_dir = Directory {
+                path,
+                files: dir_files,
+            };
 
-        if last_is_buddy_only {
-            priv_directories.push(last_dir);
-        } else {
-            directories.push(last_dir);
+            if last_is_buddy_only {
+                priv_directories.push(last_dir);
+            } else {
+                directories.push(last_dir);
+            }
         }
         let file_list = SharedFileListResponse {
             directories,
@@ -366,6 +584,58 @@ impl DiskIndex {
         Ok(file_list)
     }
 
+    /// The shared folder at `folder` and every folder under it, as a FolderContentsRequest is answered.
+    /// Buddy-only folders are left out unless `include_private` is set.
+    pub(crate) async fn folder_contents(
+        &self,
+        folder: &str,
+        include_private: bool,
+    ) -> Result<Vec<Directory>, sqlx::Error> {
+        let file_list = self.file_list().await?;
+        let prefix = format!("{folder}\\");
+        let private = if include_private {
+            file_list.priv_directories
+        } else {
+            Vec::new()
+        };
+        Ok(file_list
+            .directories
+            .into_iter()
+            .chain(private)
+            .filter(|dir| dir.path == folder || dir.path.starts_with(&prefix))
+            .collect())
+    }
+
+    /// Where a shared file really is, its size, and whether it's only shared with buddies.
+    /// `aliased` is the path other users see, only files in the index are found.
+    pub(crate) async fn shared_file(
+        &self,
+        aliased: &str,
+    ) -> Result<Option<(PathBuf, u64, bool)>, sqlx::Error> {
+        let Some((folder_alias, filename)) = aliased.rsplit_once('\\') else {
+            return Ok(None);
+        };
+        let row = sqlx::query_as::<_, (u64, bool)>(
+            r#"
+            SELECT f.size, fo.is_buddy_only
+            FROM files f
+            JOIN folders fo ON f.folder_id = fo.id
+            WHERE fo.alias = ? AND f.filename = ?
+            "#,
+        )
+        .bind(folder_alias)
+        .bind(filename)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|(size, is_buddy_only)| {
+            (
+                self.alias_components_to_path(folder_alias, filename),
+                size,
+                is_buddy_only,
+            )
+        }))
+    }
+
     pub(crate) async fn aliased_to_real(
         &self,
         aliased: &str,
@@ -406,7 +676,7 @@ impl DiskIndex {
 
         let sql = format!(
             r#"
-            SELECT DISTINCT f.id, f.filename, fo.alias, fo.is_buddy_only
+            SELECT DISTINCT f.id, f.filename, f.size, fo.alias, fo.is_buddy_only
             FROM files f
             JOIN folders fo ON f.folder_id = fo.id
             WHERE f.id IN (
@@ -422,7 +692,7 @@ impl DiskIndex {
             in_clause
         );
 
-        let mut query = sqlx::query_as::<_, (i64, String, String, bool)>(&sql);
+        let mut query = sqlx::query_as::<_, (i64, String, u64, String, bool)>(&sql);
 
         for term in &terms {
             query = query.bind(term);
@@ -433,18 +703,15 @@ impl DiskIndex {
         let mut files = Vec::new();
         let mut private_files = Vec::new();
 
-        for (file_id, filename, folder_alias, is_buddy_only) in rows {
+        for (file_id, filename, file_size, folder_alias, is_buddy_only) in rows {
             // Get metadata if it exists
-            let (attributes, file_size) = self
-                .get_file_metadata(file_id)
-                .await?
-                .unwrap_or((Vec::new(), None));
+            let attributes = self.get_file_metadata(file_id).await?.unwrap_or_default();
             let full_path = self.alias_components_to_path(&folder_alias, &filename);
 
             let file = File {
                 code: 1,
                 filename: format!("{folder_alias}\\{filename}"),
-                file_size: file_size.unwrap_or_default(),
+                file_size,
                 extension: filename
                     .rsplit_once('.')
                     .map(|(_, ext)| ext.to_string())
@@ -463,33 +730,32 @@ impl DiskIndex {
     }
 
     fn alias_components_to_path(&self, folder_alias: &str, filename: &str) -> PathBuf {
-        let (alias_root, folder) = folder_alias.split_once("\\").unwrap();
-        self.alias_to_path[alias_root].join(folder).join(&filename)
+        // files directly inside a root folder have no subfolder component
+        let (alias_root, folder) = folder_alias.split_once('\\').unwrap_or((folder_alias, ""));
+        self.alias_to_path[alias_root].join(folder).join(filename)
     }
 
     /// Get metadata for a specific file
     async fn get_file_metadata(
         &self,
         file_id: i64,
-    ) -> Result<Option<(Vec<FileAttribute>, Option<u64>)>, sqlx::Error> {
-        let row = sqlx::query_as::<_, (Option<u32>, Option<f64>, Option<bool>, Option<u32>, Option<u32>, Option<u64>)>(
-            "SELECT bitrate, duration, vbr, sample_rate, bit_depth, filesize FROM file_metadata WHERE file_id = ?"
+    ) -> Result<Option<Vec<FileAttribute>>, sqlx::Error> {
+        let row = sqlx::query_as::<_, (Option<u32>, Option<f64>, Option<bool>, Option<u32>, Option<u32>, Option<String>)>(
+            "SELECT bitrate, duration, vbr, sample_rate, bit_depth, encoder FROM file_metadata WHERE file_id = ?"
         )
         .bind(file_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some((bitrate, duration, vbr, sample_rate, bit_depth, filesize)) = row {
-            return Ok(Some((
-                FileAttribute::from_parts(
-                    bitrate,
-                    duration.map(|d| d.round() as u32),
-                    vbr,
-                    sample_rate,
-                    bit_depth,
-                ),
-                filesize,
-            )));
+        if let Some((bitrate, duration, vbr, sample_rate, bit_depth, encoder)) = row {
+            Ok(Some(FileAttribute::from_parts(
+                bitrate,
+                duration.map(|d| d.round() as u32),
+                vbr,
+                encoder.as_deref().and_then(encoder_id),
+                sample_rate,
+                bit_depth,
+            )))
         } else {
             if let Ok((filename, folder_alias)) = sqlx::query_as::<_, (String, String)>(
                 "SELECT files.filename, folder.alias
@@ -508,7 +774,7 @@ impl DiskIndex {
                     let vbr = parsed.is_vbr();
                     let sample_rate = parsed.sample_rate();
                     let bit_depth = parsed.bit_depth().map(|bd| bd as u32);
-                    let filesize = path.metadata().map(|m| m.len()).ok();
+                    let encoder = parsed.encoder();
 
                     let _ = self
                         .store_file_metadata(
@@ -518,20 +784,19 @@ impl DiskIndex {
                             vbr,
                             sample_rate,
                             bit_depth,
-                            filesize,
+                            encoder.as_deref(),
                         )
                         .await;
 
-                    let mut attrs = vec![
-                        FileAttribute::Bitrate(bitrate),
-                        FileAttribute::Duration(duration.round() as u32),
-                        FileAttribute::VBR(vbr),
-                        FileAttribute::SampleRate(sample_rate),
-                    ];
-                    if let Some(bit_depth) = bit_depth {
-                        attrs.push(FileAttribute::BitDepth(bit_depth));
-                    };
-                    return Ok(Some((attrs, filesize)));
+                    let attrs = FileAttribute::from_parts(
+                        Some(bitrate),
+                        Some(duration.round() as u32),
+                        Some(vbr),
+                        encoder.as_deref().and_then(encoder_id),
+                        Some(sample_rate),
+                        bit_depth,
+                    );
+                    return Ok(Some(attrs));
                 }
             };
             Ok(None)
@@ -547,12 +812,12 @@ impl DiskIndex {
         vbr: bool,
         sample_rate: u32,
         bit_depth: Option<u32>,
-        filesize: Option<u64>,
+        encoder: Option<&str>,
     ) -> Result<(), sqlx::Error> {
         sqlx::query(
             r#"
             INSERT OR REPLACE INTO file_metadata 
-            (file_id, bitrate, duration, vbr, sample_rate, bit_depth, filesize)
+            (file_id, bitrate, duration, vbr, sample_rate, bit_depth, encoder)
             VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
@@ -562,13 +827,253 @@ impl DiskIndex {
         .bind(vbr)
         .bind(sample_rate)
         .bind(bit_depth)
-        .bind(filesize.map(|fs| fs as i64))
+        .bind(encoder)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Get the files that `analysis` supports but hasn't looked at yet
+    pub(crate) async fn pending_analysis(&self) -> Result<Vec<(i64, PathBuf)>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (i64, String, String)>(
+            r#"
+            SELECT f.id, fo.alias, f.filename
+            FROM files f
+            JOIN folders fo ON f.folder_id = fo.id
+            LEFT JOIN file_analysis a ON a.file_id = f.id
+            WHERE a.file_id IS NULL
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(_, _, filename)| can_analyse(Path::new(filename)))
+            // the root may have been removed since the query ran
+            .filter(|(_, alias, _)| {
+                let alias_root = alias
+                    .split_once('\\')
+                    .map_or(alias.as_str(), |(root, _)| root);
+                self.alias_to_path.contains_key(alias_root)
+            })
+            .map(|(file_id, alias, filename)| {
+                (file_id, self.alias_components_to_path(&alias, &filename))
+            })
+            .collect())
+    }
+
+    /// Store the result of analysing a file
+    pub(crate) async fn store_analysis(
+        &self,
+        file_id: i64,
+        verdict: Verdict,
+    ) -> Result<(), sqlx::Error> {
+        let (verdict, cutoff) = verdict.to_parts();
+        sqlx::query(
+            "INSERT OR REPLACE INTO file_analysis (file_id, verdict, cutoff) VALUES (?, ?, ?)",
+        )
+        .bind(file_id)
+        .bind(verdict)
+        .bind(cutoff)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the paths of the files whose analysis found them suspicious
+    pub(crate) async fn suspicious_files(&self) -> Result<HashMap<String, Verdict>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, String, i64)>(
+            r#"
+            SELECT fo.alias, f.filename, a.cutoff
+            FROM file_analysis a
+            JOIN files f ON f.id = a.file_id
+            JOIN folders fo ON f.folder_id = fo.id
+            WHERE a.verdict = 1
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(alias, filename, cutoff)| {
+                (
+                    format!("{alias}\\{filename}"),
+                    Verdict::Suspicious {
+                        cutoff: cutoff as u32,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Get the tracks of every album image that has a CUE sheet, in order
+    pub(crate) async fn image_tracks(
+        &self,
+    ) -> Result<HashMap<String, Vec<ImageTrack>>, sqlx::Error> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                u32,
+                Option<String>,
+                Option<String>,
+                Option<f64>,
+            ),
+        >(
+            r#"
+            SELECT fo.alias, f.filename, t.track, t.title, t.performer, t.duration
+            FROM cue_tracks t
+            JOIN files f ON f.id = t.file_id
+            JOIN folders fo ON f.folder_id = fo.id
+            ORDER BY t.file_id, t.track
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut images: HashMap<String, Vec<ImageTrack>> = HashMap::new();
+        for (alias, filename, number, title, performer, duration) in rows {
+            images
+                .entry(format!("{alias}\\{filename}"))
+                .or_default()
+                .push(ImageTrack {
+                    number,
+                    title,
+                    performer,
+                    duration,
+                });
+        }
+        Ok(images)
+    }
+
+    /// Get the files that haven't been hashed, or have changed since they were, with their size and modified time.
+    /// Empty files are skipped, they'd all be duplicates of each other.
+    pub(crate) async fn pending_hashes(
+        &self,
+    ) -> Result<Vec<(i64, PathBuf, i64, i64)>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (i64, String, String, i64, i64)>(
+            r#"
+            SELECT f.id, fo.alias, f.filename, f.size, f.modified_time
+            FROM files f
+            JOIN folders fo ON f.folder_id = fo.id
+            LEFT JOIN file_hashes h ON h.file_id = f.id
+            WHERE f.size > 0
+                AND (h.file_id IS NULL OR h.size != f.size OR h.modified_time != f.modified_time)
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            // the root may have been removed since the query ran
+            .filter(|(_, alias, _, _, _)| {
+                let alias_root = alias
+                    .split_once('\\')
+                    .map_or(alias.as_str(), |(root, _)| root);
+                self.alias_to_path.contains_key(alias_root)
+            })
+            .map(|(file_id, alias, filename, size, modified_time)| {
+                (
+                    file_id,
+                    self.alias_components_to_path(&alias, &filename),
+                    size,
+                    modified_time,
+                )
+            })
+            .collect())
+    }
+
+    /// Store the hashes of a file, along with the size and modified time it had when it was read
+    pub(crate) async fn store_hash(
+        &self,
+        file_id: i64,
+        size: i64,
+        modified_time: i64,
+        head_hash: &str,
+        hash: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO file_hashes (file_id, size, modified_time, head_hash, hash)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(file_id)
+        .bind(size)
+        .bind(modified_time)
+        .bind(head_hash)
+        .bind(hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Groups of shared files with identical contents, by the paths users browsing us see
+    pub(crate) async fn duplicates(&self) -> Result<Vec<Vec<String>>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, String, String)>(
+            r#"
+            SELECT h.hash, fo.alias, f.filename
+            FROM file_hashes h
+            JOIN files f ON f.id = h.file_id
+            JOIN folders fo ON f.folder_id = fo.id
+            WHERE h.size = f.size AND h.modified_time = f.modified_time
+                AND h.hash IN (SELECT hash FROM file_hashes GROUP BY hash HAVING COUNT(*) > 1)
+            ORDER BY h.hash, LOWER(fo.alias), LOWER(f.filename)
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        for (hash, alias, filename) in rows {
+            let path = format!("{alias}\\{filename}");
+            match groups.last_mut() {
+                Some((group_hash, paths)) if *group_hash == hash => paths.push(path),
+                _ => groups.push((hash, vec![path])),
+            }
+        }
+        // a file whose hash is out of date doesn't count
+        Ok(groups
+            .into_iter()
+            .map(|(_, paths)| paths)
+            .filter(|paths| paths.len() > 1)
+            .collect())
+    }
+
+    /// The shared files with the given size, and if `head_hash` is given, the same first chunk
+    pub(crate) async fn local_copies(
+        &self,
+        size: u64,
+        head_hash: Option<&str>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT fo.alias, f.filename
+            FROM files f
+            JOIN folders fo ON f.folder_id = fo.id
+            LEFT JOIN file_hashes h ON h.file_id = f.id AND h.modified_time = f.modified_time
+            WHERE f.size = ?1 AND (?2 IS NULL OR h.head_hash = ?2)
+            ORDER BY LOWER(fo.alias), LOWER(f.filename)
+            "#,
+        )
+        .bind(size as i64)
+        .bind(head_hash)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(alias, filename)| format!("{alias}\\{filename}"))
+            .collect())
+    }
+
     /// Get the total number of files
     pub(crate) async fn get_total_file_count(&self) -> Result<u32, sqlx::Error> {
         let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM files")
@@ -610,6 +1115,16 @@ impl DiskIndex {
             .into());
         }
 
+        // aliases are joined with backslashes when sent to other users
+        if alias.is_empty() || alias.contains('\\') {
+            return Err(format!("Invalid alias: {alias:?}").into());
+        }
+        if let Some(existing) = self.alias_to_path.get(alias) {
+            if existing != folder_path {
+                return Err(format!("Alias {alias:?} is already used by {existing:?}").into());
+            }
+        }
+
         // Start a transaction for the entire indexing operation
         let mut tx = self.pool.begin().await?;
 
@@ -661,6 +1176,15 @@ impl DiskIndex {
             let parent_dir = file_path.parent().unwrap_or(folder_path);
 
             if entry.file_type().is_file() {
+                let metadata = entry.metadata().ok();
+                let modified_time = metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.modified().ok())
+                    .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                    .unwrap_or_default()
+                    .as_secs() as i64;
+                let size = metadata.map(|metadata| metadata.len()).unwrap_or_default() as i64;
+
                 // Determine which folder this file belongs to
                 let relative_parent = parent_dir
                     .strip_prefix(folder_path)
@@ -674,18 +1198,23 @@ impl DiskIndex {
                 files_to_insert.push((
                     file_folder_path,
                     entry.file_name().to_string_lossy().to_string(),
+                    modified_time,
+                    size,
                     true,
                 ));
                 // }
             } else {
-                files_to_insert.push((entry.path().to_path_buf(), String::new(), false));
+                files_to_insert.push((entry.path().to_path_buf(), String::new(), 0, 0, false));
             }
         }
 
         // Group files by their parent folder and ensure all folders exist
         let mut folder_cache: HashMap<PathBuf, i64> = HashMap::new();
+        // files that are new or have changed since they were last indexed
+        let mut changed_files = HashSet::new();
+        let mut cue_sheets = Vec::new();
 
-        for (file_folder_path, filename, is_file) in files_to_insert {
+        for (file_folder_path, filename, modified_time, size, is_file) in files_to_insert {
             // Create subfolder entry if it doesn't exist
             let relative_path = file_folder_path
                 .strip_prefix(folder_path)
@@ -703,14 +1232,15 @@ impl DiskIndex {
                     r#"
                     INSERT INTO folders (alias, is_buddy_only, indexed_at)
                     VALUES (?, ?, ?)
-                    ON CONFLICT(alias) DO UPDATE SET is_buddy_only = is_buddy_only, indexed_at = ?
+                    ON CONFLICT(alias) DO UPDATE SET
+                        is_buddy_only = excluded.is_buddy_only,
+                        indexed_at = excluded.indexed_at
                     RETURNING id
                     "#,
                 )
                 .bind(&subfolder_alias)
                 .bind(is_buddy_only)
                 .bind(indexed_at)
-                .bind(indexed_at)
                 .fetch_one(&mut *tx)
                 .await?;
 
@@ -722,57 +1252,132 @@ impl DiskIndex {
                 subfolder_id
             };
 
-            // Insert the file
-            let file_id = match sqlx::query_scalar::<_, i64>(
+            let file_path = file_folder_path.join(&filename);
+            let aliased_path = format!("{subfolder_alias}\\{filename}");
+
+            let existing = sqlx::query_as::<_, (i64, i64, bool)>(
                 r#"
-                INSERT OR IGNORE INTO files (folder_id, filename, indexed_at)
-                VALUES (?, ?, ?)
-                RETURNING id
+                SELECT f.id, f.modified_time, t.file_id IS NOT NULL
+                FROM files f
+                LEFT JOIN file_tags t ON t.file_id = f.id
+                WHERE f.folder_id = ? AND f.filename = ?
                 "#,
             )
             .bind(current_folder_id)
             .bind(&filename)
-            .bind(indexed_at)
-            .fetch_one(&mut *tx)
-            .await
-            {
-                Ok(file_id) => file_id,
-                Err(_) => {
-                    sqlx::query(
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let file_id = match existing {
+                None => {
+                    // Insert the file
+                    let file_id = sqlx::query_scalar::<_, i64>(
                         r#"
-                        UPDATE files
-                        SET indexed_at = ?
-                        WHERE folder_id = ?
+                        INSERT INTO files (folder_id, filename, modified_time, size, indexed_at)
+                        VALUES (?, ?, ?, ?, ?)
+                        RETURNING id
                         "#,
                     )
-                    .bind(indexed_at)
                     .bind(current_folder_id)
+                    .bind(&filename)
+                    .bind(modified_time)
+                    .bind(size)
+                    .bind(indexed_at)
+                    .fetch_one(&mut *tx)
+                    .await?;
+
+                    // Extract and index terms from full path
+                    // doing this for folders is pointless because we only want to return files
+                    self.index_file_terms(&mut tx, file_id, &aliased_path)
+                        .await?;
+                    self.index_file_tags(&mut tx, file_id, &file_path).await?;
+                    changed_files.insert(file_id);
+                    file_id
+                }
+                Some((file_id, previous_modified_time, has_tags)) => {
+                    // the size is always written, databases from before it was stored have 0 for everything
+                    sqlx::query(
+                        "UPDATE files SET indexed_at = ?, modified_time = ?, size = ? WHERE id = ?",
+                    )
+                    .bind(indexed_at)
+                    .bind(modified_time)
+                    .bind(size)
+                    .bind(file_id)
                     .execute(&mut *tx)
                     .await?;
-                    continue;
+
+                    if previous_modified_time != modified_time {
+                        // anything read from the file before it changed is out of date
+                        sqlx::query("DELETE FROM file_metadata WHERE file_id = ?")
+                            .bind(file_id)
+                            .execute(&mut *tx)
+                            .await?;
+                        sqlx::query("DELETE FROM file_terms WHERE file_id = ?")
+                            .bind(file_id)
+                            .execute(&mut *tx)
+                            .await?;
+                        sqlx::query("DELETE FROM file_analysis WHERE file_id = ?")
+                            .bind(file_id)
+                            .execute(&mut *tx)
+                            .await?;
+                        self.index_file_terms(&mut tx, file_id, &aliased_path)
+                            .await?;
+                        self.index_file_tags(&mut tx, file_id, &file_path).await?;
+                        changed_files.insert(file_id);
+                    } else if !has_tags {
+                        // files indexed before tags were read
+                        self.index_file_tags(&mut tx, file_id, &file_path).await?;
+                    }
+                    file_id
                 }
             };
 
-            // Extract and index terms from full path
-            // doing this for folders is pointless because we only want to return files
-            if is_file {
-                self.index_file_terms(&mut tx, file_id, &format!("{subfolder_alias}\\{filename}"))
-                    .await?;
+            if is_cue_sheet(&filename) {
+                cue_sheets.push((file_id, current_folder_id, file_path, subfolder_alias));
             }
         }
 
+        // sheets are linked once everything is indexed, so the audio files they describe have IDs
+        for (cue_id, folder_id, cue_path, folder_alias) in cue_sheets {
+            self.index_cue_sheet(
+                &mut tx,
+                cue_id,
+                folder_id,
+                &cue_path,
+                &folder_alias,
+                &changed_files,
+            )
+            .await?;
+        }
+
         tx.commit().await?;
 
         let mut tx = self.pool.begin().await?;
 
-        // delete folders that no longer exist, CASCADE will remove files and terms
+        // delete folders and files under this root that no longer exist, CASCADE will remove terms
         sqlx::query(
             r#"
             DELETE FROM folders
-            WHERE indexed_at < ?
+            WHERE indexed_at < ? AND (alias = ? OR instr(alias, ?) = 1)
             "#,
         )
         .bind(indexed_at)
+        .bind(alias)
+        .bind(format!("{alias}\\"))
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM files
+            WHERE indexed_at < ? AND folder_id IN (
+                SELECT id FROM folders WHERE alias = ? OR instr(alias, ?) = 1
+            )
+            "#,
+        )
+        .bind(indexed_at)
+        .bind(alias)
+        .bind(format!("{alias}\\"))
         .execute(&mut *tx)
         .await?;
 
@@ -783,6 +1388,18 @@ impl DiskIndex {
             .insert(folder_path.to_path_buf(), alias.to_string());
         self.alias_to_path
             .insert(alias.to_string(), folder_path.to_path_buf());
+        match self
+            .root_folders
+            .iter_mut()
+            .find(|(path, _, _)| path == folder_path)
+        {
+            Some(root) => *root = (folder_path.to_path_buf(), alias.to_string(), is_buddy_only),
+            None => self.root_folders.push((
+                folder_path.to_path_buf(),
+                alias.to_string(),
+                is_buddy_only,
+            )),
+        }
 
         Ok(())
     }
@@ -834,6 +1451,145 @@ impl DiskIndex {
         Ok(())
     }
 
+    /// Read a file's tags (if it's a format with a tag reader), storing them and indexing them as search terms.
+    /// Files with unreadable tags still get a row, so they aren't read again until they change.
+    async fn index_file_tags(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        file_id: i64,
+        path: &Path,
+    ) -> Result<(), sqlx::Error> {
+        let tags = match crate::parsers::read_tags(path) {
+            Some(tags) => tags.unwrap_or_default(),
+            None => return Ok(()),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO file_tags (file_id, artist, album, title, track, year)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(file_id)
+        .bind(&tags.artist)
+        .bind(&tags.album)
+        .bind(&tags.title)
+        .bind(&tags.track)
+        .bind(&tags.year)
+        .execute(&mut **tx)
+        .await?;
+
+        if !tags.is_empty() {
+            self.index_file_terms(tx, file_id, &tags.values().collect::<Vec<_>>().join(" "))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Link a CUE sheet to the audio files it describes, storing their tracks
+    /// and indexing the track titles and performers as search terms for the audio file.
+    /// The tracks are only stored again when the sheet, or the audio file it describes, changes.
+    async fn index_cue_sheet(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        cue_id: i64,
+        folder_id: i64,
+        cue_path: &Path,
+        folder_alias: &str,
+        changed_files: &HashSet<i64>,
+    ) -> Result<(), sqlx::Error> {
+        let sheet = match read_cue(cue_path) {
+            Ok(sheet) => sheet,
+            Err(_) => return Ok(()),
+        };
+        let folder_path = cue_path.parent().unwrap_or(Path::new(""));
+        let folder_files = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, filename FROM files WHERE folder_id = ?",
+        )
+        .bind(folder_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for cue_file in sheet.files.iter().filter(|file| !file.tracks.is_empty()) {
+            // sheets often name a different format to the one that's shared (e.g. a .wav that was compressed to .flac),
+            // so fall back to any file with the same name apart from the extension
+            let stem = Path::new(&cue_file.name).with_extension("");
+            let audio_file = folder_files
+                .iter()
+                .find(|(_, filename)| filename.eq_ignore_ascii_case(&cue_file.name))
+                .or_else(|| {
+                    folder_files.iter().find(|(_, filename)| {
+                        !is_cue_sheet(filename)
+                            && Path::new(filename).with_extension("") == stem
+                            && crate::parsers::parse(&folder_path.join(filename)).is_some()
+                    })
+                });
+            let Some((audio_id, audio_filename)) = audio_file else {
+                continue;
+            };
+
+            let linked = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM cue_tracks WHERE file_id = ? AND cue_file_id = ?",
+            )
+            .bind(audio_id)
+            .bind(cue_id)
+            .fetch_one(&mut **tx)
+            .await?
+                > 0;
+            if linked && !changed_files.contains(&cue_id) && !changed_files.contains(audio_id) {
+                continue;
+            }
+
+            // start the audio file's terms again, so terms from an older version of the sheet don't stick around
+            let audio_path = folder_path.join(audio_filename);
+            sqlx::query("DELETE FROM cue_tracks WHERE file_id = ?")
+                .bind(audio_id)
+                .execute(&mut **tx)
+                .await?;
+            sqlx::query("DELETE FROM file_terms WHERE file_id = ?")
+                .bind(audio_id)
+                .execute(&mut **tx)
+                .await?;
+            self.index_file_terms(tx, *audio_id, &format!("{folder_alias}\\{audio_filename}"))
+                .await?;
+            self.index_file_tags(tx, *audio_id, &audio_path).await?;
+
+            let file_duration = crate::parsers::parse(&audio_path)
+                .and_then(|metadata| metadata.ok())
+                .map(|metadata| metadata.duration());
+            let durations = cue_file.track_durations(file_duration);
+            for (track, duration) in cue_file.tracks.iter().zip(durations) {
+                sqlx::query(
+                    r#"
+                    INSERT OR REPLACE INTO cue_tracks
+                    (file_id, cue_file_id, track, title, performer, start, duration)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(audio_id)
+                .bind(cue_id)
+                .bind(track.number)
+                .bind(&track.title)
+                .bind(&track.performer)
+                .bind(track.start)
+                .bind(duration)
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            let values = cue_file
+                .tracks
+                .iter()
+                .flat_map(|track| track.values())
+                .chain(sheet.title.as_deref())
+                .collect::<Vec<_>>();
+            self.index_file_terms(tx, *audio_id, &values.join(" "))
+                .await?;
+        }
+
+        Ok(())
+    }
+
     /// Re-index all known folders (useful for updates)
     pub(crate) async fn reindex_all(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Get all folders from database
@@ -864,8 +1620,14 @@ impl DiskIndex {
         // Get the folder path before deletion
         let folder_path = self.alias_to_path.get(alias).cloned();
 
-        // Delete the folder (CASCADE will handle files and terms)
-        sqlx::query("DELETE FROM folders WHERE alias = ?")
+        // Delete the folder and its subfolders (CASCADE will handle files and terms)
+        sqlx::query("DELETE FROM folders WHERE alias = ? OR instr(alias, ?) = 1")
+            .bind(alias)
+            .bind(format!("{alias}\\"))
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM root_folders WHERE alias = ?")
             .bind(alias)
             .execute(&mut *tx)
             .await?;
@@ -887,7 +1649,112 @@ impl DiskIndex {
             self.folder_aliases.remove(&path);
         }
         self.alias_to_path.remove(alias);
+        self.root_folders
+            .retain(|(_, root_alias, _)| root_alias != alias);
+
+        Ok(())
+    }
+
+    /// Give a shared folder a new alias, re-indexing it under the new name
+    pub(crate) async fn rename_folder(
+        &mut self,
+        alias: &str,
+        new_alias: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (path, _, is_buddy_only) = self
+            .root_folders
+            .iter()
+            .find(|(_, root_alias, _)| root_alias == alias)
+            .cloned()
+            .ok_or_else(|| format!("No shared folder with alias {alias:?}"))?;
+        if alias == new_alias {
+            return Ok(());
+        }
+        if self.alias_to_path.contains_key(new_alias) {
+            return Err(format!("Alias {new_alias:?} is already in use").into());
+        }
+
+        // the alias is part of every indexed path, so the terms have to be rebuilt
+        self.remove_folder(alias).await?;
+        self.index_folder(path, new_alias, is_buddy_only).await
+    }
+
+    /// Change whether a shared folder (and everything in it) is only visible to buddies
+    pub(crate) async fn set_buddy_only(
+        &mut self,
+        alias: &str,
+        is_buddy_only: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let root = self
+            .root_folders
+            .iter_mut()
+            .find(|(_, root_alias, _)| root_alias == alias)
+            .ok_or_else(|| format!("No shared folder with alias {alias:?}"))?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE root_folders SET is_buddy_only = ? WHERE alias = ?")
+            .bind(is_buddy_only)
+            .bind(alias)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE folders SET is_buddy_only = ? WHERE alias = ? OR instr(alias, ?) = 1")
+            .bind(is_buddy_only)
+            .bind(alias)
+            .bind(format!("{alias}\\"))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        root.2 = is_buddy_only;
 
         Ok(())
     }
+
+    /// Re-index a single shared folder
+    pub(crate) async fn rescan_folder(
+        &mut self,
+        alias: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (path, _, is_buddy_only) = self
+            .root_folders
+            .iter()
+            .find(|(_, root_alias, _)| root_alias == alias)
+            .cloned()
+            .ok_or_else(|| format!("No shared folder with alias {alias:?}"))?;
+        self.index_folder(path, alias, is_buddy_only).await
+    }
+
+    /// Get every shared root folder along with its folder and file counts
+    pub(crate) async fn shared_roots(&self) -> Result<Vec<SharedRoot>, sqlx::Error> {
+        let mut roots = Vec::with_capacity(self.root_folders.len());
+
+        for (path, alias, is_buddy_only) in &self.root_folders {
+            let (folders, files, suspicious) = sqlx::query_as::<_, (i64, i64, i64)>(
+                r#"
+                SELECT COUNT(DISTINCT fo.id), COUNT(f.id), COUNT(a.file_id)
+                FROM folders fo
+                LEFT JOIN files f ON f.folder_id = fo.id
+                LEFT JOIN file_analysis a ON a.file_id = f.id AND a.verdict = 1
+                WHERE fo.alias = ? OR instr(fo.alias, ?) = 1
+                "#,
+            )
+            .bind(alias)
+            .bind(format!("{alias}\\"))
+            .fetch_one(&self.pool)
+            .await?;
+
+            roots.push(SharedRoot {
+                path: path.clone(),
+                alias: alias.clone(),
+                is_buddy_only: *is_buddy_only,
+                folders: folders as u32,
+                files: files as u32,
+                suspicious: suspicious as u32,
+            });
+        }
+
+        Ok(roots)
+    }
 }
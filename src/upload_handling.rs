@@ -0,0 +1,255 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+use crate::config::Config;
+use crate::constants::{
+    ConnectionTypes, Percentage, TransferDirections, UploadStatus, UPLOAD_SLOTS,
+};
+use crate::events::SLSKEvents;
+use crate::messages::{FileSearchResponse, MessageTrait, TransferRequest};
+
+/// How long a user has to accept an upload and connect for it before their slot goes to someone else, in seconds
+const UPLOAD_START_TIME: u64 = 60;
+
+/// The most files we send back for one search, the official clients don't show more than this anyway
+const MAX_SEARCH_RESULTS: usize = 300;
+
+/// A file another user has queued from us
+#[derive(Debug, Clone)]
+pub(crate) struct Upload {
+    pub(crate) username: String,
+    /// The path the user asked for, as they see it in our shares
+    pub(crate) filename: String,
+    /// Where the file really is
+    pub(crate) path: PathBuf,
+    pub(crate) filesize: u64,
+    pub(crate) status: Arc<RwLock<UploadStatus>>,
+    pub(crate) percentage: Arc<RwLock<Percentage>>,
+}
+
+/// Uploads the user has accepted, waiting for their file connection, by transfer token
+pub(crate) type UploadMap = Arc<Mutex<HashMap<u32, Upload>>>;
+
+/// Keeps the queue of files other users have asked for, and offers them with a TransferRequest as slots free up.
+/// Privileged users are moved to the front of the queue, as the official clients do.
+/// Once an offer is accepted, a file connection is made with the same token and the peer task sends the file.
+/// Searches are answered here too, since the results say whether a slot is free and how long the queue is.
+pub(crate) async fn start_upload_task(
+    config: Arc<RwLock<Config>>,
+    upload_map: UploadMap,
+    mut read_queue: Receiver<SLSKEvents>,
+    write_queue: Sender<SLSKEvents>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut queue: VecDeque<Upload> = VecDeque::new();
+        let mut privileged_users: HashSet<String> = HashSet::new();
+        // uploads that have been offered or are being sent, and when they were offered
+        let mut active: HashMap<u32, (Upload, Instant)> = HashMap::new();
+        // our average upload speed, as the server last reported it
+        let mut avg_speed = 0;
+        let mut expiry = interval(Duration::from_secs(5));
+
+        loop {
+            let event = tokio::select! {
+                event = read_queue.recv() => match event {
+                    Ok(event) => Some(event),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = expiry.tick() => None,
+            };
+
+            match event {
+                Some(SLSKEvents::UploadQueued {
+                    username,
+                    filename,
+                    path,
+                    filesize,
+                }) => {
+                    let already_queued = queue
+                        .iter()
+                        .chain(active.values().map(|(upload, _)| upload))
+                        .any(|upload| upload.username == username && upload.filename == filename);
+                    if !already_queued {
+                        let upload = Upload {
+                            username,
+                            filename,
+                            path,
+                            filesize,
+                            status: Arc::new(RwLock::new(UploadStatus::Queued)),
+                            percentage: Arc::new(RwLock::new(Percentage(0))),
+                        };
+                        let _ = write_queue.send(SLSKEvents::NewUpload {
+                            username: upload.username.clone(),
+                            filename: upload.filename.clone(),
+                            filesize: upload.filesize,
+                            status: Arc::clone(&upload.status),
+                            percentage: Arc::clone(&upload.percentage),
+                        });
+                        // after everyone privileged who's already waiting, if they're privileged too
+                        let position = if privileged_users.contains(&upload.username) {
+                            queue
+                                .iter()
+                                .position(|queued| !privileged_users.contains(&queued.username))
+                                .unwrap_or(queue.len())
+                        } else {
+                            queue.len()
+                        };
+                        queue.insert(position, upload);
+                    }
+                }
+                Some(SLSKEvents::PrivilegedUsers { usernames }) => {
+                    privileged_users = usernames.into_iter().collect();
+                    privileged_first(&mut queue, &privileged_users);
+                }
+                Some(SLSKEvents::PrivilegedUserAdded { username })
+                | Some(SLSKEvents::UserStatus {
+                    username,
+                    privileged: true,
+                    ..
+                }) => {
+                    if privileged_users.insert(username) {
+                        privileged_first(&mut queue, &privileged_users);
+                    }
+                }
+                Some(SLSKEvents::UploadResponse { token, allowed }) => {
+                    if let Some((upload, _)) = active.get(&token) {
+                        if allowed {
+                            upload_map.lock().await.insert(token, upload.clone());
+                            let _ = write_queue.send(SLSKEvents::Connect {
+                                username: upload.username.clone(),
+                                token,
+                                connection_type: ConnectionTypes::FileTransfer,
+                            });
+                        } else {
+                            *upload.status.write().await = UploadStatus::Failed;
+                            active.remove(&token);
+                        }
+                    }
+                }
+                Some(SLSKEvents::UploadFinished { token, .. }) => {
+                    active.remove(&token);
+                }
+                Some(SLSKEvents::UserStats { username, stats }) => {
+                    if username == config.read().await.user.name {
+                        avg_speed = stats.avg_speeds;
+                    }
+                }
+                Some(SLSKEvents::SearchRequest {
+                    username,
+                    token,
+                    query,
+                }) => {
+                    let (index, our_username, is_banned, is_buddy) = {
+                        let config = config.read().await;
+                        (
+                            config.index.clone(),
+                            config.user.name.clone(),
+                            config.users.banned.contains(&username),
+                            config.users.buddies.contains(&username),
+                        )
+                    };
+                    // banned users don't get results, and we don't answer our own searches
+                    if is_banned || username == our_username {
+                        continue;
+                    }
+                    let (files, private_files) = index.search(&query).await.unwrap_or_default();
+                    let files: Vec<_> = files
+                        .into_iter()
+                        .take(MAX_SEARCH_RESULTS)
+                        .map(|(file, _)| file)
+                        .collect();
+                    // only buddies see private files, with whatever room the public ones left
+                    let private_files: Vec<_> = if is_buddy {
+                        private_files
+                            .into_iter()
+                            .take(MAX_SEARCH_RESULTS - files.len())
+                            .map(|(file, _)| file)
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
+                    if files.is_empty() && private_files.is_empty() {
+                        continue;
+                    }
+                    let response = FileSearchResponse {
+                        username: our_username,
+                        token,
+                        files,
+                        slot_free: active.len() < UPLOAD_SLOTS,
+                        avg_speed,
+                        queue_length: queue.len() as u32,
+                        unknown_0: 0,
+                        private_files: Some(private_files),
+                    };
+                    let token = rand::random();
+                    let _ = write_queue.send(SLSKEvents::QueueMessage {
+                        token,
+                        message_bytes: FileSearchResponse::to_bytes(response),
+                    });
+                    let _ = write_queue.send(SLSKEvents::Connect {
+                        username,
+                        token,
+                        connection_type: ConnectionTypes::PeerToPeer,
+                    });
+                }
+                Some(_) => (),
+                None => {
+                    // users who never accept or connect lose their slot
+                    let mut expired = Vec::new();
+                    for (token, (upload, offered)) in &active {
+                        if offered.elapsed() > Duration::from_secs(UPLOAD_START_TIME)
+                            && *upload.status.read().await == UploadStatus::Starting
+                        {
+                            expired.push(*token);
+                        }
+                    }
+                    for token in expired {
+                        upload_map.lock().await.remove(&token);
+                        if let Some((upload, _)) = active.remove(&token) {
+                            *upload.status.write().await = UploadStatus::Failed;
+                        }
+                    }
+                }
+            }
+
+            while active.len() < UPLOAD_SLOTS {
+                let Some(upload) = queue.pop_front() else {
+                    break;
+                };
+                let token = rand::random();
+                *upload.status.write().await = UploadStatus::Starting;
+                let _ = write_queue.send(SLSKEvents::QueueMessage {
+                    token,
+                    message_bytes: TransferRequest::to_bytes(TransferRequest {
+                        direction: TransferDirections::UploadToPeer,
+                        token,
+                        filename: upload.filename.clone(),
+                        filesize: Some(upload.filesize),
+                    }),
+                });
+                let _ = write_queue.send(SLSKEvents::Connect {
+                    username: upload.username.clone(),
+                    token,
+                    connection_type: ConnectionTypes::PeerToPeer,
+                });
+                active.insert(token, (upload, Instant::now()));
+            }
+        }
+    })
+}
+
+/// Moves privileged users to the front of the queue, keeping everyone else in the order they queued in
+fn privileged_first(queue: &mut VecDeque<Upload>, privileged_users: &HashSet<String>) {
+    queue
+        .make_contiguous()
+        .sort_by_key(|upload| !privileged_users.contains(&upload.username));
+}
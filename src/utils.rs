@@ -196,6 +196,7 @@ pub(crate) fn default_results_table<'a>() -> TableWidget<'a> {
             String::from("Folder"),
             String::from("Filename"),
             String::from("Size"),
+            String::from("Encoder"),
         ],
         Vec::new(),
         Some(3..5),
@@ -206,6 +207,7 @@ pub(crate) fn default_results_table<'a>() -> TableWidget<'a> {
             Constraint::Fill(1), // folder
             Constraint::Fill(2), // filename
             Constraint::Max(10), // filesize
+            Constraint::Max(10), // encoder
         ]),
     )
 }
@@ -223,6 +225,48 @@ pub(crate) fn log<T: Into<String>>(text: T) {
     }
 }
 
+/// Completes a partially typed directory path as far as it's unambiguous.
+/// A separator is appended when exactly one directory matches.
+/// Returns `None` if no directories match.
+pub(crate) fn complete_directory(partial: &str) -> Option<String> {
+    let path = Path::new(partial);
+    let (parent, prefix) = if partial.is_empty() || partial.ends_with(std::path::is_separator) {
+        (path.to_path_buf(), String::new())
+    } else {
+        (
+            path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            path.file_name()?.to_string_lossy().to_string(),
+        )
+    };
+    let read_from = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent.as_path()
+    };
+
+    let matches: Vec<String> = std::fs::read_dir(read_from)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(&prefix) && (!prefix.is_empty() || !name.starts_with('.')))
+        .collect();
+
+    let first = matches.first()?;
+    let common: String = first
+        .chars()
+        .enumerate()
+        .take_while(|(i, c)| matches.iter().all(|name| name.chars().nth(*i) == Some(*c)))
+        .map(|(_, c)| c)
+        .collect();
+
+    let mut completed = parent.join(common).to_string_lossy().to_string();
+    if matches.len() == 1 {
+        completed.push(std::path::MAIN_SEPARATOR);
+    }
+    Some(completed)
+}
+
 pub(crate) fn file_is_hidden(path: &Path) -> bool {
     if path.file_name().unwrap().to_string_lossy().starts_with(".") {
         return true;